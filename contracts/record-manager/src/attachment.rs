@@ -0,0 +1,152 @@
+use cosmwasm_std::Binary;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+use crate::error::ContractError;
+
+pub const MAX_ATTACHMENTS: usize = 16;
+
+const URI_SCHEMES: [&str; 2] = ["ipfs://", "https://"];
+const SHA_256_LEN: usize = 32;
+
+/// File kept outside the contract, such as an ECG trace or an echocardiogram,
+/// pinned by its SHA-256 digest.
+#[derive(Serialize, Deserialize, JsonSchema, PartialEq, Eq, Debug, Clone)]
+#[serde(rename_all = "snake_case")]
+pub struct Attachment {
+    /// `ipfs://<cid>` or an institutional `https://` URL
+    pub uri: String,
+    pub sha256: Binary,
+    pub mime_type: String,
+    /// Size in bytes
+    pub size: u64,
+}
+
+impl Attachment {
+    pub fn validate(&self) -> Result<(), ContractError> {
+        if !URI_SCHEMES
+            .iter()
+            .any(|scheme| self.uri.len() > scheme.len() && self.uri.starts_with(scheme))
+        {
+            return Err(invalid(&self.uri, "uri must be an ipfs:// or https:// URI"));
+        }
+        if self.sha256.len() != SHA_256_LEN {
+            return Err(invalid(&self.uri, "sha256 must be 32 bytes"));
+        }
+        match self.mime_type.split_once('/') {
+            Some((kind, subtype)) if !kind.is_empty() && !subtype.is_empty() => {}
+            _ => return Err(invalid(&self.uri, "mime_type must be type/subtype")),
+        }
+        if self.size == 0 {
+            return Err(invalid(&self.uri, "size must be positive"));
+        }
+        Ok(())
+    }
+}
+
+/// Checks every attachment and that no URI is attached twice.
+pub fn validate_attachments(attachments: &[Attachment]) -> Result<(), ContractError> {
+    if attachments.len() > MAX_ATTACHMENTS {
+        return Err(ContractError::TooManyAttachments {
+            count: attachments.len(),
+            max: MAX_ATTACHMENTS,
+        });
+    }
+    for (i, attachment) in attachments.iter().enumerate() {
+        attachment.validate()?;
+        if attachments[..i]
+            .iter()
+            .any(|other| other.uri == attachment.uri)
+        {
+            return Err(invalid(&attachment.uri, "uri is attached twice"));
+        }
+    }
+    Ok(())
+}
+
+fn invalid(uri: &str, reason: &str) -> ContractError {
+    ContractError::InvalidAttachment {
+        uri: uri.to_string(),
+        reason: reason.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sample_attachment() -> Attachment {
+        Attachment {
+            uri: "ipfs://bafybeigdyrzt5sfp7udm7hu76uh7y26nf3efuylqabf3oclgtqy55fbzdi".to_string(),
+            sha256: Binary::from([5u8; 32]),
+            mime_type: "application/dicom".to_string(),
+            size: 1_048_576,
+        }
+    }
+
+    #[test]
+    fn validate_attachment() {
+        assert_eq!(sample_attachment().validate(), Ok(()));
+
+        let attachment = Attachment {
+            uri: "ftp://hospital.example/ecg.xml".to_string(),
+            ..sample_attachment()
+        };
+        assert!(attachment.validate().is_err());
+
+        let attachment = Attachment {
+            uri: "https://".to_string(),
+            ..sample_attachment()
+        };
+        assert!(attachment.validate().is_err());
+
+        let attachment = Attachment {
+            sha256: Binary::from([5u8; 20]),
+            ..sample_attachment()
+        };
+        assert_eq!(
+            attachment.validate(),
+            Err(invalid(&attachment.uri, "sha256 must be 32 bytes"))
+        );
+
+        let attachment = Attachment {
+            mime_type: "dicom".to_string(),
+            ..sample_attachment()
+        };
+        assert_eq!(
+            attachment.validate(),
+            Err(invalid(&attachment.uri, "mime_type must be type/subtype"))
+        );
+
+        let attachment = Attachment {
+            size: 0,
+            ..sample_attachment()
+        };
+        assert_eq!(
+            attachment.validate(),
+            Err(invalid(&attachment.uri, "size must be positive"))
+        );
+    }
+
+    #[test]
+    fn validate_attachment_list() {
+        let other = Attachment {
+            uri: "https://pacs.hospital.example/studies/42".to_string(),
+            ..sample_attachment()
+        };
+        assert_eq!(validate_attachments(&[sample_attachment(), other]), Ok(()));
+
+        assert_eq!(
+            validate_attachments(&[sample_attachment(), sample_attachment()]),
+            Err(invalid(&sample_attachment().uri, "uri is attached twice"))
+        );
+
+        assert_eq!(
+            validate_attachments(&vec![sample_attachment(); MAX_ATTACHMENTS + 1]),
+            Err(ContractError::TooManyAttachments {
+                count: MAX_ATTACHMENTS + 1,
+                max: MAX_ATTACHMENTS
+            })
+        );
+    }
+}
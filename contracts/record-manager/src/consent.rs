@@ -0,0 +1,26 @@
+use cosmwasm_std::Timestamp;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+/// Use of a patient's records beyond their own care.
+#[derive(Serialize, Deserialize, JsonSchema, PartialEq, Eq, Debug, Clone)]
+#[serde(rename_all = "snake_case")]
+pub enum ConsentPurpose {
+    ModelTraining,
+    Study { study_id: String },
+    CommercialUse,
+}
+
+#[derive(Serialize, Deserialize, JsonSchema, PartialEq, Eq, Debug, Clone)]
+#[serde(rename_all = "snake_case")]
+pub struct Consent {
+    pub purpose: ConsentPurpose,
+    pub granted_at: Timestamp,
+    pub expires: Timestamp,
+}
+
+impl Consent {
+    pub fn is_active(&self, now: &Timestamp) -> bool {
+        now < &self.expires
+    }
+}
@@ -1,8 +1,18 @@
 use crate::error::ContractError;
-use crate::msg::{CallbackInfo, ExecuteMsg, InstantiateMsg, QueryMsg, RecordPermissions};
-use crate::state::{Record, RECORD_STORE};
-use crate::state::{OWNER, REGISTRY};
-use cosmwasm_std::{to_binary, Binary, Deps, DepsMut, Env, MessageInfo, Response, StdResult};
+use crate::msg::{
+    CallbackInfo, ExecuteMsg, InstantiateMsg, MigrateMsg, QueryMsg, RecordPermissions,
+};
+use crate::state::{AccessAction, AccessEvent, ACCESS_LOG};
+use crate::state::{ContractInfo, CONTRACT_INFO, OWNER, PREFIX_REVOKED_PERMITS, REGISTRY};
+use crate::state::{ReadGrant, Record, GRANT_STORE, RECORD_HISTORY, RECORD_STORE};
+use crate::state::{CONSENT_STORE, EMERGENCY_LOG, EMERGENCY_UNLOCKS, REGISTRY_KEY};
+use cosmwasm_std::{to_binary, Addr, Binary, Deps, DepsMut, Env, MessageInfo, Response, StdResult};
+use events::{record_id_hash, ActorRole, AuditEvent};
+use secret_toolkit::crypto::sha_256;
+use secret_toolkit::permit::Permit;
+
+const CONTRACT_NAME: &str = env!("CARGO_PKG_NAME");
+const CONTRACT_VERSION: &str = env!("CARGO_PKG_VERSION");
 
 pub fn instantiate(
     deps: DepsMut,
@@ -10,8 +20,18 @@ pub fn instantiate(
     info: MessageInfo,
     msg: InstantiateMsg,
 ) -> StdResult<Response> {
+    CONTRACT_INFO.save(
+        deps.storage,
+        &ContractInfo {
+            name: CONTRACT_NAME.to_string(),
+            version: CONTRACT_VERSION.to_string(),
+        },
+    )?;
     OWNER.save(deps.storage, &msg.owner)?;
     REGISTRY.save(deps.storage, &info.sender)?;
+    if let Some(registry_key) = &msg.registry_key {
+        REGISTRY_KEY.save(deps.storage, &sha_256(registry_key.as_bytes()).to_vec())?;
+    }
 
     let callback_info = CallbackInfo {
         offspring_address: env.contract.address,
@@ -30,63 +50,449 @@ pub fn execute(
     info: MessageInfo,
     msg: ExecuteMsg,
 ) -> Result<Response, ContractError> {
-    if REGISTRY.load(deps.storage).unwrap() != info.sender {
-        return Err(ContractError::Unauthorized {
-            sender: info.sender,
-        });
-    }
+    let event = audit_event(deps.as_ref(), &env, &info.sender, &msg)?;
 
-    match msg {
+    let response = match msg {
         ExecuteMsg::AddRecord {
             id,
             title,
             description,
             data,
+            observation,
+            envelope,
+            attachments,
+            category,
+            permit,
+            author,
+        } => {
+            check_write_permit(deps.as_ref(), &env, &info, &permit, RecordPermissions::Add)?;
+
+            let author = author.unwrap_or(info.sender);
+            execute::add_record(
+                deps,
+                env,
+                id,
+                title,
+                description,
+                data,
+                observation,
+                envelope,
+                attachments,
+                category,
+                author,
+            )
+        }
+        ExecuteMsg::AmendRecord {
+            id,
+            title,
+            description,
+            data,
+            observation,
+            envelope,
+            attachments,
+            category,
+            reason,
             permit,
+            author,
         } => {
-            secret_toolkit::permit::validate(
+            check_write_permit(
                 deps.as_ref(),
-                "revoked_permits",
+                &env,
+                &info,
                 &permit,
-                env.contract.address.to_string(),
-                None,
+                RecordPermissions::Amend {
+                    record_id: id.clone(),
+                },
             )?;
 
-            if !permit.check_permission(&RecordPermissions::Add) {
-                return Err(ContractError::InvalidPermit);
+            let author = author.unwrap_or(info.sender);
+            execute::amend_record(
+                deps,
+                env,
+                id,
+                title,
+                description,
+                data,
+                observation,
+                envelope,
+                attachments,
+                category,
+                reason,
+                author,
+            )
+        }
+        ExecuteMsg::RevokePermit { permit_name } => {
+            check_owner_or_registry(deps.as_ref(), &info.sender)?;
+            execute::revoke_permit(deps, permit_name)
+        }
+        ExecuteMsg::GrantAccess {
+            grantee,
+            record_ids,
+            expires,
+        } => {
+            check_owner_or_registry(deps.as_ref(), &info.sender)?;
+            execute::grant_access(deps, env, grantee, record_ids, expires)
+        }
+        ExecuteMsg::RevokeAccess { grantee } => {
+            check_owner_or_registry(deps.as_ref(), &info.sender)?;
+            execute::revoke_access(deps, env, grantee)
+        }
+        ExecuteMsg::GrantConsent { purpose, expires } => {
+            check_owner_or_registry(deps.as_ref(), &info.sender)?;
+            execute::grant_consent(deps, env, purpose, expires)
+        }
+        ExecuteMsg::WithdrawConsent { purpose } => {
+            check_owner_or_registry(deps.as_ref(), &info.sender)?;
+            execute::withdraw_consent(deps, purpose)
+        }
+        ExecuteMsg::Erase {} => {
+            check_owner_or_registry(deps.as_ref(), &info.sender)?;
+            execute::erase(deps, env)
+        }
+        ExecuteMsg::LogView { record_id, permit } => {
+            let account = check_read_permit(deps.as_ref(), &env, &permit, &record_id)?;
+            execute::log_view(deps, env, account, record_id)
+        }
+        ExecuteMsg::BreakGlass {
+            provider,
+            justification,
+        } => {
+            if REGISTRY.load(deps.storage)? != info.sender {
+                return Err(ContractError::Unauthorized {
+                    sender: info.sender,
+                });
             }
-
-            execute::add_record(deps, env, id, title, description, data)
+            execute::break_glass(deps, env, provider, justification)
         }
-    }
+        ExecuteMsg::DisputeEmergencyAccess { index, reason } => {
+            check_owner_or_registry(deps.as_ref(), &info.sender)?;
+            execute::dispute_emergency_access(deps, env, index, reason)
+        }
+    }?;
+
+    Ok(response.add_event(event.into_event(CONTRACT_VERSION)))
 }
 
 pub fn query(deps: Deps, env: Env, msg: QueryMsg) -> Result<Binary, ContractError> {
     match msg {
         QueryMsg::ViewById { permit, record_id } => {
-            let account = secret_toolkit::permit::validate(
-                deps,
-                "revoked_permits",
-                &permit,
-                env.contract.address.to_string(),
-                None,
-            )?;
+            check_read_permit(deps, &env, &permit, &record_id)?;
+            query::get_record_by_id(deps, record_id)
+        }
+        QueryMsg::ListRecords {
+            permit,
+            start_after,
+            limit,
+        } => {
+            let grant = check_list_permit(deps, &env, &permit)?;
+            query::list_records(deps, start_after, limit, &grant)
+        }
+        QueryMsg::ListByCategory {
+            permit,
+            category,
+            start_after,
+            limit,
+        } => {
+            let grant = check_list_permit(deps, &env, &permit)?;
+            query::list_by_category(deps, category, start_after, limit, &grant)
+        }
+        QueryMsg::ListByTimeRange {
+            permit,
+            from,
+            to,
+            start_after,
+            limit,
+        } => {
+            let grant = check_list_permit(deps, &env, &permit)?;
+            query::list_by_time_range(deps, from, to, start_after, limit, &grant)
+        }
+        QueryMsg::ViewHistory { permit, record_id } => {
+            check_read_permit(deps, &env, &permit, &record_id)?;
+            query::get_record_history(deps, record_id)
+        }
+        QueryMsg::VerifyAttachment {
+            permit,
+            record_id,
+            uri,
+            sha256,
+        } => {
+            check_read_permit(deps, &env, &permit, &record_id)?;
+            query::verify_attachment(deps, record_id, uri, sha256)
+        }
+        QueryMsg::AccessLog {
+            permit,
+            start,
+            limit,
+        } => {
+            check_owner_permit(deps, &env, &permit, RecordPermissions::ViewAccessLog)?;
+            query::get_access_log(deps, start, limit)
+        }
+        QueryMsg::Consents { permit } => {
+            check_owner_permit(deps, &env, &permit, RecordPermissions::ViewConsents)?;
+            query::list_consents(deps)
+        }
+        QueryMsg::EmergencyAccessLog {
+            permit,
+            start,
+            limit,
+        } => {
+            check_owner_permit(deps, &env, &permit, RecordPermissions::ViewAccessLog)?;
+            query::get_emergency_access_log(deps, start, limit)
+        }
+        QueryMsg::CheckConsent {
+            registry_key,
+            purpose,
+        } => {
+            check_registry_key(deps, &registry_key)?;
+            query::check_consent(deps, &env, purpose)
+        }
+        QueryMsg::ResearchData {
+            registry_key,
+            purpose,
+        } => {
+            check_registry_key(deps, &registry_key)?;
+            query::get_research_data(deps, &env, purpose)
+        }
+    }
+}
 
-            if !permit.check_permission(&RecordPermissions::ViewById {
-                record_id: record_id.clone(),
-            }) || account != OWNER.load(deps.storage)?
-            {
-                return Err(ContractError::InvalidPermit);
+fn check_registry_key(deps: Deps, registry_key: &str) -> Result<(), ContractError> {
+    match REGISTRY_KEY.may_load(deps.storage)? {
+        Some(hash) if hash == sha_256(registry_key.as_bytes()) => Ok(()),
+        _ => Err(ContractError::InvalidRegistryKey),
+    }
+}
+
+pub fn migrate(mut deps: DepsMut, env: Env, msg: MigrateMsg) -> Result<Response, ContractError> {
+    match CONTRACT_INFO.may_load(deps.storage)? {
+        Some(info) => {
+            if info.name != CONTRACT_NAME {
+                return Err(ContractError::ContractNameMismatch {
+                    expected: CONTRACT_NAME.to_string(),
+                    found: info.name,
+                });
+            }
+            if parse_version(&info.version)? > parse_version(CONTRACT_VERSION)? {
+                return Err(ContractError::CannotDowngrade {
+                    stored: info.version,
+                    current: CONTRACT_VERSION.to_string(),
+                });
             }
+        }
+        // 0.1.0 did not store its contract info
+        None => migrate::from_v0_1(deps.branch(), &env)?,
+    }
 
-            query::get_record_by_id(deps, record_id)
+    if let Some(registry_key) = &msg.registry_key {
+        REGISTRY_KEY.save(deps.storage, &sha_256(registry_key.as_bytes()).to_vec())?;
+    }
+
+    CONTRACT_INFO.save(
+        deps.storage,
+        &ContractInfo {
+            name: CONTRACT_NAME.to_string(),
+            version: CONTRACT_VERSION.to_string(),
+        },
+    )?;
+
+    Ok(Response::new())
+}
+
+fn parse_version(version: &str) -> Result<Vec<u64>, ContractError> {
+    version
+        .split('.')
+        .map(|part| part.parse::<u64>())
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|_| ContractError::InvalidVersion {
+            version: version.to_string(),
+        })
+}
+
+/// Checks that the permit lets its signer read `record_id` and returns the signer.
+fn check_read_permit(
+    deps: Deps,
+    env: &Env,
+    permit: &Permit<RecordPermissions>,
+    record_id: &str,
+) -> Result<Addr, ContractError> {
+    let account = secret_toolkit::permit::validate(
+        deps,
+        PREFIX_REVOKED_PERMITS,
+        permit,
+        env.contract.address.to_string(),
+        None,
+    )
+    .map_err(|_| ContractError::InvalidPermit)?;
+
+    if !permit.check_permission(&RecordPermissions::ViewById {
+        record_id: record_id.to_string(),
+    }) {
+        return Err(ContractError::InvalidPermit);
+    }
+
+    let account = Addr::unchecked(account);
+    let grant = read_grant(deps, env, &account)?;
+    if !grant.allows(record_id) {
+        return Err(ContractError::InvalidPermit);
+    }
+
+    Ok(account)
+}
+
+/// Describes `msg` for indexers without revealing the patient or record ids.
+fn audit_event(deps: Deps, env: &Env, sender: &Addr, msg: &ExecuteMsg) -> StdResult<AuditEvent> {
+    let event_key = REGISTRY_KEY.may_load(deps.storage)?.unwrap_or_default();
+    let record_manager = &env.contract.address;
+
+    let record_id = match msg {
+        ExecuteMsg::AddRecord { id, .. } | ExecuteMsg::AmendRecord { id, .. } => Some(id),
+        ExecuteMsg::LogView { record_id, .. } => Some(record_id),
+        _ => None,
+    };
+
+    let actor_role = if OWNER.load(deps.storage)? == *sender {
+        ActorRole::Patient
+    } else if REGISTRY.load(deps.storage)? == *sender {
+        ActorRole::Registry
+    } else {
+        ActorRole::Reader
+    };
+
+    Ok(AuditEvent {
+        action: msg.action(),
+        actor_role,
+        // the registry reports the patient; here it would only link the
+        // pseudonym to this contract's address
+        patient: None,
+        record_id_hash: record_id.map(|id| record_id_hash(&event_key, record_manager, id)),
+    })
+}
+
+/// Checks that the permit was signed by the owner and grants `permission`.
+fn check_owner_permit(
+    deps: Deps,
+    env: &Env,
+    permit: &Permit<RecordPermissions>,
+    permission: RecordPermissions,
+) -> Result<(), ContractError> {
+    let account = secret_toolkit::permit::validate(
+        deps,
+        PREFIX_REVOKED_PERMITS,
+        permit,
+        env.contract.address.to_string(),
+        None,
+    )
+    .map_err(|_| ContractError::InvalidPermit)?;
+
+    if !permit.check_permission(&permission) || OWNER.load(deps.storage)? != account {
+        return Err(ContractError::InvalidPermit);
+    }
+    Ok(())
+}
+
+/// Checks that the permit lets its signer list records and returns the grant
+/// that limits which ones they see.
+fn check_list_permit(
+    deps: Deps,
+    env: &Env,
+    permit: &Permit<RecordPermissions>,
+) -> Result<ReadGrant, ContractError> {
+    let account = secret_toolkit::permit::validate(
+        deps,
+        PREFIX_REVOKED_PERMITS,
+        permit,
+        env.contract.address.to_string(),
+        None,
+    )
+    .map_err(|_| ContractError::InvalidPermit)?;
+
+    if !permit.check_permission(&RecordPermissions::ViewAll) {
+        return Err(ContractError::InvalidPermit);
+    }
+
+    read_grant(deps, env, &Addr::unchecked(account))
+}
+
+/// Record writes are relayed by the registry and must carry a valid permit
+/// signed by the owner granting `permission` on this contract.
+fn check_write_permit(
+    deps: Deps,
+    env: &Env,
+    info: &MessageInfo,
+    permit: &Permit<RecordPermissions>,
+    permission: RecordPermissions,
+) -> Result<(), ContractError> {
+    if REGISTRY.load(deps.storage)? != info.sender {
+        return Err(ContractError::Unauthorized {
+            sender: info.sender.clone(),
+        });
+    }
+
+    let account = secret_toolkit::permit::validate(
+        deps,
+        PREFIX_REVOKED_PERMITS,
+        permit,
+        env.contract.address.to_string(),
+        None,
+    )
+    .map_err(|_| ContractError::InvalidPermit)?;
+
+    if !permit.check_permission(&permission) || OWNER.load(deps.storage)? != account {
+        return Err(ContractError::InvalidPermit);
+    }
+    Ok(())
+}
+
+fn check_owner_or_registry(deps: Deps, sender: &Addr) -> Result<(), ContractError> {
+    if REGISTRY.load(deps.storage)? != *sender && OWNER.load(deps.storage)? != *sender {
+        return Err(ContractError::Unauthorized {
+            sender: sender.clone(),
+        });
+    }
+    Ok(())
+}
+
+/// Returns the grant under which `account` may read records. The owner reads
+/// under an unrestricted grant, as does a provider during an emergency unlock;
+/// anyone else needs an active grant from the owner.
+fn read_grant(deps: Deps, env: &Env, account: &Addr) -> Result<ReadGrant, ContractError> {
+    if OWNER.load(deps.storage)? == *account {
+        return Ok(ReadGrant::default());
+    }
+
+    if let Some(index) = EMERGENCY_UNLOCKS.get(deps.storage, account) {
+        let access = EMERGENCY_LOG.get_at(deps.storage, index)?;
+        if access.is_active(&env.block.time) {
+            return Ok(ReadGrant {
+                record_ids: None,
+                expires: Some(access.expires),
+            });
         }
     }
+
+    match GRANT_STORE.get(deps.storage, account) {
+        Some(grant) if grant.is_active(&env.block.time) => Ok(grant),
+        _ => Err(ContractError::InvalidPermit),
+    }
 }
 
 mod execute {
     use super::*;
+    use crate::attachment::{validate_attachments, Attachment};
+    use crate::consent::{Consent, ConsentPurpose};
+    use crate::envelope::EncryptedEnvelope;
+    use crate::observation::CvdObservation;
+    use crate::state::{
+        Dispute, EmergencyAccess, RecordCategory, TimeIndexEntry, CATEGORY_INDEX, TIME_INDEX,
+    };
+    use cosmwasm_std::{Event, Storage, Timestamp};
+    use secret_toolkit::permit::RevokedPermits;
+
+    /// How long an emergency unlock lasts: 4 hours.
+    pub const EMERGENCY_WINDOW: u64 = 4 * 60 * 60;
+    const MAX_JUSTIFICATION_LEN: usize = 512;
 
+    #[allow(clippy::too_many_arguments)]
     pub fn add_record(
         deps: DepsMut,
         env: Env,
@@ -94,99 +500,2679 @@ mod execute {
         title: String,
         description: String,
         data: String,
+        observation: Option<CvdObservation>,
+        envelope: Option<EncryptedEnvelope>,
+        attachments: Vec<Attachment>,
+        category: Option<RecordCategory>,
+        author: Addr,
     ) -> Result<Response, ContractError> {
-        RECORD_STORE.insert(
+        if RECORD_STORE.contains(deps.storage, &id) {
+            return Err(ContractError::RecordExists { id });
+        }
+        if let Some(observation) = &observation {
+            observation.validate()?;
+        }
+        check_envelope(&title, &description, &data, &observation, &envelope)?;
+        validate_attachments(&attachments)?;
+
+        let record = Record {
+            title,
+            timestamp: env.block.time,
+            description,
+            data,
+            observation,
+            envelope,
+            attachments,
+            category,
+            version: 1,
+            author: author.clone(),
+            reason: None,
+        };
+
+        RECORD_STORE.insert(deps.storage, &id, &record)?;
+        index_record(deps.storage, &id, &record, None)?;
+        RECORD_HISTORY
+            .add_suffix(id.as_bytes())
+            .push(deps.storage, &record)?;
+        log_access(
             deps.storage,
-            &id,
-            &Record {
-                title: title,
-                timestamp: env.block.time,
-                description: description,
-                data: data,
-            },
+            &env,
+            author,
+            AccessAction::Add,
+            Some(id),
+            None,
         )?;
 
         Ok(Response::new())
     }
-}
-
-mod query {
-    use super::*;
 
-    pub fn get_record_by_id(deps: Deps, record_id: String) -> Result<Binary, ContractError> {
-        let record = RECORD_STORE.get(deps.storage, &record_id);
-        match record {
-            Some(record) => Ok(to_binary(&record).unwrap()),
-            None => Err(ContractError::NonexistentRecord { id: record_id }),
+    #[allow(clippy::too_many_arguments)]
+    pub fn amend_record(
+        deps: DepsMut,
+        env: Env,
+        id: String,
+        title: String,
+        description: String,
+        data: String,
+        observation: Option<CvdObservation>,
+        envelope: Option<EncryptedEnvelope>,
+        attachments: Vec<Attachment>,
+        category: Option<RecordCategory>,
+        reason: String,
+        author: Addr,
+    ) -> Result<Response, ContractError> {
+        let previous = match RECORD_STORE.get(deps.storage, &id) {
+            Some(previous) => previous,
+            None => return Err(ContractError::NonexistentRecord { id }),
+        };
+        if let Some(observation) = &observation {
+            observation.validate()?;
         }
-    }
-}
+        check_envelope(&title, &description, &data, &observation, &envelope)?;
+        validate_attachments(&attachments)?;
 
-#[cfg(test)]
-mod tests {
-    use cosmwasm_std::{
-        testing::{mock_dependencies, mock_env, mock_info},
-        Addr,
-    };
+        let record = Record {
+            title,
+            timestamp: env.block.time,
+            description,
+            data,
+            observation,
+            envelope,
+            attachments,
+            category,
+            version: previous.version + 1,
+            author: author.clone(),
+            reason: Some(reason),
+        };
 
-    use super::*;
+        RECORD_STORE.insert(deps.storage, &id, &record)?;
+        index_record(deps.storage, &id, &record, previous.category)?;
+        RECORD_HISTORY
+            .add_suffix(id.as_bytes())
+            .push(deps.storage, &record)?;
+        log_access(
+            deps.storage,
+            &env,
+            author,
+            AccessAction::Amend,
+            Some(id),
+            None,
+        )?;
 
-    #[test]
-    fn run_instantiate() {
-        let mut deps = mock_dependencies();
-        let env = mock_env();
+        Ok(Response::new())
+    }
 
-        let resp = instantiate(
-            deps.as_mut(),
-            env,
-            mock_info("sender", &[]),
-            InstantiateMsg {
-                owner: Addr::unchecked("owner"),
-                owner_id: "John Doe".to_string(),
-                owner_pubkey: "".to_string(),
-                key: "".to_string(),
+    /// Files the latest version of a record in the category and time indexes.
+    fn index_record(
+        storage: &mut dyn Storage,
+        id: &str,
+        record: &Record,
+        previous_category: Option<RecordCategory>,
+    ) -> StdResult<()> {
+        let id = id.to_string();
+        if let Some(previous) = previous_category.filter(|c| Some(*c) != record.category) {
+            CATEGORY_INDEX
+                .add_suffix(previous.key())
+                .remove(storage, &id)?;
+        }
+        if let Some(category) = record.category {
+            CATEGORY_INDEX
+                .add_suffix(category.key())
+                .insert(storage, &id, &record.timestamp)?;
+        }
+
+        TIME_INDEX.push(
+            storage,
+            &TimeIndexEntry {
+                timestamp: record.timestamp,
+                record_id: id,
+                version: record.version,
             },
         )
-        .unwrap();
+    }
 
-        assert_eq!(0, resp.messages.len());
+    /// An encrypted record carries its payload only in the envelope, so that
+    /// nothing readable is stored next to it.
+    fn check_envelope(
+        title: &str,
+        description: &str,
+        data: &str,
+        observation: &Option<CvdObservation>,
+        envelope: &Option<EncryptedEnvelope>,
+    ) -> Result<(), ContractError> {
+        if let Some(envelope) = envelope {
+            if !title.is_empty()
+                || !description.is_empty()
+                || !data.is_empty()
+                || observation.is_some()
+            {
+                return Err(ContractError::InvalidEnvelope {
+                    reason: "encrypted records cannot carry plaintext data".to_string(),
+                });
+            }
+            envelope.validate()?;
+        }
+        Ok(())
     }
 
-    #[test]
-    fn run_add_record_unauthorized() {
-        let mut deps = mock_dependencies();
-        let env = mock_env();
+    pub fn revoke_permit(deps: DepsMut, permit_name: String) -> Result<Response, ContractError> {
+        let owner = OWNER.load(deps.storage)?;
 
-        instantiate(
-            deps.as_mut(),
-            env.clone(),
-            mock_info("registry", &[]),
-            InstantiateMsg {
-                owner: Addr::unchecked("owner"),
-                owner_id: "Alice".to_string(),
-                owner_pubkey: "".to_string(),
-                key: "password".to_string(),
+        RevokedPermits::revoke_permit(
+            deps.storage,
+            PREFIX_REVOKED_PERMITS,
+            owner.as_str(),
+            &permit_name,
+        );
+
+        Ok(Response::new())
+    }
+
+    pub fn grant_access(
+        deps: DepsMut,
+        env: Env,
+        grantee: Addr,
+        record_ids: Option<Vec<String>>,
+        expires: Option<Timestamp>,
+    ) -> Result<Response, ContractError> {
+        if let Some(expires) = expires {
+            if expires <= env.block.time {
+                return Err(ContractError::InvalidExpiry { expires });
+            }
+        }
+
+        GRANT_STORE.insert(
+            deps.storage,
+            &grantee,
+            &ReadGrant {
+                record_ids: record_ids.clone(),
+                expires,
             },
-        )
-        .unwrap();
+        )?;
+
+        let owner = OWNER.load(deps.storage)?;
+        match record_ids {
+            Some(record_ids) => {
+                for record_id in record_ids {
+                    log_access(
+                        deps.storage,
+                        &env,
+                        owner.clone(),
+                        AccessAction::Grant,
+                        Some(record_id),
+                        Some(grantee.clone()),
+                    )?;
+                }
+            }
+            None => log_access(
+                deps.storage,
+                &env,
+                owner,
+                AccessAction::Grant,
+                None,
+                Some(grantee),
+            )?,
+        }
+
+        Ok(Response::new())
     }
 
-    #[test]
-    fn run_add_record() {
-        let mut deps = mock_dependencies();
-        let env = mock_env();
+    pub fn revoke_access(
+        deps: DepsMut,
+        env: Env,
+        grantee: Addr,
+    ) -> Result<Response, ContractError> {
+        if !GRANT_STORE.contains(deps.storage, &grantee) {
+            return Err(ContractError::NonexistentGrant { grantee });
+        }
 
-        instantiate(
-            deps.as_mut(),
-            env.clone(),
-            mock_info("registry", &[]),
-            InstantiateMsg {
-                owner: Addr::unchecked("owner"),
-                owner_id: "Alice".to_string(),
-                owner_pubkey: "".to_string(),
-                key: "password".to_string(),
+        GRANT_STORE.remove(deps.storage, &grantee)?;
+
+        let owner = OWNER.load(deps.storage)?;
+        log_access(
+            deps.storage,
+            &env,
+            owner,
+            AccessAction::Revoke,
+            None,
+            Some(grantee),
+        )?;
+
+        Ok(Response::new())
+    }
+
+    pub fn grant_consent(
+        deps: DepsMut,
+        env: Env,
+        purpose: ConsentPurpose,
+        expires: Timestamp,
+    ) -> Result<Response, ContractError> {
+        if expires <= env.block.time {
+            return Err(ContractError::InvalidExpiry { expires });
+        }
+
+        CONSENT_STORE.insert(
+            deps.storage,
+            &purpose,
+            &Consent {
+                purpose: purpose.clone(),
+                granted_at: env.block.time,
+                expires,
             },
-        )
-        .unwrap();
+        )?;
+
+        Ok(Response::new())
+    }
+
+    pub fn withdraw_consent(
+        deps: DepsMut,
+        purpose: ConsentPurpose,
+    ) -> Result<Response, ContractError> {
+        if !CONSENT_STORE.contains(deps.storage, &purpose) {
+            return Err(ContractError::NonexistentConsent { purpose });
+        }
+
+        CONSENT_STORE.remove(deps.storage, &purpose)?;
+
+        Ok(Response::new())
+    }
+
+    /// Removes every record and grant. History entries are overwritten with
+    /// tombstones before the history is cleared, since an append store only
+    /// drops its length.
+    pub fn erase(deps: DepsMut, env: Env) -> Result<Response, ContractError> {
+        let record_store = RECORD_STORE;
+        let record_ids = record_store
+            .iter_keys(deps.storage)?
+            .collect::<StdResult<Vec<_>>>()?;
+        let tombstone = Record::tombstone(env.block.time);
+
+        for id in &record_ids {
+            if let Some(category) = RECORD_STORE.get(deps.storage, id).and_then(|r| r.category) {
+                CATEGORY_INDEX
+                    .add_suffix(category.key())
+                    .remove(deps.storage, id)?;
+            }
+            let history = RECORD_HISTORY.add_suffix(id.as_bytes());
+            for pos in 0..history.get_len(deps.storage)? {
+                history.set_at(deps.storage, pos, &tombstone)?;
+            }
+            history.clear(deps.storage);
+            RECORD_STORE.remove(deps.storage, id)?;
+        }
+
+        let blank_entry = TimeIndexEntry {
+            timestamp: env.block.time,
+            record_id: String::new(),
+            version: 0,
+        };
+        for pos in 0..TIME_INDEX.get_len(deps.storage)? {
+            TIME_INDEX.set_at(deps.storage, pos, &blank_entry)?;
+        }
+        TIME_INDEX.clear(deps.storage);
+
+        let grant_store = GRANT_STORE;
+        let grantees = grant_store
+            .iter_keys(deps.storage)?
+            .collect::<StdResult<Vec<_>>>()?;
+        for grantee in &grantees {
+            GRANT_STORE.remove(deps.storage, grantee)?;
+        }
+
+        let unlocks = EMERGENCY_UNLOCKS;
+        let providers = unlocks
+            .iter_keys(deps.storage)?
+            .collect::<StdResult<Vec<_>>>()?;
+        for provider in &providers {
+            EMERGENCY_UNLOCKS.remove(deps.storage, provider)?;
+        }
+
+        let consent_store = CONSENT_STORE;
+        let purposes = consent_store
+            .iter_keys(deps.storage)?
+            .collect::<StdResult<Vec<_>>>()?;
+        for purpose in &purposes {
+            CONSENT_STORE.remove(deps.storage, purpose)?;
+        }
+
+        // the logs name grantees, providers and record ids; the registry
+        // keeps the erasure receipt
+        let blank_event = AccessEvent {
+            actor: Addr::unchecked(""),
+            action: AccessAction::Erase,
+            record_id: None,
+            grantee: None,
+            time: env.block.time,
+            height: env.block.height,
+        };
+        for pos in 0..ACCESS_LOG.get_len(deps.storage)? {
+            ACCESS_LOG.set_at(deps.storage, pos, &blank_event)?;
+        }
+        ACCESS_LOG.clear(deps.storage);
+
+        let blank_access = EmergencyAccess {
+            provider: Addr::unchecked(""),
+            justification: String::new(),
+            time: env.block.time,
+            height: env.block.height,
+            expires: env.block.time,
+            dispute: None,
+        };
+        for pos in 0..EMERGENCY_LOG.get_len(deps.storage)? {
+            EMERGENCY_LOG.set_at(deps.storage, pos, &blank_access)?;
+        }
+        EMERGENCY_LOG.clear(deps.storage);
+
+        Ok(Response::new())
+    }
+
+    pub fn break_glass(
+        deps: DepsMut,
+        env: Env,
+        provider: Addr,
+        justification: String,
+    ) -> Result<Response, ContractError> {
+        let justification = justification.trim().to_string();
+        if justification.is_empty() || justification.chars().count() > MAX_JUSTIFICATION_LEN {
+            return Err(ContractError::InvalidJustification {
+                max: MAX_JUSTIFICATION_LEN,
+            });
+        }
+
+        let expires = env.block.time.plus_seconds(EMERGENCY_WINDOW);
+        let index = EMERGENCY_LOG.get_len(deps.storage)?;
+        EMERGENCY_LOG.push(
+            deps.storage,
+            &EmergencyAccess {
+                provider: provider.clone(),
+                justification,
+                time: env.block.time,
+                height: env.block.height,
+                expires,
+                dispute: None,
+            },
+        )?;
+        EMERGENCY_UNLOCKS.insert(deps.storage, &provider, &index)?;
+        log_access(
+            deps.storage,
+            &env,
+            provider.clone(),
+            AccessAction::BreakGlass,
+            None,
+            None,
+        )?;
+
+        Ok(Response::new().add_event(
+            Event::new("break_glass")
+                .add_attribute("provider", provider)
+                .add_attribute("index", index.to_string())
+                .add_attribute("expires", expires.seconds().to_string()),
+        ))
+    }
+
+    pub fn dispute_emergency_access(
+        deps: DepsMut,
+        env: Env,
+        index: u32,
+        reason: String,
+    ) -> Result<Response, ContractError> {
+        if index >= EMERGENCY_LOG.get_len(deps.storage)? {
+            return Err(ContractError::NonexistentEmergencyAccess { index });
+        }
+        let mut access = EMERGENCY_LOG.get_at(deps.storage, index)?;
+        if access.dispute.is_some() {
+            return Err(ContractError::AlreadyDisputed { index });
+        }
+
+        access.dispute = Some(Dispute {
+            reason,
+            time: env.block.time,
+        });
+        EMERGENCY_LOG.set_at(deps.storage, index, &access)?;
+
+        let owner = OWNER.load(deps.storage)?;
+        log_access(
+            deps.storage,
+            &env,
+            owner,
+            AccessAction::Dispute,
+            None,
+            Some(access.provider),
+        )?;
+
+        Ok(Response::new())
+    }
+
+    pub fn log_view(
+        deps: DepsMut,
+        env: Env,
+        actor: Addr,
+        record_id: String,
+    ) -> Result<Response, ContractError> {
+        if !RECORD_STORE.contains(deps.storage, &record_id) {
+            return Err(ContractError::NonexistentRecord { id: record_id });
+        }
+
+        log_access(
+            deps.storage,
+            &env,
+            actor,
+            AccessAction::View,
+            Some(record_id),
+            None,
+        )?;
+
+        Ok(Response::new())
+    }
+
+    fn log_access(
+        storage: &mut dyn Storage,
+        env: &Env,
+        actor: Addr,
+        action: AccessAction,
+        record_id: Option<String>,
+        grantee: Option<Addr>,
+    ) -> StdResult<()> {
+        ACCESS_LOG.push(
+            storage,
+            &AccessEvent {
+                actor,
+                action,
+                record_id,
+                grantee,
+                time: env.block.time,
+                height: env.block.height,
+            },
+        )
+    }
+}
+
+mod migrate {
+    use super::*;
+    use crate::state::{TimeIndexEntry, LEGACY_RECORD_STORE, TIME_INDEX};
+
+    /// Rewrites 0.1.0 records in the current layout. They were all relayed by
+    /// the registry and start their version history here.
+    pub fn from_v0_1(deps: DepsMut, env: &Env) -> Result<(), ContractError> {
+        let registry = REGISTRY.load(deps.storage)?;
+        let legacy_store = LEGACY_RECORD_STORE;
+        let legacy_records = legacy_store
+            .iter(deps.storage)?
+            .collect::<StdResult<Vec<_>>>()?;
+
+        for (id, legacy) in legacy_records {
+            let record = Record {
+                title: legacy.title,
+                timestamp: legacy.timestamp,
+                description: legacy.description,
+                data: legacy.data,
+                observation: None,
+                envelope: None,
+                attachments: vec![],
+                category: None,
+                version: 1,
+                author: registry.clone(),
+                reason: None,
+            };
+
+            RECORD_STORE.insert(deps.storage, &id, &record)?;
+            RECORD_HISTORY
+                .add_suffix(id.as_bytes())
+                .push(deps.storage, &record)?;
+            TIME_INDEX.push(
+                deps.storage,
+                &TimeIndexEntry {
+                    timestamp: record.timestamp,
+                    record_id: id.clone(),
+                    version: record.version,
+                },
+            )?;
+            ACCESS_LOG.push(
+                deps.storage,
+                &AccessEvent {
+                    actor: registry.clone(),
+                    action: AccessAction::Add,
+                    record_id: Some(id),
+                    grantee: None,
+                    time: env.block.time,
+                    height: env.block.height,
+                },
+            )?;
+        }
+
+        Ok(())
+    }
+}
+
+mod query {
+    use super::*;
+    use crate::consent::ConsentPurpose;
+    use crate::msg::{
+        AccessLogResp, CheckConsentResp, ConsentsResp, EmergencyAccessLogResp, ListByTimeRangeResp,
+        ListRecordsResp, RecordHistoryResp, RecordSummary, ResearchDataResp, ResearchObservation,
+        VerifyAttachmentResp,
+    };
+    use crate::state::{RecordCategory, CATEGORY_INDEX, TIME_INDEX};
+    use cosmwasm_std::Timestamp;
+
+    /// Length in seconds of the windows research observation times are
+    /// coarsened to.
+    pub const RESEARCH_PERIOD: u64 = 30 * 24 * 60 * 60;
+
+    const DEFAULT_LIMIT: u32 = 10;
+    const MAX_LIMIT: u32 = 30;
+    /// Time-index entries a page may scan per record it can return
+    const SCANNED_PER_RECORD: usize = 4;
+
+    pub fn get_record_by_id(deps: Deps, record_id: String) -> Result<Binary, ContractError> {
+        let record = RECORD_STORE.get(deps.storage, &record_id);
+        match record {
+            Some(record) => Ok(to_binary(&record).unwrap()),
+            None => Err(ContractError::NonexistentRecord { id: record_id }),
+        }
+    }
+
+    pub fn get_record_history(deps: Deps, record_id: String) -> Result<Binary, ContractError> {
+        if !RECORD_STORE.contains(deps.storage, &record_id) {
+            return Err(ContractError::NonexistentRecord { id: record_id });
+        }
+
+        let history = RECORD_HISTORY.add_suffix(record_id.as_bytes());
+        let versions = history.iter(deps.storage)?.collect::<StdResult<Vec<_>>>()?;
+
+        Ok(to_binary(&RecordHistoryResp { versions }).unwrap())
+    }
+
+    /// Compares `sha256` with the digest of the attachment at `uri` in the
+    /// latest version of the record.
+    pub fn verify_attachment(
+        deps: Deps,
+        record_id: String,
+        uri: String,
+        sha256: Binary,
+    ) -> Result<Binary, ContractError> {
+        let record = match RECORD_STORE.get(deps.storage, &record_id) {
+            Some(record) => record,
+            None => return Err(ContractError::NonexistentRecord { id: record_id }),
+        };
+
+        let attachment = match record.attachments.into_iter().find(|a| a.uri == uri) {
+            Some(attachment) => attachment,
+            None => return Err(ContractError::NonexistentAttachment { record_id, uri }),
+        };
+
+        Ok(to_binary(&VerifyAttachmentResp {
+            matches: attachment.sha256 == sha256,
+            attachment,
+        })
+        .unwrap())
+    }
+
+    pub fn list_consents(deps: Deps) -> Result<Binary, ContractError> {
+        let consent_store = CONSENT_STORE;
+        let consents = consent_store
+            .iter(deps.storage)?
+            .map(|item| item.map(|(_, consent)| consent))
+            .collect::<StdResult<Vec<_>>>()?;
+
+        Ok(to_binary(&ConsentsResp { consents }).unwrap())
+    }
+
+    pub fn check_consent(
+        deps: Deps,
+        env: &Env,
+        purpose: ConsentPurpose,
+    ) -> Result<Binary, ContractError> {
+        let resp = match CONSENT_STORE.get(deps.storage, &purpose) {
+            Some(consent) => CheckConsentResp {
+                active: consent.is_active(&env.block.time),
+                expires: Some(consent.expires),
+            },
+            None => CheckConsentResp {
+                active: false,
+                expires: None,
+            },
+        };
+
+        Ok(to_binary(&resp).unwrap())
+    }
+
+    pub fn get_research_data(
+        deps: Deps,
+        env: &Env,
+        purpose: ConsentPurpose,
+    ) -> Result<Binary, ContractError> {
+        match CONSENT_STORE.get(deps.storage, &purpose) {
+            Some(consent) if consent.is_active(&env.block.time) => {}
+            _ => return Err(ContractError::NoConsent { purpose }),
+        }
+
+        let record_store = RECORD_STORE;
+        let observations = record_store
+            .iter(deps.storage)?
+            .filter_map(|item| match item {
+                Ok((_, record)) => record.observation.map(|observation| {
+                    let seconds = record.timestamp.seconds();
+                    Ok(ResearchObservation {
+                        period: Timestamp::from_seconds(seconds - seconds % RESEARCH_PERIOD),
+                        observation,
+                    })
+                }),
+                Err(err) => Some(Err(err)),
+            })
+            .collect::<StdResult<Vec<_>>>()?;
+
+        Ok(to_binary(&ResearchDataResp { observations }).unwrap())
+    }
+
+    pub fn get_access_log(
+        deps: Deps,
+        start: Option<u32>,
+        limit: Option<u32>,
+    ) -> Result<Binary, ContractError> {
+        let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as usize;
+        let access_log = ACCESS_LOG;
+        let total = access_log.get_len(deps.storage)?;
+        let entries = access_log
+            .iter(deps.storage)?
+            .skip(start.unwrap_or(0) as usize)
+            .take(limit)
+            .collect::<StdResult<Vec<_>>>()?;
+
+        Ok(to_binary(&AccessLogResp { entries, total }).unwrap())
+    }
+
+    pub fn get_emergency_access_log(
+        deps: Deps,
+        start: Option<u32>,
+        limit: Option<u32>,
+    ) -> Result<Binary, ContractError> {
+        let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as usize;
+        let emergency_log = EMERGENCY_LOG;
+        let total = emergency_log.get_len(deps.storage)?;
+        let entries = emergency_log
+            .iter(deps.storage)?
+            .skip(start.unwrap_or(0) as usize)
+            .take(limit)
+            .collect::<StdResult<Vec<_>>>()?;
+
+        Ok(to_binary(&EmergencyAccessLogResp { entries, total }).unwrap())
+    }
+
+    pub fn list_records(
+        deps: Deps,
+        start_after: Option<String>,
+        limit: Option<u32>,
+        grant: &ReadGrant,
+    ) -> Result<Binary, ContractError> {
+        let limit = limit.unwrap_or(DEFAULT_LIMIT).clamp(1, MAX_LIMIT) as usize;
+        let record_store = RECORD_STORE;
+        let mut records = record_store.iter(deps.storage)?;
+
+        if let Some(start_after) = start_after {
+            // Ids outside the grant are reported exactly like missing ones so
+            // a restricted reader cannot probe for other records.
+            if !grant.allows(&start_after) || !RECORD_STORE.contains(deps.storage, &start_after) {
+                return Err(ContractError::NonexistentRecord { id: start_after });
+            }
+            for item in records.by_ref() {
+                if item?.0 == start_after {
+                    break;
+                }
+            }
+        }
+
+        let records = records
+            .filter(|item| match item {
+                Ok((id, _)) => grant.allows(id),
+                Err(_) => true,
+            })
+            .take(limit + 1)
+            .map(|item| {
+                item.map(|(id, record)| RecordSummary {
+                    id,
+                    title: record.title,
+                    timestamp: record.timestamp,
+                    category: record.category,
+                })
+            })
+            .collect::<StdResult<Vec<_>>>()?;
+
+        Ok(to_binary(&page(records, limit)).unwrap())
+    }
+
+    pub fn list_by_category(
+        deps: Deps,
+        category: RecordCategory,
+        start_after: Option<String>,
+        limit: Option<u32>,
+        grant: &ReadGrant,
+    ) -> Result<Binary, ContractError> {
+        let limit = limit.unwrap_or(DEFAULT_LIMIT).clamp(1, MAX_LIMIT) as usize;
+        let category_index = CATEGORY_INDEX.add_suffix(category.key());
+        let mut ids = category_index.iter_keys(deps.storage)?;
+
+        if let Some(start_after) = start_after {
+            if !grant.allows(&start_after) || !category_index.contains(deps.storage, &start_after) {
+                return Err(ContractError::NonexistentRecord { id: start_after });
+            }
+            for id in ids.by_ref() {
+                if id? == start_after {
+                    break;
+                }
+            }
+        }
+
+        let records = ids
+            .filter(|id| match id {
+                Ok(id) => grant.allows(id),
+                Err(_) => true,
+            })
+            // index entries left behind by a removed record are skipped
+            .filter_map(|id| match id {
+                Ok(id) => RECORD_STORE
+                    .get(deps.storage, &id)
+                    .map(|record| Ok((id, record))),
+                Err(err) => Some(Err(err)),
+            })
+            .take(limit + 1)
+            .map(|item| {
+                item.map(|(id, record)| RecordSummary {
+                    id,
+                    title: record.title,
+                    timestamp: record.timestamp,
+                    category: record.category,
+                })
+            })
+            .collect::<StdResult<Vec<_>>>()?;
+
+        Ok(to_binary(&page(records, limit)).unwrap())
+    }
+
+    /// Keeps the first `limit` of `records`, which were read one past the
+    /// limit to tell whether another page follows.
+    fn page(mut records: Vec<RecordSummary>, limit: usize) -> ListRecordsResp {
+        let next = if records.len() > limit {
+            records.truncate(limit);
+            records.last().map(|record| record.id.clone())
+        } else {
+            None
+        };
+        ListRecordsResp { records, next }
+    }
+
+    /// Lists the records whose latest version was written in `[from, to)`,
+    /// oldest first. `start_after` is the position in the time index returned
+    /// as `next` by the previous page. A page scans at most
+    /// `SCANNED_PER_RECORD` entries per record it may return, so a range full
+    /// of stale or hidden entries can yield short pages before the last one.
+    pub fn list_by_time_range(
+        deps: Deps,
+        from: Timestamp,
+        to: Timestamp,
+        start_after: Option<u32>,
+        limit: Option<u32>,
+        grant: &ReadGrant,
+    ) -> Result<Binary, ContractError> {
+        // a page must make progress, or `next` would never move
+        let limit = limit.unwrap_or(DEFAULT_LIMIT).clamp(1, MAX_LIMIT) as usize;
+        let max_scanned = limit * SCANNED_PER_RECORD;
+        let time_index = TIME_INDEX;
+        let len = time_index.get_len(deps.storage)?;
+
+        let mut pos = match start_after {
+            Some(start_after) => start_after.saturating_add(1),
+            None => {
+                // first entry at or after `from`
+                let (mut low, mut high) = (0, len);
+                while low < high {
+                    let mid = low + (high - low) / 2;
+                    if time_index.get_at(deps.storage, mid)?.timestamp < from {
+                        low = mid + 1;
+                    } else {
+                        high = mid;
+                    }
+                }
+                low
+            }
+        };
+
+        let mut records = vec![];
+        let mut scanned = 0;
+        let mut next = None;
+        while pos < len {
+            let entry = time_index.get_at(deps.storage, pos)?;
+            if entry.timestamp >= to {
+                break;
+            }
+            // something was scanned before either limit is reached, so the
+            // previous position exists
+            if records.len() == limit || scanned == max_scanned {
+                next = Some(pos - 1);
+                break;
+            }
+            pos += 1;
+            scanned += 1;
+
+            if entry.timestamp < from || !grant.allows(&entry.record_id) {
+                continue;
+            }
+            match RECORD_STORE.get(deps.storage, &entry.record_id) {
+                Some(record) if record.version == entry.version => records.push(RecordSummary {
+                    id: entry.record_id,
+                    title: record.title,
+                    timestamp: record.timestamp,
+                    category: record.category,
+                }),
+                _ => continue,
+            }
+        }
+
+        Ok(to_binary(&ListByTimeRangeResp { records, next }).unwrap())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use cosmwasm_std::{
+        from_binary,
+        testing::{mock_dependencies, mock_env, mock_info, MockApi},
+        Addr,
+    };
+    use secret_toolkit::crypto::secp256k1::PrivateKey;
+    use secret_toolkit::permit::{PermitParams, PermitSignature, PubKey, SignedPermit};
+
+    use super::*;
+
+    #[test]
+    fn run_instantiate() {
+        let mut deps = mock_dependencies();
+        let env = mock_env();
+
+        let resp = instantiate(
+            deps.as_mut(),
+            env,
+            mock_info("sender", &[]),
+            InstantiateMsg {
+                owner: Addr::unchecked("owner"),
+                owner_id: "John Doe".to_string(),
+                owner_pubkey: "".to_string(),
+                key: "".to_string(),
+                registry_key: None,
+            },
+        )
+        .unwrap();
+
+        assert_eq!(0, resp.messages.len());
+    }
+
+    /// Signs `permissions` for the mock contract with a fixed secp256k1 key,
+    /// the way the owner's wallet does.
+    fn signed_permit(permissions: Vec<RecordPermissions>) -> Permit<RecordPermissions> {
+        let key = PrivateKey::parse(&[7; 32]).unwrap();
+        let params = PermitParams {
+            allowed_tokens: vec![mock_env().contract.address.to_string()],
+            permit_name: "records".to_string(),
+            chain_id: mock_env().block.chain_id,
+            permissions,
+        };
+        let signed_bytes = to_binary(&SignedPermit::from_params(&params)).unwrap();
+        let signature = key.sign(signed_bytes.as_slice(), MockApi::default());
+
+        Permit {
+            params,
+            signature: PermitSignature {
+                pub_key: PubKey {
+                    r#type: "tendermint/PubKeySecp256k1".to_string(),
+                    value: Binary::from(key.pubkey().serialize_compressed().as_slice()),
+                },
+                signature: Binary::from(signature.serialize().as_slice()),
+            },
+        }
+    }
+
+    /// Address of the key that signs `signed_permit`.
+    fn permit_account() -> Addr {
+        let account = secret_toolkit::permit::validate(
+            mock_dependencies().as_ref(),
+            PREFIX_REVOKED_PERMITS,
+            &signed_permit(vec![]),
+            mock_env().contract.address.to_string(),
+            None,
+        )
+        .unwrap();
+        Addr::unchecked(account)
+    }
+
+    fn add_record_msg(id: &str, permit: Permit<RecordPermissions>) -> ExecuteMsg {
+        ExecuteMsg::AddRecord {
+            id: id.to_string(),
+            title: "ECG".to_string(),
+            description: "Resting ECG".to_string(),
+            data: "sinus rhythm".to_string(),
+            observation: None,
+            envelope: None,
+            attachments: vec![],
+            category: None,
+            permit,
+            author: Some(Addr::unchecked("clinician")),
+        }
+    }
+
+    #[test]
+    fn run_add_record_unauthorized() {
+        let mut deps = mock_dependencies();
+        let env = mock_env();
+        let owner = permit_account();
+
+        instantiate(
+            deps.as_mut(),
+            env.clone(),
+            mock_info("registry", &[]),
+            InstantiateMsg {
+                owner: owner.clone(),
+                owner_id: "Alice".to_string(),
+                owner_pubkey: "".to_string(),
+                key: "password".to_string(),
+                registry_key: None,
+            },
+        )
+        .unwrap();
+
+        // a valid permit does not let anyone but the registry write
+        for sender in [owner.as_str(), "clinician"] {
+            let err = execute(
+                deps.as_mut(),
+                env.clone(),
+                mock_info(sender, &[]),
+                add_record_msg("1", signed_permit(vec![RecordPermissions::Add])),
+            )
+            .unwrap_err();
+            assert_eq!(
+                err,
+                ContractError::Unauthorized {
+                    sender: Addr::unchecked(sender)
+                }
+            );
+        }
+
+        // the registry cannot write with a permit lacking the permission
+        let err = execute(
+            deps.as_mut(),
+            env.clone(),
+            mock_info("registry", &[]),
+            add_record_msg("1", signed_permit(vec![RecordPermissions::ViewAll])),
+        )
+        .unwrap_err();
+        assert_eq!(err, ContractError::InvalidPermit);
+
+        // nor with a permit whose signature does not match its content
+        let mut permit = signed_permit(vec![RecordPermissions::Add]);
+        permit.params.permit_name = "tampered".to_string();
+        execute(
+            deps.as_mut(),
+            env.clone(),
+            mock_info("registry", &[]),
+            add_record_msg("1", permit),
+        )
+        .unwrap_err();
+
+        assert!(!RECORD_STORE.contains(deps.as_ref().storage, &"1".to_string()));
+
+        // nor with a valid permit signed by someone other than the owner
+        let mut deps = mock_dependencies();
+        instantiate(
+            deps.as_mut(),
+            env.clone(),
+            mock_info("registry", &[]),
+            InstantiateMsg {
+                owner: Addr::unchecked("someone-else"),
+                owner_id: "Bob".to_string(),
+                owner_pubkey: "".to_string(),
+                key: "password".to_string(),
+                registry_key: None,
+            },
+        )
+        .unwrap();
+        let err = execute(
+            deps.as_mut(),
+            env,
+            mock_info("registry", &[]),
+            add_record_msg("1", signed_permit(vec![RecordPermissions::Add])),
+        )
+        .unwrap_err();
+        assert_eq!(err, ContractError::InvalidPermit);
+    }
+
+    #[test]
+    fn run_add_record() {
+        let mut deps = mock_dependencies();
+        let env = mock_env();
+        let owner = permit_account();
+
+        instantiate(
+            deps.as_mut(),
+            env.clone(),
+            mock_info("registry", &[]),
+            InstantiateMsg {
+                owner: owner.clone(),
+                owner_id: "Alice".to_string(),
+                owner_pubkey: "".to_string(),
+                key: "password".to_string(),
+                registry_key: None,
+            },
+        )
+        .unwrap();
+
+        execute(
+            deps.as_mut(),
+            env.clone(),
+            mock_info("registry", &[]),
+            add_record_msg("1", signed_permit(vec![RecordPermissions::Add])),
+        )
+        .unwrap();
+
+        let err = execute(
+            deps.as_mut(),
+            env.clone(),
+            mock_info("registry", &[]),
+            add_record_msg("1", signed_permit(vec![RecordPermissions::Add])),
+        )
+        .unwrap_err();
+        assert_eq!(
+            err,
+            ContractError::RecordExists {
+                id: "1".to_string()
+            }
+        );
+
+        let resp = query::get_record_by_id(deps.as_ref(), "1".to_string()).unwrap();
+        let resp: Record = from_binary(&resp).unwrap();
+        assert_eq!(resp.title, "ECG");
+        assert_eq!(resp.author, Addr::unchecked("clinician"));
+        assert_eq!(resp.version, 1);
+        assert_eq!(resp.timestamp, env.block.time);
+    }
+
+    #[test]
+    fn run_list_records() {
+        let mut deps = mock_dependencies();
+        let env = mock_env();
+
+        for id in ["1", "2", "3"] {
+            execute::add_record(
+                deps.as_mut(),
+                env.clone(),
+                id.to_string(),
+                format!("Record {}", id),
+                "".to_string(),
+                "".to_string(),
+                None,
+                None,
+                vec![],
+                None,
+                Addr::unchecked("clinician"),
+            )
+            .unwrap();
+        }
+
+        let resp =
+            query::list_records(deps.as_ref(), None, Some(2), &ReadGrant::default()).unwrap();
+        let resp: crate::msg::ListRecordsResp = from_binary(&resp).unwrap();
+        let ids: Vec<String> = resp.records.into_iter().map(|r| r.id).collect();
+        assert_eq!(ids, vec!["1".to_string(), "2".to_string()]);
+
+        let resp = query::list_records(
+            deps.as_ref(),
+            Some("2".to_string()),
+            None,
+            &ReadGrant::default(),
+        )
+        .unwrap();
+        let resp: crate::msg::ListRecordsResp = from_binary(&resp).unwrap();
+        assert_eq!(resp.records.len(), 1);
+        assert_eq!(resp.records[0].id, "3");
+        assert_eq!(resp.records[0].title, "Record 3");
+        assert_eq!(resp.records[0].timestamp, env.block.time);
+
+        let err = query::list_records(
+            deps.as_ref(),
+            Some("4".to_string()),
+            None,
+            &ReadGrant::default(),
+        )
+        .unwrap_err();
+        assert_eq!(
+            err,
+            ContractError::NonexistentRecord {
+                id: "4".to_string()
+            }
+        );
+
+        // a restricted grant cannot page from, or probe for, ids outside it
+        let grant = ReadGrant {
+            record_ids: Some(vec!["1".to_string(), "3".to_string()]),
+            expires: None,
+        };
+        let err =
+            query::list_records(deps.as_ref(), Some("2".to_string()), None, &grant).unwrap_err();
+        assert_eq!(
+            err,
+            ContractError::NonexistentRecord {
+                id: "2".to_string()
+            }
+        );
+
+        let resp = query::list_records(deps.as_ref(), Some("1".to_string()), None, &grant).unwrap();
+        let resp: crate::msg::ListRecordsResp = from_binary(&resp).unwrap();
+        let ids: Vec<String> = resp.records.into_iter().map(|r| r.id).collect();
+        assert_eq!(ids, vec!["3".to_string()]);
+    }
+
+    #[test]
+    fn run_revoke_permit() {
+        let mut deps = mock_dependencies();
+        let env = mock_env();
+        let owner = permit_account();
+
+        instantiate(
+            deps.as_mut(),
+            env.clone(),
+            mock_info("registry", &[]),
+            InstantiateMsg {
+                owner: owner.clone(),
+                owner_id: "Alice".to_string(),
+                owner_pubkey: "".to_string(),
+                key: "password".to_string(),
+                registry_key: None,
+            },
+        )
+        .unwrap();
+
+        let permit = signed_permit(vec![
+            RecordPermissions::Add,
+            RecordPermissions::ViewById {
+                record_id: "1".to_string(),
+            },
+        ]);
+        execute(
+            deps.as_mut(),
+            env.clone(),
+            mock_info("registry", &[]),
+            add_record_msg("1", permit.clone()),
+        )
+        .unwrap();
+        let view_by_id = QueryMsg::ViewById {
+            permit: permit.clone(),
+            record_id: "1".to_string(),
+        };
+        query(deps.as_ref(), env.clone(), view_by_id.clone()).unwrap();
+
+        let err = execute(
+            deps.as_mut(),
+            env.clone(),
+            mock_info("stranger", &[]),
+            ExecuteMsg::RevokePermit {
+                permit_name: "records".to_string(),
+            },
+        )
+        .unwrap_err();
+        assert_eq!(
+            err,
+            ContractError::Unauthorized {
+                sender: Addr::unchecked("stranger")
+            }
+        );
+
+        execute(
+            deps.as_mut(),
+            env.clone(),
+            mock_info(owner.as_str(), &[]),
+            ExecuteMsg::RevokePermit {
+                permit_name: "records".to_string(),
+            },
+        )
+        .unwrap();
+
+        let err = query(deps.as_ref(), env.clone(), view_by_id).unwrap_err();
+        assert_eq!(err, ContractError::InvalidPermit);
+
+        let err = execute(
+            deps.as_mut(),
+            env.clone(),
+            mock_info("registry", &[]),
+            add_record_msg("2", permit),
+        )
+        .unwrap_err();
+        assert_eq!(err, ContractError::InvalidPermit);
+        assert!(!RECORD_STORE.contains(deps.as_ref().storage, &"2".to_string()));
+    }
+
+    #[test]
+    fn run_grant_access() {
+        let mut deps = mock_dependencies();
+        let mut env = mock_env();
+        let clinician = Addr::unchecked("clinician");
+
+        instantiate(
+            deps.as_mut(),
+            env.clone(),
+            mock_info("registry", &[]),
+            InstantiateMsg {
+                owner: Addr::unchecked("owner"),
+                owner_id: "Alice".to_string(),
+                owner_pubkey: "".to_string(),
+                key: "password".to_string(),
+                registry_key: None,
+            },
+        )
+        .unwrap();
+
+        for id in ["1", "2"] {
+            execute::add_record(
+                deps.as_mut(),
+                env.clone(),
+                id.to_string(),
+                format!("Record {}", id),
+                "".to_string(),
+                "".to_string(),
+                None,
+                None,
+                vec![],
+                None,
+                Addr::unchecked("clinician"),
+            )
+            .unwrap();
+        }
+
+        let grant_access = ExecuteMsg::GrantAccess {
+            grantee: clinician.clone(),
+            record_ids: Some(vec!["1".to_string()]),
+            expires: Some(env.block.time.plus_seconds(100)),
+        };
+
+        let err = execute(
+            deps.as_mut(),
+            env.clone(),
+            mock_info("clinician", &[]),
+            grant_access.clone(),
+        )
+        .unwrap_err();
+        assert_eq!(
+            err,
+            ContractError::Unauthorized {
+                sender: clinician.clone()
+            }
+        );
+
+        // a grant that has already expired is neither stored nor logged
+        let logged = ACCESS_LOG.get_len(deps.as_ref().storage).unwrap();
+        let err = execute(
+            deps.as_mut(),
+            env.clone(),
+            mock_info("owner", &[]),
+            ExecuteMsg::GrantAccess {
+                grantee: clinician.clone(),
+                record_ids: None,
+                expires: Some(env.block.time),
+            },
+        )
+        .unwrap_err();
+        assert_eq!(
+            err,
+            ContractError::InvalidExpiry {
+                expires: env.block.time
+            }
+        );
+        assert_eq!(ACCESS_LOG.get_len(deps.as_ref().storage).unwrap(), logged);
+
+        assert_eq!(
+            read_grant(deps.as_ref(), &env, &clinician).unwrap_err(),
+            ContractError::InvalidPermit
+        );
+
+        let resp = execute(
+            deps.as_mut(),
+            env.clone(),
+            mock_info("owner", &[]),
+            grant_access,
+        )
+        .unwrap();
+        let event = &resp.events[0];
+        assert_eq!(event.ty, events::EVENT_TYPE);
+        assert_eq!(event.attributes[0].value, "grant_access");
+        assert_eq!(event.attributes[1].value, "patient");
+        // the patient pseudonym is left to the registry
+        assert!(event.attributes.iter().all(|a| a.key != "patient"));
+        assert_eq!(event.attributes[2].value, CONTRACT_VERSION);
+
+        let grant = read_grant(deps.as_ref(), &env, &clinician).unwrap();
+        assert!(grant.allows("1"));
+        assert!(!grant.allows("2"));
+
+        let resp = query::list_records(deps.as_ref(), None, None, &grant).unwrap();
+        let resp: crate::msg::ListRecordsResp = from_binary(&resp).unwrap();
+        assert_eq!(resp.records.len(), 1);
+        assert_eq!(resp.records[0].id, "1");
+
+        env.block.time = env.block.time.plus_seconds(100);
+        assert_eq!(
+            read_grant(deps.as_ref(), &env, &clinician).unwrap_err(),
+            ContractError::InvalidPermit
+        );
+
+        let revoke_access = ExecuteMsg::RevokeAccess {
+            grantee: clinician.clone(),
+        };
+        execute(
+            deps.as_mut(),
+            env.clone(),
+            mock_info("registry", &[]),
+            revoke_access.clone(),
+        )
+        .unwrap();
+        let err = execute(deps.as_mut(), env, mock_info("owner", &[]), revoke_access).unwrap_err();
+        assert_eq!(err, ContractError::NonexistentGrant { grantee: clinician });
+    }
+
+    #[test]
+    fn run_break_glass() {
+        let mut deps = mock_dependencies();
+        let mut env = mock_env();
+        let paramedic = Addr::unchecked("paramedic");
+
+        instantiate(
+            deps.as_mut(),
+            env.clone(),
+            mock_info("registry", &[]),
+            InstantiateMsg {
+                owner: Addr::unchecked("owner"),
+                owner_id: "Alice".to_string(),
+                owner_pubkey: "".to_string(),
+                key: "password".to_string(),
+                registry_key: None,
+            },
+        )
+        .unwrap();
+
+        let break_glass = ExecuteMsg::BreakGlass {
+            provider: paramedic.clone(),
+            justification: "Unconscious patient in the emergency room".to_string(),
+        };
+        let err = execute(
+            deps.as_mut(),
+            env.clone(),
+            mock_info("paramedic", &[]),
+            break_glass.clone(),
+        )
+        .unwrap_err();
+        assert_eq!(
+            err,
+            ContractError::Unauthorized {
+                sender: paramedic.clone()
+            }
+        );
+
+        let err = execute(
+            deps.as_mut(),
+            env.clone(),
+            mock_info("registry", &[]),
+            ExecuteMsg::BreakGlass {
+                provider: paramedic.clone(),
+                justification: "  ".to_string(),
+            },
+        )
+        .unwrap_err();
+        assert_eq!(err, ContractError::InvalidJustification { max: 512 });
+
+        let resp = execute(
+            deps.as_mut(),
+            env.clone(),
+            mock_info("registry", &[]),
+            break_glass.clone(),
+        )
+        .unwrap();
+        assert_eq!(resp.events[0].ty, "break_glass");
+
+        let grant = read_grant(deps.as_ref(), &env, &paramedic).unwrap();
+        assert!(grant.allows("1"));
+
+        // the unlock lapses after the window
+        let mut later = env.clone();
+        later.block.time = env.block.time.plus_seconds(execute::EMERGENCY_WINDOW);
+        assert_eq!(
+            read_grant(deps.as_ref(), &later, &paramedic).unwrap_err(),
+            ContractError::InvalidPermit
+        );
+
+        // a second unlock can be disputed right away
+        env.block.time = env.block.time.plus_seconds(60);
+        execute(
+            deps.as_mut(),
+            env.clone(),
+            mock_info("registry", &[]),
+            break_glass,
+        )
+        .unwrap();
+        let dispute = ExecuteMsg::DisputeEmergencyAccess {
+            index: 1,
+            reason: "I was conscious and refused".to_string(),
+        };
+        let err = execute(
+            deps.as_mut(),
+            env.clone(),
+            mock_info("paramedic", &[]),
+            dispute.clone(),
+        )
+        .unwrap_err();
+        assert_eq!(
+            err,
+            ContractError::Unauthorized {
+                sender: paramedic.clone()
+            }
+        );
+        execute(
+            deps.as_mut(),
+            env.clone(),
+            mock_info("owner", &[]),
+            dispute.clone(),
+        )
+        .unwrap();
+        assert_eq!(
+            read_grant(deps.as_ref(), &env, &paramedic).unwrap_err(),
+            ContractError::InvalidPermit
+        );
+
+        let err =
+            execute(deps.as_mut(), env.clone(), mock_info("owner", &[]), dispute).unwrap_err();
+        assert_eq!(err, ContractError::AlreadyDisputed { index: 1 });
+        let err = execute(
+            deps.as_mut(),
+            env.clone(),
+            mock_info("owner", &[]),
+            ExecuteMsg::DisputeEmergencyAccess {
+                index: 2,
+                reason: "".to_string(),
+            },
+        )
+        .unwrap_err();
+        assert_eq!(err, ContractError::NonexistentEmergencyAccess { index: 2 });
+
+        let resp = query::get_emergency_access_log(deps.as_ref(), None, None).unwrap();
+        let resp: crate::msg::EmergencyAccessLogResp = from_binary(&resp).unwrap();
+        assert_eq!(resp.total, 2);
+        assert_eq!(resp.entries[0].provider, paramedic);
+        assert_eq!(resp.entries[0].dispute, None);
+        assert_eq!(
+            resp.entries[1].dispute.as_ref().unwrap().reason,
+            "I was conscious and refused"
+        );
+
+        let resp = query::get_access_log(deps.as_ref(), None, None).unwrap();
+        let resp: crate::msg::AccessLogResp = from_binary(&resp).unwrap();
+        let actions: Vec<_> = resp.entries.iter().map(|event| event.action).collect();
+        assert_eq!(
+            actions,
+            vec![
+                AccessAction::BreakGlass,
+                AccessAction::BreakGlass,
+                AccessAction::Dispute
+            ]
+        );
+    }
+
+    #[test]
+    fn run_amend_record() {
+        let mut deps = mock_dependencies();
+        let mut env = mock_env();
+        let clinician = Addr::unchecked("clinician");
+        let cardiologist = Addr::unchecked("cardiologist");
+
+        execute::add_record(
+            deps.as_mut(),
+            env.clone(),
+            "1".to_string(),
+            "Lipid panel".to_string(),
+            "".to_string(),
+            "LDL 160".to_string(),
+            None,
+            None,
+            vec![],
+            None,
+            clinician.clone(),
+        )
+        .unwrap();
+
+        let err = execute::add_record(
+            deps.as_mut(),
+            env.clone(),
+            "1".to_string(),
+            "Lipid panel".to_string(),
+            "".to_string(),
+            "LDL 100".to_string(),
+            None,
+            None,
+            vec![],
+            None,
+            clinician.clone(),
+        )
+        .unwrap_err();
+        assert_eq!(
+            err,
+            ContractError::RecordExists {
+                id: "1".to_string()
+            }
+        );
+
+        env.block.time = env.block.time.plus_seconds(60);
+        execute::amend_record(
+            deps.as_mut(),
+            env.clone(),
+            "1".to_string(),
+            "Lipid panel".to_string(),
+            "".to_string(),
+            "LDL 106".to_string(),
+            None,
+            None,
+            vec![],
+            None,
+            "Transcription error".to_string(),
+            cardiologist.clone(),
+        )
+        .unwrap();
+
+        let resp = query::get_record_by_id(deps.as_ref(), "1".to_string()).unwrap();
+        let record: Record = from_binary(&resp).unwrap();
+        assert_eq!(record.version, 2);
+        assert_eq!(record.data, "LDL 106");
+        assert_eq!(record.author, cardiologist);
+        assert_eq!(record.timestamp, env.block.time);
+
+        let resp = query::get_record_history(deps.as_ref(), "1".to_string()).unwrap();
+        let resp: crate::msg::RecordHistoryResp = from_binary(&resp).unwrap();
+        assert_eq!(resp.versions.len(), 2);
+        assert_eq!(resp.versions[0].data, "LDL 160");
+        assert_eq!(resp.versions[0].author, clinician);
+        assert_eq!(resp.versions[0].reason, None);
+        assert_eq!(resp.versions[1], record);
+
+        let err = execute::amend_record(
+            deps.as_mut(),
+            env,
+            "2".to_string(),
+            "".to_string(),
+            "".to_string(),
+            "".to_string(),
+            None,
+            None,
+            vec![],
+            None,
+            "".to_string(),
+            cardiologist,
+        )
+        .unwrap_err();
+        assert_eq!(
+            err,
+            ContractError::NonexistentRecord {
+                id: "2".to_string()
+            }
+        );
+    }
+
+    #[test]
+    fn run_add_observation() {
+        use crate::observation::{ChestPainType, CvdObservation, RestEcg, Sex, StSlope, Thal};
+
+        let mut deps = mock_dependencies();
+        let env = mock_env();
+        let observation = CvdObservation {
+            age: 57,
+            sex: Sex::Female,
+            chest_pain_type: ChestPainType::Asymptomatic,
+            resting_bp: 130,
+            cholesterol: 236,
+            fasting_blood_sugar: false,
+            rest_ecg: RestEcg::Normal,
+            max_heart_rate: 174,
+            exercise_angina: false,
+            oldpeak: 0,
+            slope: StSlope::Flat,
+            ca: 1,
+            thal: Thal::Normal,
+        };
+
+        let err = execute::add_record(
+            deps.as_mut(),
+            env.clone(),
+            "1".to_string(),
+            "Screening".to_string(),
+            "".to_string(),
+            "".to_string(),
+            Some(CvdObservation {
+                resting_bp: 20,
+                ..observation.clone()
+            }),
+            None,
+            vec![],
+            None,
+            Addr::unchecked("clinician"),
+        )
+        .unwrap_err();
+        assert_eq!(
+            err,
+            ContractError::InvalidObservation {
+                field: "resting_bp".to_string(),
+                value: 20,
+                min: 50,
+                max: 250,
+            }
+        );
+
+        execute::add_record(
+            deps.as_mut(),
+            env,
+            "1".to_string(),
+            "Screening".to_string(),
+            "".to_string(),
+            "".to_string(),
+            Some(observation.clone()),
+            None,
+            vec![],
+            None,
+            Addr::unchecked("clinician"),
+        )
+        .unwrap();
+
+        let resp = query::get_record_by_id(deps.as_ref(), "1".to_string()).unwrap();
+        let record: Record = from_binary(&resp).unwrap();
+        assert_eq!(record.observation, Some(observation));
+    }
+
+    #[test]
+    fn run_add_envelope() {
+        use crate::envelope::{EncryptedEnvelope, EnvelopeAlgorithm, EnvelopeRecipient};
+
+        let mut deps = mock_dependencies();
+        let env = mock_env();
+
+        let envelope = EncryptedEnvelope {
+            algorithm: EnvelopeAlgorithm::X25519XChaCha20Poly1305,
+            ephemeral_pubkey: Binary::from([9u8; 32]),
+            nonce: Binary::from([0u8; 24]),
+            ciphertext: Binary::from([7u8; 64]),
+            recipients: vec![EnvelopeRecipient {
+                key_id: "patient".to_string(),
+                sealed_key: Binary::from([3u8; 48]),
+            }],
+        };
+
+        let err = execute::add_record(
+            deps.as_mut(),
+            env.clone(),
+            "1".to_string(),
+            "ECG report".to_string(),
+            "".to_string(),
+            "sinus rhythm".to_string(),
+            None,
+            Some(envelope.clone()),
+            vec![],
+            None,
+            Addr::unchecked("clinician"),
+        )
+        .unwrap_err();
+        assert_eq!(
+            err,
+            ContractError::InvalidEnvelope {
+                reason: "encrypted records cannot carry plaintext data".to_string()
+            }
+        );
+
+        let err = execute::add_record(
+            deps.as_mut(),
+            env.clone(),
+            "1".to_string(),
+            "".to_string(),
+            "".to_string(),
+            "".to_string(),
+            None,
+            Some(EncryptedEnvelope {
+                nonce: Binary::from([0u8; 12]),
+                ..envelope.clone()
+            }),
+            vec![],
+            None,
+            Addr::unchecked("clinician"),
+        )
+        .unwrap_err();
+        assert_eq!(
+            err,
+            ContractError::InvalidEnvelope {
+                reason: "nonce has the wrong length".to_string()
+            }
+        );
+
+        // nor a readable title
+        let err = execute::add_record(
+            deps.as_mut(),
+            env.clone(),
+            "1".to_string(),
+            "ECG report".to_string(),
+            "".to_string(),
+            "".to_string(),
+            None,
+            Some(envelope.clone()),
+            vec![],
+            None,
+            Addr::unchecked("clinician"),
+        )
+        .unwrap_err();
+        assert_eq!(
+            err,
+            ContractError::InvalidEnvelope {
+                reason: "encrypted records cannot carry plaintext data".to_string()
+            }
+        );
+
+        execute::add_record(
+            deps.as_mut(),
+            env,
+            "1".to_string(),
+            "".to_string(),
+            "".to_string(),
+            "".to_string(),
+            None,
+            Some(envelope.clone()),
+            vec![],
+            None,
+            Addr::unchecked("clinician"),
+        )
+        .unwrap();
+
+        let resp = query::get_record_by_id(deps.as_ref(), "1".to_string()).unwrap();
+        let record: Record = from_binary(&resp).unwrap();
+        assert_eq!(record.envelope, Some(envelope));
+    }
+
+    #[test]
+    fn run_verify_attachment() {
+        use crate::attachment::Attachment;
+        use crate::msg::VerifyAttachmentResp;
+
+        let mut deps = mock_dependencies();
+        let env = mock_env();
+
+        let attachment = Attachment {
+            uri: "ipfs://bafybeigdyrzt5sfp7udm7hu76uh7y26nf3efuylqabf3oclgtqy55fbzdi".to_string(),
+            sha256: Binary::from(sha_256(b"12-lead ECG")),
+            mime_type: "application/xml".to_string(),
+            size: 524_288,
+        };
+
+        let err = execute::add_record(
+            deps.as_mut(),
+            env.clone(),
+            "1".to_string(),
+            "ECG".to_string(),
+            "".to_string(),
+            "".to_string(),
+            None,
+            None,
+            vec![Attachment {
+                size: 0,
+                ..attachment.clone()
+            }],
+            None,
+            Addr::unchecked("clinician"),
+        )
+        .unwrap_err();
+        assert_eq!(
+            err,
+            ContractError::InvalidAttachment {
+                uri: attachment.uri.clone(),
+                reason: "size must be positive".to_string()
+            }
+        );
+
+        execute::add_record(
+            deps.as_mut(),
+            env,
+            "1".to_string(),
+            "ECG".to_string(),
+            "".to_string(),
+            "".to_string(),
+            None,
+            None,
+            vec![attachment.clone()],
+            None,
+            Addr::unchecked("clinician"),
+        )
+        .unwrap();
+
+        let resp = query::verify_attachment(
+            deps.as_ref(),
+            "1".to_string(),
+            attachment.uri.clone(),
+            Binary::from(sha_256(b"12-lead ECG")),
+        )
+        .unwrap();
+        let resp: VerifyAttachmentResp = from_binary(&resp).unwrap();
+        assert!(resp.matches);
+        assert_eq!(resp.attachment, attachment);
+
+        let resp = query::verify_attachment(
+            deps.as_ref(),
+            "1".to_string(),
+            attachment.uri.clone(),
+            Binary::from(sha_256(b"tampered ECG")),
+        )
+        .unwrap();
+        let resp: VerifyAttachmentResp = from_binary(&resp).unwrap();
+        assert!(!resp.matches);
+
+        let err = query::verify_attachment(
+            deps.as_ref(),
+            "1".to_string(),
+            "https://pacs.hospital.example/studies/42".to_string(),
+            Binary::from(sha_256(b"12-lead ECG")),
+        )
+        .unwrap_err();
+        assert_eq!(
+            err,
+            ContractError::NonexistentAttachment {
+                record_id: "1".to_string(),
+                uri: "https://pacs.hospital.example/studies/42".to_string()
+            }
+        );
+    }
+
+    #[test]
+    fn run_access_log() {
+        use crate::msg::AccessLogResp;
+
+        let mut deps = mock_dependencies();
+        let mut env = mock_env();
+        let owner = Addr::unchecked("owner");
+        let clinician = Addr::unchecked("clinician");
+
+        instantiate(
+            deps.as_mut(),
+            env.clone(),
+            mock_info("registry", &[]),
+            InstantiateMsg {
+                owner: owner.clone(),
+                owner_id: "Alice".to_string(),
+                owner_pubkey: "".to_string(),
+                key: "password".to_string(),
+                registry_key: None,
+            },
+        )
+        .unwrap();
+
+        execute::add_record(
+            deps.as_mut(),
+            env.clone(),
+            "1".to_string(),
+            "Lipid panel".to_string(),
+            "".to_string(),
+            "LDL 160".to_string(),
+            None,
+            None,
+            vec![],
+            None,
+            clinician.clone(),
+        )
+        .unwrap();
+
+        env.block.height += 1;
+        execute(
+            deps.as_mut(),
+            env.clone(),
+            mock_info("registry", &[]),
+            ExecuteMsg::GrantAccess {
+                grantee: clinician.clone(),
+                record_ids: Some(vec!["1".to_string()]),
+                expires: None,
+            },
+        )
+        .unwrap();
+
+        env.block.height += 1;
+        execute::log_view(
+            deps.as_mut(),
+            env.clone(),
+            clinician.clone(),
+            "1".to_string(),
+        )
+        .unwrap();
+        let err = execute::log_view(
+            deps.as_mut(),
+            env.clone(),
+            clinician.clone(),
+            "2".to_string(),
+        )
+        .unwrap_err();
+        assert_eq!(
+            err,
+            ContractError::NonexistentRecord {
+                id: "2".to_string()
+            }
+        );
+
+        execute(
+            deps.as_mut(),
+            env.clone(),
+            mock_info("owner", &[]),
+            ExecuteMsg::RevokeAccess {
+                grantee: clinician.clone(),
+            },
+        )
+        .unwrap();
+
+        let resp = query::get_access_log(deps.as_ref(), None, None).unwrap();
+        let resp: AccessLogResp = from_binary(&resp).unwrap();
+        assert_eq!(resp.total, 4);
+        let actions: Vec<_> = resp.entries.iter().map(|e| (e.action, &e.actor)).collect();
+        assert_eq!(
+            actions,
+            vec![
+                (AccessAction::Add, &clinician),
+                (AccessAction::Grant, &owner),
+                (AccessAction::View, &clinician),
+                (AccessAction::Revoke, &owner),
+            ]
+        );
+        assert_eq!(resp.entries[1].grantee, Some(clinician.clone()));
+        assert_eq!(resp.entries[2].record_id, Some("1".to_string()));
+        assert_eq!(resp.entries[2].height, env.block.height);
+
+        let resp = query::get_access_log(deps.as_ref(), Some(3), Some(2)).unwrap();
+        let resp: AccessLogResp = from_binary(&resp).unwrap();
+        assert_eq!(resp.entries.len(), 1);
+        assert_eq!(resp.entries[0].action, AccessAction::Revoke);
+    }
+
+    #[test]
+    fn run_list_by_category_and_time() {
+        use crate::msg::{ListByTimeRangeResp, ListRecordsResp};
+        use crate::state::{RecordCategory, CATEGORY_INDEX, TIME_INDEX};
+
+        let mut deps = mock_dependencies();
+        let mut env = mock_env();
+        let start = env.block.time;
+
+        instantiate(
+            deps.as_mut(),
+            env.clone(),
+            mock_info("registry", &[]),
+            InstantiateMsg {
+                owner: Addr::unchecked("owner"),
+                owner_id: "Alice".to_string(),
+                owner_pubkey: "".to_string(),
+                key: "password".to_string(),
+                registry_key: None,
+            },
+        )
+        .unwrap();
+
+        for (id, category) in [
+            ("1", RecordCategory::Lab),
+            ("2", RecordCategory::Ecg),
+            ("3", RecordCategory::Lab),
+        ] {
+            execute::add_record(
+                deps.as_mut(),
+                env.clone(),
+                id.to_string(),
+                format!("Record {}", id),
+                "".to_string(),
+                "".to_string(),
+                None,
+                None,
+                vec![],
+                Some(category),
+                Addr::unchecked("clinician"),
+            )
+            .unwrap();
+            env.block.time = env.block.time.plus_seconds(100);
+        }
+
+        // filed under the wrong category
+        execute::amend_record(
+            deps.as_mut(),
+            env.clone(),
+            "1".to_string(),
+            "Record 1".to_string(),
+            "".to_string(),
+            "".to_string(),
+            None,
+            None,
+            vec![],
+            Some(RecordCategory::Ecg),
+            "Wrong category".to_string(),
+            Addr::unchecked("clinician"),
+        )
+        .unwrap();
+
+        let list_ids = |resp: Binary| -> Vec<String> {
+            let resp: ListRecordsResp = from_binary(&resp).unwrap();
+            resp.records.into_iter().map(|r| r.id).collect()
+        };
+
+        let resp = query::list_by_category(
+            deps.as_ref(),
+            RecordCategory::Lab,
+            None,
+            None,
+            &ReadGrant::default(),
+        )
+        .unwrap();
+        assert_eq!(list_ids(resp), vec!["3"]);
+
+        let resp = query::list_by_category(
+            deps.as_ref(),
+            RecordCategory::Ecg,
+            None,
+            None,
+            &ReadGrant::default(),
+        )
+        .unwrap();
+        assert_eq!(list_ids(resp), vec!["2", "1"]);
+
+        let resp = query::list_by_category(
+            deps.as_ref(),
+            RecordCategory::Ecg,
+            None,
+            Some(1),
+            &ReadGrant::default(),
+        )
+        .unwrap();
+        let first: ListRecordsResp = from_binary(&resp).unwrap();
+        assert_eq!(first.records.len(), 1);
+        assert_eq!(first.next, Some("2".to_string()));
+
+        let resp = query::list_by_category(
+            deps.as_ref(),
+            RecordCategory::Ecg,
+            first.next,
+            Some(1),
+            &ReadGrant::default(),
+        )
+        .unwrap();
+        let second: ListRecordsResp = from_binary(&resp).unwrap();
+        assert_eq!(second.records[0].id, "1");
+        assert_eq!(second.next, None);
+
+        let grant = ReadGrant {
+            record_ids: Some(vec!["1".to_string()]),
+            expires: None,
+        };
+        let resp = query::list_by_category(deps.as_ref(), RecordCategory::Ecg, None, None, &grant)
+            .unwrap();
+        assert_eq!(list_ids(resp), vec!["1"]);
+
+        // record 1 has moved on to its amended version at +300
+        let resp = query::list_by_time_range(
+            deps.as_ref(),
+            start.plus_seconds(50),
+            start.plus_seconds(250),
+            None,
+            None,
+            &ReadGrant::default(),
+        )
+        .unwrap();
+        let resp: ListByTimeRangeResp = from_binary(&resp).unwrap();
+        let ids: Vec<_> = resp.records.iter().map(|r| r.id.as_str()).collect();
+        assert_eq!(ids, vec!["2", "3"]);
+        assert_eq!(resp.next, None);
+
+        let resp = query::list_by_time_range(
+            deps.as_ref(),
+            start,
+            start.plus_seconds(400),
+            None,
+            Some(2),
+            &ReadGrant::default(),
+        )
+        .unwrap();
+        let first: ListByTimeRangeResp = from_binary(&resp).unwrap();
+        let ids: Vec<_> = first.records.iter().map(|r| r.id.as_str()).collect();
+        assert_eq!(ids, vec!["2", "3"]);
+        assert!(first.next.is_some());
+
+        let resp = query::list_by_time_range(
+            deps.as_ref(),
+            start,
+            start.plus_seconds(400),
+            first.next,
+            Some(2),
+            &ReadGrant::default(),
+        )
+        .unwrap();
+        let second: ListByTimeRangeResp = from_binary(&resp).unwrap();
+        assert_eq!(second.records.len(), 1);
+        assert_eq!(second.records[0].id, "1");
+        assert_eq!(second.records[0].category, Some(RecordCategory::Ecg));
+        assert_eq!(second.next, None);
+
+        // a zero limit still moves the cursor forward
+        let resp = query::list_by_time_range(
+            deps.as_ref(),
+            start,
+            start.plus_seconds(400),
+            None,
+            Some(0),
+            &ReadGrant::default(),
+        )
+        .unwrap();
+        let resp: ListByTimeRangeResp = from_binary(&resp).unwrap();
+        assert_eq!(resp.records.len(), 1);
+        assert!(resp.next.is_some());
+
+        // entries the grant hides still count towards the scan limit, so a
+        // page can end early with nothing in it
+        for _ in 0..4 {
+            env.block.time = env.block.time.plus_seconds(100);
+            for id in ["3", "1"] {
+                execute::amend_record(
+                    deps.as_mut(),
+                    env.clone(),
+                    id.to_string(),
+                    format!("Record {}", id),
+                    "".to_string(),
+                    "".to_string(),
+                    None,
+                    None,
+                    vec![],
+                    None,
+                    "Follow-up".to_string(),
+                    Addr::unchecked("clinician"),
+                )
+                .unwrap();
+            }
+        }
+        let grant = ReadGrant {
+            record_ids: Some(vec!["1".to_string()]),
+            expires: None,
+        };
+        let time_range = |start_after| {
+            let resp = query::list_by_time_range(
+                deps.as_ref(),
+                start.plus_seconds(250),
+                env.block.time.plus_seconds(1),
+                start_after,
+                Some(1),
+                &grant,
+            )
+            .unwrap();
+            from_binary::<ListByTimeRangeResp>(&resp).unwrap()
+        };
+        let mut pages = vec![];
+        let mut next = None;
+        loop {
+            let resp = time_range(next);
+            pages.push(resp.records.len());
+            next = resp.next;
+            if next.is_none() {
+                break;
+            }
+        }
+        // nine entries from +250, only the last of them visible and current
+        assert_eq!(pages, vec![0, 0, 1]);
+
+        execute::erase(deps.as_mut(), env.clone()).unwrap();
+        assert_eq!(TIME_INDEX.get_len(deps.as_ref().storage).unwrap(), 0);
+        for category in [RecordCategory::Lab, RecordCategory::Ecg] {
+            assert_eq!(
+                CATEGORY_INDEX
+                    .add_suffix(category.key())
+                    .get_len(deps.as_ref().storage)
+                    .unwrap(),
+                0
+            );
+        }
+
+        // an index entry without its record is skipped
+        CATEGORY_INDEX
+            .add_suffix(RecordCategory::Ecg.key())
+            .insert(deps.as_mut().storage, &"9".to_string(), &env.block.time)
+            .unwrap();
+        let resp = query::list_by_category(
+            deps.as_ref(),
+            RecordCategory::Ecg,
+            None,
+            None,
+            &ReadGrant::default(),
+        )
+        .unwrap();
+        assert!(list_ids(resp).is_empty());
+    }
+
+    #[test]
+    fn run_erase() {
+        let mut deps = mock_dependencies();
+        let env = mock_env();
+        let clinician = Addr::unchecked("clinician");
+
+        instantiate(
+            deps.as_mut(),
+            env.clone(),
+            mock_info("registry", &[]),
+            InstantiateMsg {
+                owner: Addr::unchecked("owner"),
+                owner_id: "Alice".to_string(),
+                owner_pubkey: "".to_string(),
+                key: "password".to_string(),
+                registry_key: None,
+            },
+        )
+        .unwrap();
+
+        for id in ["1", "2"] {
+            execute::add_record(
+                deps.as_mut(),
+                env.clone(),
+                id.to_string(),
+                format!("Record {}", id),
+                "".to_string(),
+                "LDL 160".to_string(),
+                None,
+                None,
+                vec![],
+                None,
+                clinician.clone(),
+            )
+            .unwrap();
+        }
+        execute(
+            deps.as_mut(),
+            env.clone(),
+            mock_info("owner", &[]),
+            ExecuteMsg::GrantAccess {
+                grantee: clinician.clone(),
+                record_ids: None,
+                expires: None,
+            },
+        )
+        .unwrap();
+        execute(
+            deps.as_mut(),
+            env.clone(),
+            mock_info("owner", &[]),
+            ExecuteMsg::GrantConsent {
+                purpose: crate::consent::ConsentPurpose::ModelTraining,
+                expires: env.block.time.plus_seconds(100),
+            },
+        )
+        .unwrap();
+        execute(
+            deps.as_mut(),
+            env.clone(),
+            mock_info("registry", &[]),
+            ExecuteMsg::BreakGlass {
+                provider: Addr::unchecked("paramedic"),
+                justification: "Unconscious patient".to_string(),
+            },
+        )
+        .unwrap();
+
+        let err = execute(
+            deps.as_mut(),
+            env.clone(),
+            mock_info("clinician", &[]),
+            ExecuteMsg::Erase {},
+        )
+        .unwrap_err();
+        assert_eq!(
+            err,
+            ContractError::Unauthorized {
+                sender: clinician.clone()
+            }
+        );
+
+        execute(
+            deps.as_mut(),
+            env.clone(),
+            mock_info("registry", &[]),
+            ExecuteMsg::Erase {},
+        )
+        .unwrap();
+
+        let resp = query::list_records(deps.as_ref(), None, None, &ReadGrant::default()).unwrap();
+        let resp: crate::msg::ListRecordsResp = from_binary(&resp).unwrap();
+        assert!(resp.records.is_empty());
+        assert_eq!(
+            query::get_record_history(deps.as_ref(), "1".to_string()).unwrap_err(),
+            ContractError::NonexistentRecord {
+                id: "1".to_string()
+            }
+        );
+
+        let history = RECORD_HISTORY.add_suffix(b"1");
+        assert_eq!(history.get_len(deps.as_ref().storage).unwrap(), 0);
+        assert_eq!(
+            read_grant(deps.as_ref(), &env, &clinician).unwrap_err(),
+            ContractError::InvalidPermit
+        );
+
+        let storage = deps.as_ref().storage;
+        assert_eq!(ACCESS_LOG.get_len(storage).unwrap(), 0);
+        assert_eq!(EMERGENCY_LOG.get_len(storage).unwrap(), 0);
+        assert_eq!(EMERGENCY_UNLOCKS.get_len(storage).unwrap(), 0);
+        assert_eq!(CONSENT_STORE.get_len(storage).unwrap(), 0);
+    }
+
+    #[test]
+    fn run_migrate() {
+        use crate::state::{LegacyRecord, LEGACY_RECORD_STORE};
+
+        let mut deps = mock_dependencies();
+        let env = mock_env();
+        let registry = Addr::unchecked("registry");
+
+        // state as left by 0.1.0
+        OWNER
+            .save(deps.as_mut().storage, &Addr::unchecked("owner"))
+            .unwrap();
+        REGISTRY.save(deps.as_mut().storage, &registry).unwrap();
+        for id in ["1", "2"] {
+            LEGACY_RECORD_STORE
+                .insert(
+                    deps.as_mut().storage,
+                    &id.to_string(),
+                    &LegacyRecord {
+                        title: format!("Record {}", id),
+                        timestamp: env.block.time,
+                        description: "".to_string(),
+                        data: "LDL 160".to_string(),
+                    },
+                )
+                .unwrap();
+        }
+
+        migrate(
+            deps.as_mut(),
+            env.clone(),
+            MigrateMsg { registry_key: None },
+        )
+        .unwrap();
+
+        let resp = query::get_record_by_id(deps.as_ref(), "2".to_string()).unwrap();
+        let record: Record = from_binary(&resp).unwrap();
+        assert_eq!(record.title, "Record 2");
+        assert_eq!(record.version, 1);
+        assert_eq!(record.author, registry);
+
+        let resp = query::get_record_history(deps.as_ref(), "1".to_string()).unwrap();
+        let resp: crate::msg::RecordHistoryResp = from_binary(&resp).unwrap();
+        assert_eq!(resp.versions.len(), 1);
+
+        let info = CONTRACT_INFO.load(deps.as_ref().storage).unwrap();
+        assert_eq!(info.name, "record-manager");
+        assert_eq!(info.version, CONTRACT_VERSION);
+
+        // migrating again to the same version leaves the records alone
+        migrate(
+            deps.as_mut(),
+            env.clone(),
+            MigrateMsg { registry_key: None },
+        )
+        .unwrap();
+        let resp = query::get_record_history(deps.as_ref(), "1".to_string()).unwrap();
+        let resp: crate::msg::RecordHistoryResp = from_binary(&resp).unwrap();
+        assert_eq!(resp.versions.len(), 1);
+
+        CONTRACT_INFO
+            .save(
+                deps.as_mut().storage,
+                &ContractInfo {
+                    name: CONTRACT_NAME.to_string(),
+                    version: "10.0.0".to_string(),
+                },
+            )
+            .unwrap();
+        let err = migrate(
+            deps.as_mut(),
+            env.clone(),
+            MigrateMsg { registry_key: None },
+        )
+        .unwrap_err();
+        assert_eq!(
+            err,
+            ContractError::CannotDowngrade {
+                stored: "10.0.0".to_string(),
+                current: CONTRACT_VERSION.to_string(),
+            }
+        );
+
+        CONTRACT_INFO
+            .save(
+                deps.as_mut().storage,
+                &ContractInfo {
+                    name: "registry".to_string(),
+                    version: "0.1.0".to_string(),
+                },
+            )
+            .unwrap();
+        let err = migrate(deps.as_mut(), env, MigrateMsg { registry_key: None }).unwrap_err();
+        assert_eq!(
+            err,
+            ContractError::ContractNameMismatch {
+                expected: "record-manager".to_string(),
+                found: "registry".to_string(),
+            }
+        );
+    }
+
+    #[test]
+    fn run_consent() {
+        use crate::consent::ConsentPurpose;
+        use crate::msg::{CheckConsentResp, ConsentsResp};
+
+        let mut deps = mock_dependencies();
+        let mut env = mock_env();
+
+        instantiate(
+            deps.as_mut(),
+            env.clone(),
+            mock_info("registry", &[]),
+            InstantiateMsg {
+                owner: Addr::unchecked("owner"),
+                owner_id: "Alice".to_string(),
+                owner_pubkey: "".to_string(),
+                key: "password".to_string(),
+                registry_key: Some("registry key".to_string()),
+            },
+        )
+        .unwrap();
+
+        let study = ConsentPurpose::Study {
+            study_id: "cleveland-2".to_string(),
+        };
+        let check_consent = |deps: Deps, env: &Env, registry_key: &str| {
+            query(
+                deps,
+                env.clone(),
+                QueryMsg::CheckConsent {
+                    registry_key: registry_key.to_string(),
+                    purpose: study.clone(),
+                },
+            )
+            .map(|resp| from_binary::<CheckConsentResp>(&resp).unwrap())
+        };
+
+        let err = execute(
+            deps.as_mut(),
+            env.clone(),
+            mock_info("clinician", &[]),
+            ExecuteMsg::GrantConsent {
+                purpose: study.clone(),
+                expires: env.block.time.plus_seconds(100),
+            },
+        )
+        .unwrap_err();
+        assert_eq!(
+            err,
+            ContractError::Unauthorized {
+                sender: Addr::unchecked("clinician")
+            }
+        );
+
+        let err = execute(
+            deps.as_mut(),
+            env.clone(),
+            mock_info("owner", &[]),
+            ExecuteMsg::GrantConsent {
+                purpose: study.clone(),
+                expires: env.block.time,
+            },
+        )
+        .unwrap_err();
+        assert_eq!(
+            err,
+            ContractError::InvalidExpiry {
+                expires: env.block.time
+            }
+        );
+
+        for purpose in [study.clone(), ConsentPurpose::ModelTraining] {
+            execute(
+                deps.as_mut(),
+                env.clone(),
+                mock_info("owner", &[]),
+                ExecuteMsg::GrantConsent {
+                    purpose,
+                    expires: env.block.time.plus_seconds(100),
+                },
+            )
+            .unwrap();
+        }
+
+        let resp = query::list_consents(deps.as_ref()).unwrap();
+        let resp: ConsentsResp = from_binary(&resp).unwrap();
+        assert_eq!(resp.consents.len(), 2);
+        assert_eq!(resp.consents[0].granted_at, env.block.time);
+
+        assert_eq!(
+            check_consent(deps.as_ref(), &env, "wrong key").unwrap_err(),
+            ContractError::InvalidRegistryKey
+        );
+        assert!(
+            check_consent(deps.as_ref(), &env, "registry key")
+                .unwrap()
+                .active
+        );
+
+        env.block.time = env.block.time.plus_seconds(100);
+        assert!(
+            !check_consent(deps.as_ref(), &env, "registry key")
+                .unwrap()
+                .active
+        );
+
+        execute(
+            deps.as_mut(),
+            env.clone(),
+            mock_info("registry", &[]),
+            ExecuteMsg::WithdrawConsent {
+                purpose: study.clone(),
+            },
+        )
+        .unwrap();
+        assert_eq!(
+            check_consent(deps.as_ref(), &env, "registry key").unwrap(),
+            CheckConsentResp {
+                active: false,
+                expires: None
+            }
+        );
+        let err = execute(
+            deps.as_mut(),
+            env,
+            mock_info("owner", &[]),
+            ExecuteMsg::WithdrawConsent {
+                purpose: study.clone(),
+            },
+        )
+        .unwrap_err();
+        assert_eq!(err, ContractError::NonexistentConsent { purpose: study });
+    }
+
+    #[test]
+    fn run_research_data() {
+        use crate::consent::ConsentPurpose;
+        use crate::msg::ResearchDataResp;
+        use crate::observation::{ChestPainType, CvdObservation, RestEcg, Sex, StSlope, Thal};
+
+        let mut deps = mock_dependencies();
+        let env = mock_env();
+        let observation = CvdObservation {
+            age: 57,
+            sex: Sex::Female,
+            chest_pain_type: ChestPainType::Asymptomatic,
+            resting_bp: 130,
+            cholesterol: 236,
+            fasting_blood_sugar: false,
+            rest_ecg: RestEcg::Normal,
+            max_heart_rate: 174,
+            exercise_angina: false,
+            oldpeak: 0,
+            slope: StSlope::Flat,
+            ca: 1,
+            thal: Thal::Normal,
+        };
+
+        for (id, observation) in [("1", Some(observation.clone())), ("2", None)] {
+            execute::add_record(
+                deps.as_mut(),
+                env.clone(),
+                id.to_string(),
+                "Screening".to_string(),
+                "".to_string(),
+                "".to_string(),
+                observation,
+                None,
+                vec![],
+                None,
+                Addr::unchecked("clinician"),
+            )
+            .unwrap();
+        }
+
+        let err = query::get_research_data(deps.as_ref(), &env, ConsentPurpose::ModelTraining)
+            .unwrap_err();
+        assert_eq!(
+            err,
+            ContractError::NoConsent {
+                purpose: ConsentPurpose::ModelTraining
+            }
+        );
+
+        execute::grant_consent(
+            deps.as_mut(),
+            env.clone(),
+            ConsentPurpose::ModelTraining,
+            env.block.time.plus_seconds(3600),
+        )
+        .unwrap();
+
+        let resp =
+            query::get_research_data(deps.as_ref(), &env, ConsentPurpose::ModelTraining).unwrap();
+        let resp: ResearchDataResp = from_binary(&resp).unwrap();
+        assert_eq!(resp.observations.len(), 1);
+        assert_eq!(resp.observations[0].observation, observation);
+
+        let period = resp.observations[0].period.seconds();
+        assert_eq!(period % query::RESEARCH_PERIOD, 0);
+        assert!(env.block.time.seconds() - period < query::RESEARCH_PERIOD);
+
+        let err = query::get_research_data(
+            deps.as_ref(),
+            &env,
+            ConsentPurpose::Study {
+                study_id: "other".to_string(),
+            },
+        )
+        .unwrap_err();
+        assert!(matches!(err, ContractError::NoConsent { .. }));
     }
 }
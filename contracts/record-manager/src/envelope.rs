@@ -0,0 +1,192 @@
+use cosmwasm_std::Binary;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+use crate::error::ContractError;
+
+/// Length of the symmetric key the payload is encrypted with.
+pub const CONTENT_KEY_LEN: usize = 32;
+/// Length of the authentication tag both supported ciphers append.
+pub const TAG_LEN: usize = 16;
+
+/// Encryption scheme of an envelope. In both schemes the payload is encrypted
+/// with a random content key, which is then sealed for every recipient with a
+/// key agreed between the ephemeral key and the recipient's public key.
+#[derive(Serialize, Deserialize, JsonSchema, PartialEq, Eq, Debug, Clone, Copy)]
+#[serde(rename_all = "snake_case")]
+pub enum EnvelopeAlgorithm {
+    /// ECDH over secp256k1, HKDF-SHA256 and AES-256-GCM
+    Secp256k1Aes256Gcm,
+    /// X25519, HKDF-SHA256 and XChaCha20-Poly1305
+    X25519XChaCha20Poly1305,
+}
+
+impl EnvelopeAlgorithm {
+    fn public_key_len(&self) -> usize {
+        match self {
+            // compressed point
+            EnvelopeAlgorithm::Secp256k1Aes256Gcm => 33,
+            EnvelopeAlgorithm::X25519XChaCha20Poly1305 => 32,
+        }
+    }
+
+    fn nonce_len(&self) -> usize {
+        match self {
+            EnvelopeAlgorithm::Secp256k1Aes256Gcm => 12,
+            EnvelopeAlgorithm::X25519XChaCha20Poly1305 => 24,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, JsonSchema, PartialEq, Eq, Debug, Clone)]
+#[serde(rename_all = "snake_case")]
+pub struct EnvelopeRecipient {
+    /// Base64 SHA-256 of the recipient's public key. The registry only relays
+    /// envelopes with a recipient for the owner's registered key.
+    pub key_id: String,
+    /// Content key sealed for the recipient
+    pub sealed_key: Binary,
+}
+
+/// Record payload encrypted on the client. The contract only checks its shape
+/// and returns it unchanged; it never sees the plaintext.
+#[derive(Serialize, Deserialize, JsonSchema, PartialEq, Eq, Debug, Clone)]
+#[serde(rename_all = "snake_case")]
+pub struct EncryptedEnvelope {
+    pub algorithm: EnvelopeAlgorithm,
+    pub ephemeral_pubkey: Binary,
+    pub nonce: Binary,
+    pub ciphertext: Binary,
+    pub recipients: Vec<EnvelopeRecipient>,
+}
+
+impl EncryptedEnvelope {
+    pub fn validate(&self) -> Result<(), ContractError> {
+        let algorithm = self.algorithm;
+
+        if self.ephemeral_pubkey.len() != algorithm.public_key_len() {
+            return Err(invalid("ephemeral_pubkey has the wrong length"));
+        }
+        if algorithm == EnvelopeAlgorithm::Secp256k1Aes256Gcm
+            && !matches!(self.ephemeral_pubkey[0], 0x02 | 0x03)
+        {
+            return Err(invalid(
+                "ephemeral_pubkey is not a compressed secp256k1 key",
+            ));
+        }
+        if self.nonce.len() != algorithm.nonce_len() {
+            return Err(invalid("nonce has the wrong length"));
+        }
+        if self.ciphertext.len() <= TAG_LEN {
+            return Err(invalid("ciphertext is too short"));
+        }
+
+        if self.recipients.is_empty() {
+            return Err(invalid("envelope has no recipients"));
+        }
+        for (i, recipient) in self.recipients.iter().enumerate() {
+            if recipient.key_id.is_empty() {
+                return Err(invalid("recipient key_id is empty"));
+            }
+            if self.recipients[..i]
+                .iter()
+                .any(|other| other.key_id == recipient.key_id)
+            {
+                return Err(invalid("recipient key_id is repeated"));
+            }
+            if recipient.sealed_key.len() != CONTENT_KEY_LEN + TAG_LEN {
+                return Err(invalid("sealed_key has the wrong length"));
+            }
+        }
+
+        Ok(())
+    }
+}
+
+fn invalid(reason: &str) -> ContractError {
+    ContractError::InvalidEnvelope {
+        reason: reason.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sample_envelope() -> EncryptedEnvelope {
+        let mut ephemeral_pubkey = vec![0x02];
+        ephemeral_pubkey.extend([1u8; 32]);
+
+        EncryptedEnvelope {
+            algorithm: EnvelopeAlgorithm::Secp256k1Aes256Gcm,
+            ephemeral_pubkey: Binary::from(ephemeral_pubkey),
+            nonce: Binary::from([0u8; 12]),
+            ciphertext: Binary::from([7u8; 64]),
+            recipients: vec![
+                EnvelopeRecipient {
+                    key_id: "patient".to_string(),
+                    sealed_key: Binary::from([3u8; 48]),
+                },
+                EnvelopeRecipient {
+                    key_id: "cardiologist".to_string(),
+                    sealed_key: Binary::from([4u8; 48]),
+                },
+            ],
+        }
+    }
+
+    #[test]
+    fn validate_envelope() {
+        assert_eq!(sample_envelope().validate(), Ok(()));
+
+        let envelope = EncryptedEnvelope {
+            ephemeral_pubkey: Binary::from([0x04; 33]),
+            ..sample_envelope()
+        };
+        assert_eq!(
+            envelope.validate(),
+            Err(invalid(
+                "ephemeral_pubkey is not a compressed secp256k1 key"
+            ))
+        );
+
+        let envelope = EncryptedEnvelope {
+            algorithm: EnvelopeAlgorithm::X25519XChaCha20Poly1305,
+            ..sample_envelope()
+        };
+        assert_eq!(
+            envelope.validate(),
+            Err(invalid("ephemeral_pubkey has the wrong length"))
+        );
+
+        let envelope = EncryptedEnvelope {
+            algorithm: EnvelopeAlgorithm::X25519XChaCha20Poly1305,
+            ephemeral_pubkey: Binary::from([9u8; 32]),
+            nonce: Binary::from([0u8; 24]),
+            ..sample_envelope()
+        };
+        assert_eq!(envelope.validate(), Ok(()));
+
+        let envelope = EncryptedEnvelope {
+            ciphertext: Binary::from([7u8; 16]),
+            ..sample_envelope()
+        };
+        assert_eq!(envelope.validate(), Err(invalid("ciphertext is too short")));
+
+        let mut envelope = sample_envelope();
+        envelope.recipients[1].key_id = "patient".to_string();
+        assert_eq!(
+            envelope.validate(),
+            Err(invalid("recipient key_id is repeated"))
+        );
+
+        let envelope = EncryptedEnvelope {
+            recipients: vec![],
+            ..sample_envelope()
+        };
+        assert_eq!(
+            envelope.validate(),
+            Err(invalid("envelope has no recipients"))
+        );
+    }
+}
@@ -1,4 +1,6 @@
-use cosmwasm_std::{Addr, StdError};
+use cosmwasm_std::{Addr, StdError, Timestamp};
+
+use crate::consent::ConsentPurpose;
 use thiserror::Error;
 
 #[derive(Error, Debug, PartialEq)]
@@ -11,4 +13,43 @@ pub enum ContractError {
     InvalidPermit,
     #[error("No record with id {id} found")]
     NonexistentRecord { id: String },
+    #[error("Observation field {field} is {value}, expected a value between {min} and {max}")]
+    InvalidObservation {
+        field: String,
+        value: i64,
+        min: i64,
+        max: i64,
+    },
+    #[error("Invalid encrypted envelope: {reason}")]
+    InvalidEnvelope { reason: String },
+    #[error("Invalid attachment {uri}: {reason}")]
+    InvalidAttachment { uri: String, reason: String },
+    #[error("A record has {count} attachments, at most {max} are allowed")]
+    TooManyAttachments { count: usize, max: usize },
+    #[error("Record {record_id} has no attachment {uri}")]
+    NonexistentAttachment { record_id: String, uri: String },
+    #[error("A record with id {id} already exists")]
+    RecordExists { id: String },
+    #[error("No access grant for {grantee} found")]
+    NonexistentGrant { grantee: Addr },
+    #[error("No consent for {purpose:?} found")]
+    NonexistentConsent { purpose: ConsentPurpose },
+    #[error("Patient has not consented to {purpose:?}")]
+    NoConsent { purpose: ConsentPurpose },
+    #[error("Grants and consents must expire after the current block time, got {expires}")]
+    InvalidExpiry { expires: Timestamp },
+    #[error("Justification must be between 1 and {max} characters")]
+    InvalidJustification { max: usize },
+    #[error("No emergency access at position {index} found")]
+    NonexistentEmergencyAccess { index: u32 },
+    #[error("Emergency access at position {index} is already disputed")]
+    AlreadyDisputed { index: u32 },
+    #[error("Registry key is not valid")]
+    InvalidRegistryKey,
+    #[error("Cannot migrate {found} to {expected}")]
+    ContractNameMismatch { expected: String, found: String },
+    #[error("Cannot migrate from version {stored} to older version {current}")]
+    CannotDowngrade { stored: String, current: String },
+    #[error("Invalid contract version {version}")]
+    InvalidVersion { version: String },
 }
@@ -1,8 +1,14 @@
-use cosmwasm_std::Addr;
+use cosmwasm_std::{Addr, Binary, Timestamp};
 use schemars::JsonSchema;
 use secret_toolkit::permit::Permit;
 use serde::{Deserialize, Serialize};
 
+use crate::attachment::Attachment;
+use crate::consent::{Consent, ConsentPurpose};
+use crate::envelope::EncryptedEnvelope;
+use crate::observation::CvdObservation;
+use crate::state::{AccessEvent, EmergencyAccess, Record, RecordCategory};
+
 #[derive(Serialize, Deserialize, PartialEq, Debug, Clone)]
 #[serde(rename_all = "snake_case")]
 pub struct InstantiateMsg {
@@ -10,18 +16,102 @@ pub struct InstantiateMsg {
     pub owner_id: String,
     pub owner_pubkey: String,
     pub key: String,
+    pub registry_key: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, PartialEq, Debug, Clone)]
+#[serde(rename_all = "snake_case")]
+pub struct MigrateMsg {
+    /// Sets the registry key of record managers created before it existed
+    pub registry_key: Option<String>,
 }
 
 #[derive(Serialize, Deserialize, PartialEq, Debug, Clone)]
 #[serde(rename_all = "snake_case")]
+#[allow(clippy::large_enum_variant)]
 pub enum ExecuteMsg {
     AddRecord {
         id: String,
         title: String,
         description: String,
         data: String,
+        observation: Option<CvdObservation>,
+        envelope: Option<EncryptedEnvelope>,
+        #[serde(default)]
+        attachments: Vec<Attachment>,
+        category: Option<RecordCategory>,
+        permit: Permit<RecordPermissions>,
+        author: Option<Addr>,
+    },
+    AmendRecord {
+        id: String,
+        title: String,
+        description: String,
+        data: String,
+        observation: Option<CvdObservation>,
+        envelope: Option<EncryptedEnvelope>,
+        #[serde(default)]
+        attachments: Vec<Attachment>,
+        category: Option<RecordCategory>,
+        reason: String,
         permit: Permit<RecordPermissions>,
+        author: Option<Addr>,
     },
+    RevokePermit {
+        permit_name: String,
+    },
+    GrantAccess {
+        grantee: Addr,
+        record_ids: Option<Vec<String>>,
+        expires: Option<Timestamp>,
+    },
+    RevokeAccess {
+        grantee: Addr,
+    },
+    GrantConsent {
+        purpose: ConsentPurpose,
+        expires: Timestamp,
+    },
+    WithdrawConsent {
+        purpose: ConsentPurpose,
+    },
+    /// Erases every record, its history and all grants.
+    Erase {},
+    /// Records in the access log that the permit signer viewed `record_id`.
+    LogView {
+        record_id: String,
+        permit: Permit<RecordPermissions>,
+    },
+    /// Unlocks every record for `provider` during `EMERGENCY_WINDOW`. Only the
+    /// registry may send it, after checking the provider's emergency role.
+    BreakGlass {
+        provider: Addr,
+        justification: String,
+    },
+    /// Lets the patient contest an emergency unlock, ending it if still active.
+    DisputeEmergencyAccess {
+        index: u32,
+        reason: String,
+    },
+}
+
+impl ExecuteMsg {
+    /// Name of the message in emitted events.
+    pub fn action(&self) -> &'static str {
+        match self {
+            ExecuteMsg::AddRecord { .. } => "add_record",
+            ExecuteMsg::AmendRecord { .. } => "amend_record",
+            ExecuteMsg::RevokePermit { .. } => "revoke_permit",
+            ExecuteMsg::GrantAccess { .. } => "grant_access",
+            ExecuteMsg::RevokeAccess { .. } => "revoke_access",
+            ExecuteMsg::GrantConsent { .. } => "grant_consent",
+            ExecuteMsg::WithdrawConsent { .. } => "withdraw_consent",
+            ExecuteMsg::Erase {} => "erase",
+            ExecuteMsg::LogView { .. } => "log_view",
+            ExecuteMsg::BreakGlass { .. } => "break_glass",
+            ExecuteMsg::DisputeEmergencyAccess { .. } => "dispute_emergency_access",
+        }
+    }
 }
 
 #[derive(Serialize, Deserialize, PartialEq, Debug, Clone)]
@@ -31,6 +121,59 @@ pub enum QueryMsg {
         permit: Permit<RecordPermissions>,
         record_id: String,
     },
+    ListRecords {
+        permit: Permit<RecordPermissions>,
+        start_after: Option<String>,
+        limit: Option<u32>,
+    },
+    ViewHistory {
+        permit: Permit<RecordPermissions>,
+        record_id: String,
+    },
+    ListByCategory {
+        permit: Permit<RecordPermissions>,
+        category: RecordCategory,
+        start_after: Option<String>,
+        limit: Option<u32>,
+    },
+    /// Records whose latest version was written in `[from, to)`.
+    ListByTimeRange {
+        permit: Permit<RecordPermissions>,
+        from: Timestamp,
+        to: Timestamp,
+        start_after: Option<u32>,
+        limit: Option<u32>,
+    },
+    /// Checks that `sha256` is the digest recorded for the attachment at `uri`.
+    VerifyAttachment {
+        permit: Permit<RecordPermissions>,
+        record_id: String,
+        uri: String,
+        sha256: Binary,
+    },
+    AccessLog {
+        permit: Permit<RecordPermissions>,
+        start: Option<u32>,
+        limit: Option<u32>,
+    },
+    Consents {
+        permit: Permit<RecordPermissions>,
+    },
+    EmergencyAccessLog {
+        permit: Permit<RecordPermissions>,
+        start: Option<u32>,
+        limit: Option<u32>,
+    },
+    /// Lets the registry check consent before using records across patients.
+    CheckConsent {
+        registry_key: String,
+        purpose: ConsentPurpose,
+    },
+    /// De-identified observations for a purpose the patient consented to.
+    ResearchData {
+        registry_key: String,
+        purpose: ConsentPurpose,
+    },
 }
 
 #[derive(Serialize, Deserialize, PartialEq, Debug, Clone)]
@@ -43,9 +186,93 @@ pub struct CallbackInfo {
     pub key: String,
 }
 
+#[derive(Serialize, Deserialize, PartialEq, Debug, Clone)]
+#[serde(rename_all = "snake_case")]
+pub struct RecordSummary {
+    pub id: String,
+    pub title: String,
+    pub timestamp: Timestamp,
+    pub category: Option<RecordCategory>,
+}
+
+#[derive(Serialize, Deserialize, PartialEq, Debug, Clone)]
+#[serde(rename_all = "snake_case")]
+pub struct ListRecordsResp {
+    pub records: Vec<RecordSummary>,
+    /// Value of `start_after` for the next page, if there are more records
+    pub next: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, PartialEq, Debug, Clone)]
+#[serde(rename_all = "snake_case")]
+pub struct ListByTimeRangeResp {
+    pub records: Vec<RecordSummary>,
+    /// Value of `start_after` for the next page, if there are more records
+    pub next: Option<u32>,
+}
+
+#[derive(Serialize, Deserialize, PartialEq, Debug, Clone)]
+#[serde(rename_all = "snake_case")]
+pub struct RecordHistoryResp {
+    pub versions: Vec<Record>,
+}
+
+#[derive(Serialize, Deserialize, PartialEq, Debug, Clone)]
+#[serde(rename_all = "snake_case")]
+pub struct VerifyAttachmentResp {
+    pub matches: bool,
+    pub attachment: Attachment,
+}
+
+#[derive(Serialize, Deserialize, PartialEq, Debug, Clone)]
+#[serde(rename_all = "snake_case")]
+pub struct AccessLogResp {
+    pub entries: Vec<AccessEvent>,
+    pub total: u32,
+}
+
+#[derive(Serialize, Deserialize, PartialEq, Debug, Clone)]
+#[serde(rename_all = "snake_case")]
+pub struct EmergencyAccessLogResp {
+    pub entries: Vec<EmergencyAccess>,
+    pub total: u32,
+}
+
+#[derive(Serialize, Deserialize, PartialEq, Debug, Clone)]
+#[serde(rename_all = "snake_case")]
+pub struct ConsentsResp {
+    pub consents: Vec<Consent>,
+}
+
+#[derive(Serialize, Deserialize, PartialEq, Debug, Clone)]
+#[serde(rename_all = "snake_case")]
+pub struct CheckConsentResp {
+    pub active: bool,
+    pub expires: Option<Timestamp>,
+}
+
+/// Observation stripped of its record id, with its time coarsened to the start
+/// of a 30-day window.
+#[derive(Serialize, Deserialize, PartialEq, Debug, Clone)]
+#[serde(rename_all = "snake_case")]
+pub struct ResearchObservation {
+    pub period: Timestamp,
+    pub observation: CvdObservation,
+}
+
+#[derive(Serialize, Deserialize, PartialEq, Debug, Clone)]
+#[serde(rename_all = "snake_case")]
+pub struct ResearchDataResp {
+    pub observations: Vec<ResearchObservation>,
+}
+
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, JsonSchema)]
 #[serde(rename_all = "snake_case")]
 pub enum RecordPermissions {
     ViewById { record_id: String },
+    ViewAll,
     Add,
+    Amend { record_id: String },
+    ViewAccessLog,
+    ViewConsents,
 }
@@ -0,0 +1,144 @@
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+use crate::error::ContractError;
+
+#[derive(Serialize, Deserialize, JsonSchema, PartialEq, Eq, Debug, Clone, Copy)]
+#[serde(rename_all = "snake_case")]
+pub enum Sex {
+    Female,
+    Male,
+}
+
+#[derive(Serialize, Deserialize, JsonSchema, PartialEq, Eq, Debug, Clone, Copy)]
+#[serde(rename_all = "snake_case")]
+pub enum ChestPainType {
+    TypicalAngina,
+    AtypicalAngina,
+    NonAnginalPain,
+    Asymptomatic,
+}
+
+#[derive(Serialize, Deserialize, JsonSchema, PartialEq, Eq, Debug, Clone, Copy)]
+#[serde(rename_all = "snake_case")]
+pub enum RestEcg {
+    Normal,
+    StTWaveAbnormality,
+    LeftVentricularHypertrophy,
+}
+
+#[derive(Serialize, Deserialize, JsonSchema, PartialEq, Eq, Debug, Clone, Copy)]
+#[serde(rename_all = "snake_case")]
+pub enum StSlope {
+    Upsloping,
+    Flat,
+    Downsloping,
+}
+
+#[derive(Serialize, Deserialize, JsonSchema, PartialEq, Eq, Debug, Clone, Copy)]
+#[serde(rename_all = "snake_case")]
+pub enum Thal {
+    Normal,
+    FixedDefect,
+    ReversibleDefect,
+}
+
+/// Structured heart-disease observation following the features of the UCI
+/// Cleveland dataset. Contracts cannot use floating point, so `oldpeak` is kept
+/// in tenths of a millimetre.
+#[derive(Serialize, Deserialize, JsonSchema, PartialEq, Eq, Debug, Clone)]
+#[serde(rename_all = "snake_case")]
+pub struct CvdObservation {
+    /// Age in years
+    pub age: u8,
+    pub sex: Sex,
+    pub chest_pain_type: ChestPainType,
+    /// Resting blood pressure in mm Hg
+    pub resting_bp: u16,
+    /// Serum cholesterol in mg/dl
+    pub cholesterol: u16,
+    /// Fasting blood sugar above 120 mg/dl
+    pub fasting_blood_sugar: bool,
+    pub rest_ecg: RestEcg,
+    /// Maximum heart rate achieved in beats per minute
+    pub max_heart_rate: u16,
+    pub exercise_angina: bool,
+    /// ST depression induced by exercise relative to rest, in tenths of a mm
+    pub oldpeak: i16,
+    pub slope: StSlope,
+    /// Number of major vessels coloured by fluoroscopy
+    pub ca: u8,
+    pub thal: Thal,
+}
+
+impl CvdObservation {
+    pub fn validate(&self) -> Result<(), ContractError> {
+        check_range("age", self.age as i64, 1, 120)?;
+        check_range("resting_bp", self.resting_bp as i64, 50, 250)?;
+        check_range("cholesterol", self.cholesterol as i64, 50, 700)?;
+        check_range("max_heart_rate", self.max_heart_rate as i64, 40, 250)?;
+        check_range("oldpeak", self.oldpeak as i64, -100, 100)?;
+        check_range("ca", self.ca as i64, 0, 3)?;
+        Ok(())
+    }
+}
+
+fn check_range(field: &str, value: i64, min: i64, max: i64) -> Result<(), ContractError> {
+    if value < min || value > max {
+        return Err(ContractError::InvalidObservation {
+            field: field.to_string(),
+            value,
+            min,
+            max,
+        });
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sample_observation() -> CvdObservation {
+        CvdObservation {
+            age: 63,
+            sex: Sex::Male,
+            chest_pain_type: ChestPainType::TypicalAngina,
+            resting_bp: 145,
+            cholesterol: 233,
+            fasting_blood_sugar: true,
+            rest_ecg: RestEcg::LeftVentricularHypertrophy,
+            max_heart_rate: 150,
+            exercise_angina: false,
+            oldpeak: 23,
+            slope: StSlope::Downsloping,
+            ca: 0,
+            thal: Thal::FixedDefect,
+        }
+    }
+
+    #[test]
+    fn validate_ranges() {
+        assert_eq!(sample_observation().validate(), Ok(()));
+
+        let observation = CvdObservation {
+            cholesterol: 1200,
+            ..sample_observation()
+        };
+        assert_eq!(
+            observation.validate(),
+            Err(ContractError::InvalidObservation {
+                field: "cholesterol".to_string(),
+                value: 1200,
+                min: 50,
+                max: 700,
+            })
+        );
+
+        let observation = CvdObservation {
+            ca: 4,
+            ..sample_observation()
+        };
+        assert!(observation.validate().is_err());
+    }
+}
@@ -1,7 +1,13 @@
 use cosmwasm_std::{Addr, Timestamp};
-use secret_toolkit::storage::{Item, Keymap};
+use schemars::JsonSchema;
+use secret_toolkit::storage::{AppendStore, Item, Keymap};
 use serde::{Deserialize, Serialize};
 
+use crate::attachment::Attachment;
+use crate::consent::{Consent, ConsentPurpose};
+use crate::envelope::EncryptedEnvelope;
+use crate::observation::CvdObservation;
+
 #[derive(Serialize, Deserialize, PartialEq, Debug, Clone)]
 #[serde(rename_all = "snake_case")]
 pub struct Record {
@@ -9,8 +15,185 @@ pub struct Record {
     pub timestamp: Timestamp,
     pub description: String,
     pub data: String,
+    pub observation: Option<CvdObservation>,
+    pub envelope: Option<EncryptedEnvelope>,
+    pub attachments: Vec<Attachment>,
+    pub category: Option<RecordCategory>,
+    pub version: u32,
+    pub author: Addr,
+    pub reason: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, JsonSchema, PartialEq, Eq, Debug, Clone, Copy)]
+#[serde(rename_all = "snake_case")]
+pub enum RecordCategory {
+    Lab,
+    Vitals,
+    Ecg,
+    Imaging,
+    Medication,
+    Diagnosis,
+    Prediction,
 }
 
+impl RecordCategory {
+    /// Suffix of the category's index in `CATEGORY_INDEX`.
+    pub fn key(&self) -> &'static [u8] {
+        match self {
+            RecordCategory::Lab => b"lab",
+            RecordCategory::Vitals => b"vitals",
+            RecordCategory::Ecg => b"ecg",
+            RecordCategory::Imaging => b"imaging",
+            RecordCategory::Medication => b"medication",
+            RecordCategory::Diagnosis => b"diagnosis",
+            RecordCategory::Prediction => b"prediction",
+        }
+    }
+}
+
+/// Entry of `TIME_INDEX`. It is stale once the record has a newer version.
+#[derive(Serialize, Deserialize, PartialEq, Debug, Clone)]
+#[serde(rename_all = "snake_case")]
+pub struct TimeIndexEntry {
+    pub timestamp: Timestamp,
+    pub record_id: String,
+    pub version: u32,
+}
+
+#[derive(Serialize, Deserialize, PartialEq, Debug, Clone, Default)]
+#[serde(rename_all = "snake_case")]
+pub struct ReadGrant {
+    pub record_ids: Option<Vec<String>>,
+    pub expires: Option<Timestamp>,
+}
+
+impl Record {
+    /// Blank record written over erased history entries.
+    pub fn tombstone(timestamp: Timestamp) -> Self {
+        Record {
+            title: String::new(),
+            timestamp,
+            description: String::new(),
+            data: String::new(),
+            observation: None,
+            envelope: None,
+            attachments: vec![],
+            category: None,
+            version: 0,
+            author: Addr::unchecked(""),
+            reason: None,
+        }
+    }
+}
+
+impl ReadGrant {
+    pub fn is_active(&self, now: &Timestamp) -> bool {
+        match &self.expires {
+            Some(expires) => now < expires,
+            None => true,
+        }
+    }
+
+    pub fn allows(&self, record_id: &str) -> bool {
+        match &self.record_ids {
+            Some(record_ids) => record_ids.iter().any(|id| id == record_id),
+            None => true,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, PartialEq, Eq, Debug, Clone, Copy)]
+#[serde(rename_all = "snake_case")]
+pub enum AccessAction {
+    Add,
+    View,
+    Amend,
+    Grant,
+    Revoke,
+    Erase,
+    BreakGlass,
+    Dispute,
+}
+
+/// Entry of the access log. `grantee` is only set for grant and revoke entries,
+/// `record_id` for everything but a grant covering all records.
+#[derive(Serialize, Deserialize, PartialEq, Debug, Clone)]
+#[serde(rename_all = "snake_case")]
+pub struct AccessEvent {
+    pub actor: Addr,
+    pub action: AccessAction,
+    pub record_id: Option<String>,
+    pub grantee: Option<Addr>,
+    pub time: Timestamp,
+    pub height: u64,
+}
+
+/// Emergency unlock of the records by a provider the registry designated for
+/// break-glass access. The patient can dispute it afterwards.
+#[derive(Serialize, Deserialize, PartialEq, Debug, Clone)]
+#[serde(rename_all = "snake_case")]
+pub struct EmergencyAccess {
+    pub provider: Addr,
+    pub justification: String,
+    pub time: Timestamp,
+    pub height: u64,
+    pub expires: Timestamp,
+    pub dispute: Option<Dispute>,
+}
+
+#[derive(Serialize, Deserialize, PartialEq, Debug, Clone)]
+#[serde(rename_all = "snake_case")]
+pub struct Dispute {
+    pub reason: String,
+    pub time: Timestamp,
+}
+
+impl EmergencyAccess {
+    /// A disputed unlock ends right away.
+    pub fn is_active(&self, now: &Timestamp) -> bool {
+        self.dispute.is_none() && now < &self.expires
+    }
+}
+
+#[derive(Serialize, Deserialize, PartialEq, Debug, Clone)]
+#[serde(rename_all = "snake_case")]
+pub struct ContractInfo {
+    pub name: String,
+    pub version: String,
+}
+
+/// Record layout of 0.1.0, which stored no contract info.
+#[derive(Serialize, Deserialize, PartialEq, Debug, Clone)]
+#[serde(rename_all = "snake_case")]
+pub struct LegacyRecord {
+    pub title: String,
+    pub timestamp: Timestamp,
+    pub description: String,
+    pub data: String,
+}
+
+pub const PREFIX_REVOKED_PERMITS: &str = "revoked_permits";
+
+pub static CONTRACT_INFO: Item<ContractInfo> = Item::new(b"contract_info");
 pub static OWNER: Item<Addr> = Item::new(b"owner");
 pub static REGISTRY: Item<Addr> = Item::new(b"registry");
-pub static RECORD_STORE: Keymap<String, Record> = Keymap::new(b"record");
+/// Hash of the key the registry authenticates its own queries with.
+pub static REGISTRY_KEY: Item<Vec<u8>> = Item::new(b"registry_key");
+pub const RECORD_STORE: Keymap<String, Record> = Keymap::new(b"record");
+/// `RECORD_STORE` as written by 0.1.0; only read while migrating.
+pub const LEGACY_RECORD_STORE: Keymap<String, LegacyRecord> = Keymap::new(b"record");
+/// Every version of a record, oldest first; suffixed with the record id.
+pub const RECORD_HISTORY: AppendStore<Record> = AppendStore::new(b"record_history");
+/// Ids of the records currently in each category, with the time they were
+/// filed there; suffixed with `RecordCategory::key`.
+pub const CATEGORY_INDEX: Keymap<String, Timestamp> = Keymap::new(b"category_index");
+/// Every record version in the order it was written, and so by timestamp.
+pub const TIME_INDEX: AppendStore<TimeIndexEntry> = AppendStore::new(b"time_index");
+pub const GRANT_STORE: Keymap<Addr, ReadGrant> = Keymap::new(b"grant");
+/// Append-only log of every add, amend, grant and attested view.
+pub const ACCESS_LOG: AppendStore<AccessEvent> = AppendStore::new(b"access_log");
+/// Every emergency unlock, oldest first.
+pub const EMERGENCY_LOG: AppendStore<EmergencyAccess> = AppendStore::new(b"emergency_log");
+/// Position in `EMERGENCY_LOG` of each provider's latest unlock.
+pub const EMERGENCY_UNLOCKS: Keymap<Addr, u32> = Keymap::new(b"emergency_unlock");
+pub const CONSENT_STORE: Keymap<ConsentPurpose, Consent> = Keymap::new(b"consent");
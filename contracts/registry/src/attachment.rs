@@ -0,0 +1,15 @@
+//! Mirror of the record-manager attachment type, used to relay attachment
+//! references to the patient record managers.
+
+use cosmwasm_std::Binary;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+#[derive(Serialize, Deserialize, JsonSchema, PartialEq, Eq, Debug, Clone)]
+#[serde(rename_all = "snake_case")]
+pub struct Attachment {
+    pub uri: String,
+    pub sha256: Binary,
+    pub mime_type: String,
+    pub size: u64,
+}
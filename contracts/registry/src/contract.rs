@@ -1,18 +1,45 @@
 use crate::error::ContractError;
-use crate::msg::{ExecuteMsg, InfoResp, InstantiateMsg, QueryMsg, QueryWithPermit};
-use crate::state::{OffspringInfo, Person, OFFSPRING, OWNER, PERSON_STORE};
+use crate::msg::{
+    ExecuteMsg, InfoResp, InstantiateMsg, MigrateMsg, OffspringExecuteMsg, QueryMsg,
+    QueryWithPermit,
+};
+use crate::state::{
+    ContractInfo, OffspringInfo, Person, Provider, ProviderRole, ProviderRoles, CONTRACT_INDEX,
+    CONTRACT_INFO, OFFSPRING, OWNER, PATIENT_INDEX, PERSON_STORE, PROVIDER_STORE, REGISTRY_KEY,
+};
 use cosmwasm_std::{
     to_binary, Addr, Binary, Deps, DepsMut, Env, MessageInfo, Reply, Response, StdResult, SubMsg,
 };
+use events::{patient_pseudonym, record_id_hash, ActorRole, AuditEvent};
+use secret_toolkit::crypto::sha_256;
 use secret_toolkit::viewing_key::{ViewingKey, ViewingKeyStore};
 
+const CONTRACT_NAME: &str = env!("CARGO_PKG_NAME");
+const CONTRACT_VERSION: &str = env!("CARGO_PKG_VERSION");
+
+const DEFAULT_LIMIT: u32 = 10;
+const MAX_LIMIT: u32 = 30;
+/// Registered patients `AggregateStats` queries at most in one transaction
+const MAX_AGGREGATE_PATIENTS: u32 = 100;
+
 pub fn instantiate(
     deps: DepsMut,
-    _env: Env,
+    env: Env,
     _info: MessageInfo,
     msg: InstantiateMsg,
 ) -> StdResult<Response> {
+    CONTRACT_INFO.save(
+        deps.storage,
+        &ContractInfo {
+            name: CONTRACT_NAME.to_string(),
+            version: CONTRACT_VERSION.to_string(),
+        },
+    )?;
     OWNER.save(deps.storage, &msg.owner)?;
+    if let Some(random) = env.block.random {
+        ViewingKey::set_seed(deps.storage, random.as_slice());
+        REGISTRY_KEY.save(deps.storage, &registry_key(&random))?;
+    }
     OFFSPRING.save(
         deps.storage,
         &OffspringInfo {
@@ -29,7 +56,10 @@ pub fn execute(
     info: MessageInfo,
     msg: ExecuteMsg,
 ) -> Result<Response, ContractError> {
-    match msg {
+    // built up front, while a patient being deregistered can still be resolved
+    let event = audit_event(deps.as_ref(), &info.sender, &msg)?;
+
+    let response = match msg {
         ExecuteMsg::Register {
             id,
             address,
@@ -41,22 +71,358 @@ pub fn execute(
             record_id,
             record,
             permit,
-        } => execute::add_record(deps, patient_id, record_id, record, permit),
-    }
+        } => execute::add_record(deps, info, patient_id, record_id, record, permit),
+        ExecuteMsg::AmendRecord {
+            patient_id,
+            record_id,
+            record,
+            reason,
+            permit,
+        } => execute::amend_record(deps, info, patient_id, record_id, record, reason, permit),
+        ExecuteMsg::RevokePermit {
+            patient_id,
+            permit_name,
+        } => execute::forward_as_patient(
+            deps,
+            info,
+            patient_id,
+            OffspringExecuteMsg::RevokePermit { permit_name },
+        ),
+        ExecuteMsg::GrantAccess {
+            patient_id,
+            grantee,
+            record_ids,
+            expires,
+        } => execute::forward_as_patient(
+            deps,
+            info,
+            patient_id,
+            OffspringExecuteMsg::GrantAccess {
+                grantee,
+                record_ids,
+                expires,
+            },
+        ),
+        ExecuteMsg::RevokeAccess {
+            patient_id,
+            grantee,
+        } => execute::forward_as_patient(
+            deps,
+            info,
+            patient_id,
+            OffspringExecuteMsg::RevokeAccess { grantee },
+        ),
+        ExecuteMsg::ProposeNewOwner { address } => execute::propose_new_owner(deps, info, address),
+        ExecuteMsg::AcceptOwnership {} => execute::accept_ownership(deps, info),
+        ExecuteMsg::CancelOwnershipProposal {} => execute::cancel_ownership_proposal(deps, info),
+        ExecuteMsg::AddProvider {
+            address,
+            name,
+            roles,
+        } => execute::add_provider(deps, info, address, name, roles),
+        ExecuteMsg::UpdateProvider {
+            address,
+            name,
+            roles,
+        } => execute::update_provider(deps, info, address, name, roles),
+        ExecuteMsg::RemoveProvider { address } => execute::remove_provider(deps, info, address),
+        ExecuteMsg::UpdateOffspring { code_id, code_hash } => {
+            execute::update_offspring(deps, info, code_id, code_hash)
+        }
+        ExecuteMsg::MigrateRecordManagers { start_after, limit } => {
+            execute::migrate_record_managers(deps, info, start_after, limit)
+        }
+        ExecuteMsg::GrantConsent {
+            patient_id,
+            purpose,
+            expires,
+        } => execute::forward_as_patient(
+            deps,
+            info,
+            patient_id,
+            OffspringExecuteMsg::GrantConsent { purpose, expires },
+        ),
+        ExecuteMsg::WithdrawConsent {
+            patient_id,
+            purpose,
+        } => execute::forward_as_patient(
+            deps,
+            info,
+            patient_id,
+            OffspringExecuteMsg::WithdrawConsent { purpose },
+        ),
+        ExecuteMsg::Deregister { patient_id } => execute::deregister(deps, env, info, patient_id),
+        ExecuteMsg::CreateViewingKey { entropy } => {
+            execute::create_viewing_key(deps, env, info, entropy)
+        }
+        ExecuteMsg::SetViewingKey { key } => execute::set_viewing_key(deps, info, key),
+        ExecuteMsg::LogView {
+            patient_id,
+            record_id,
+            permit,
+        } => execute::log_view(deps, info, patient_id, record_id, permit),
+        ExecuteMsg::RegisterModel {
+            version,
+            model,
+            weights_hash,
+            dataset_hash,
+            metrics,
+        } => execute::register_model(
+            deps,
+            env,
+            info,
+            version,
+            model,
+            weights_hash,
+            dataset_hash,
+            metrics,
+        ),
+        ExecuteMsg::ActivateModel { version } => execute::activate_model(deps, env, info, version),
+        ExecuteMsg::RetireModel { version } => execute::retire_model(deps, env, info, version),
+        ExecuteMsg::SetPrivacyBudget { account, epsilon } => {
+            execute::set_privacy_budget(deps, info, account, epsilon)
+        }
+        ExecuteMsg::AggregateStats {
+            purpose,
+            statistic,
+            cohort,
+            epsilon,
+        } => execute::aggregate_stats(deps, env, info, purpose, statistic, cohort, epsilon),
+        ExecuteMsg::BreakGlass {
+            patient_id,
+            justification,
+        } => execute::break_glass(deps, info, patient_id, justification),
+        ExecuteMsg::DisputeEmergencyAccess {
+            patient_id,
+            index,
+            reason,
+        } => execute::forward_as_patient(
+            deps,
+            info,
+            patient_id,
+            OffspringExecuteMsg::DisputeEmergencyAccess { index, reason },
+        ),
+    }?;
+
+    Ok(response.add_event(event.into_event(CONTRACT_VERSION)))
 }
 
-pub fn query(deps: Deps, _env: Env, msg: QueryMsg) -> Result<Binary, ContractError> {
+pub fn query(deps: Deps, env: Env, msg: QueryMsg) -> Result<Binary, ContractError> {
     match msg {
         QueryMsg::Info { id, key } => {
-            to_binary(&query::get_info(deps, id, key).unwrap()).map_err(Into::into)
+            to_binary(&query::get_info(deps, id, key)?).map_err(Into::into)
         }
         QueryMsg::WithPermit { id, permit, query } => match query {
             QueryWithPermit::ViewById { record_id } => {
                 query::get_record_by_id(deps, id, permit, record_id)
             }
+            QueryWithPermit::ListRecords { start_after, limit } => {
+                query::list_records(deps, id, permit, start_after, limit)
+            }
+            QueryWithPermit::ViewHistory { record_id } => {
+                query::get_record_history(deps, id, permit, record_id)
+            }
+            QueryWithPermit::ListByCategory {
+                category,
+                start_after,
+                limit,
+            } => query::list_by_category(deps, id, permit, category, start_after, limit),
+            QueryWithPermit::ListByTimeRange {
+                from,
+                to,
+                start_after,
+                limit,
+            } => query::list_by_time_range(deps, id, permit, from, to, start_after, limit),
+            QueryWithPermit::VerifyAttachment {
+                record_id,
+                uri,
+                sha256,
+            } => query::verify_attachment(deps, id, permit, record_id, uri, sha256),
+            QueryWithPermit::PredictRisk { record_id } => {
+                query::predict_risk(deps, id, permit, record_id)
+            }
+            QueryWithPermit::AccessLog { start, limit } => {
+                query::get_access_log(deps, id, permit, start, limit)
+            }
+            QueryWithPermit::EmergencyAccessLog { start, limit } => {
+                query::get_emergency_access_log(deps, id, permit, start, limit)
+            }
+            QueryWithPermit::Consents {} => query::list_consents(deps, id, permit),
             QueryWithPermit::Add => Ok(to_binary("").unwrap()),
         },
+        QueryMsg::CheckConsent {
+            id,
+            purpose,
+            permit,
+        } => {
+            let account = query::validate_registry_permit(deps, &env, &permit)?;
+            if check_admin(deps, &account).is_err() {
+                check_provider_role(deps, &account, ProviderRole::ReadWithConsent)?;
+            }
+            query::get_consent(deps, id, purpose)
+        }
+        QueryMsg::ResearchExport {
+            purpose,
+            permit,
+            start,
+            limit,
+        } => {
+            let account = query::validate_registry_permit(deps, &env, &permit)?;
+            check_provider_role(deps, &account, ProviderRole::Research)?;
+            query::research_export(deps, purpose, start, limit)
+        }
+        QueryMsg::Registration { permit } => {
+            let account = query::validate_registry_permit(deps, &env, &permit)?;
+            query::get_registration(deps, &account)
+        }
+        QueryMsg::RegistrationByContract {
+            contract_address,
+            permit,
+        } => {
+            let account = query::validate_registry_permit(deps, &env, &permit)?;
+            query::get_registration_by_contract(deps, &account, contract_address)
+        }
+        QueryMsg::Owner {} => query::get_owner(deps),
+        QueryMsg::Providers { start_after, limit } => {
+            query::list_providers(deps, start_after, limit)
+        }
+        QueryMsg::ErasureReceipts { start, limit } => {
+            query::list_erasure_receipts(deps, start, limit)
+        }
+        QueryMsg::ActiveModel {} => query::get_active_model(deps),
+        QueryMsg::PrivacyBudget { permit } => {
+            let account = query::validate_registry_permit(deps, &env, &permit)?;
+            query::get_privacy_budget(deps, &account)
+        }
+        QueryMsg::Models { start_after, limit } => query::list_models(deps, start_after, limit),
+    }
+}
+
+pub fn migrate(mut deps: DepsMut, env: Env, _msg: MigrateMsg) -> Result<Response, ContractError> {
+    match CONTRACT_INFO.may_load(deps.storage)? {
+        Some(info) => {
+            if info.name != CONTRACT_NAME {
+                return Err(ContractError::ContractNameMismatch {
+                    expected: CONTRACT_NAME.to_string(),
+                    found: info.name,
+                });
+            }
+            if parse_version(&info.version)? > parse_version(CONTRACT_VERSION)? {
+                return Err(ContractError::CannotDowngrade {
+                    stored: info.version,
+                    current: CONTRACT_VERSION.to_string(),
+                });
+            }
+        }
+        // 0.1.0 did not store its contract info
+        None => migrate::from_v0_1(deps.branch(), &env)?,
     }
+
+    CONTRACT_INFO.save(
+        deps.storage,
+        &ContractInfo {
+            name: CONTRACT_NAME.to_string(),
+            version: CONTRACT_VERSION.to_string(),
+        },
+    )?;
+
+    Ok(Response::new())
+}
+
+/// Derives the key the registry authenticates itself to record managers with.
+fn registry_key(random: &Binary) -> String {
+    Binary::from(sha_256(&[random.as_slice(), b"registry_key"].concat()).as_slice()).to_base64()
+}
+
+/// Key the patient pseudonyms and record id hashes in events are derived from.
+/// Record managers store the same hash of the registry key.
+fn event_key(deps: Deps) -> StdResult<Vec<u8>> {
+    Ok(REGISTRY_KEY
+        .may_load(deps.storage)?
+        .map(|registry_key| sha_256(registry_key.as_bytes()).to_vec())
+        .unwrap_or_default())
+}
+
+/// Hash of an erased patient id for its receipt. It is keyed with the event
+/// key so that nobody outside the registry can test guessed ids against it.
+fn erased_id_hash(deps: Deps, patient_id: &str) -> StdResult<Binary> {
+    let input = [
+        event_key(deps)?.as_slice(),
+        b"erasure",
+        patient_id.as_bytes(),
+    ]
+    .concat();
+    Ok(Binary::from(sha_256(&input).as_slice()))
+}
+
+/// Describes `msg` for indexers without revealing the patient or record ids.
+fn audit_event(deps: Deps, sender: &Addr, msg: &ExecuteMsg) -> StdResult<AuditEvent> {
+    let (patient_id, record_id) = match msg {
+        ExecuteMsg::AddRecord {
+            patient_id,
+            record_id,
+            ..
+        }
+        | ExecuteMsg::AmendRecord {
+            patient_id,
+            record_id,
+            ..
+        }
+        | ExecuteMsg::LogView {
+            patient_id,
+            record_id,
+            ..
+        } => (Some(patient_id.clone()), Some(record_id)),
+        ExecuteMsg::RevokePermit { patient_id, .. }
+        | ExecuteMsg::GrantAccess { patient_id, .. }
+        | ExecuteMsg::RevokeAccess { patient_id, .. }
+        | ExecuteMsg::GrantConsent { patient_id, .. }
+        | ExecuteMsg::WithdrawConsent { patient_id, .. }
+        | ExecuteMsg::Deregister { patient_id }
+        | ExecuteMsg::BreakGlass { patient_id, .. }
+        | ExecuteMsg::DisputeEmergencyAccess { patient_id, .. } => (Some(patient_id.clone()), None),
+        ExecuteMsg::CreateViewingKey { .. } | ExecuteMsg::SetViewingKey { .. } => {
+            (PATIENT_INDEX.get(deps.storage, sender), None)
+        }
+        _ => (None, None),
+    };
+
+    let actor_role = if OWNER.load(deps.storage)? == *sender {
+        ActorRole::Admin
+    } else if PATIENT_INDEX.contains(deps.storage, sender) {
+        ActorRole::Patient
+    } else if PROVIDER_STORE.contains(deps.storage, sender) {
+        ActorRole::Provider
+    } else {
+        ActorRole::Other
+    };
+
+    let event_key = event_key(deps)?;
+    let record_manager = patient_id
+        .and_then(|id| PERSON_STORE.get(deps.storage, &id))
+        .map(|person| person.contract_address);
+
+    Ok(AuditEvent {
+        action: msg.action(),
+        actor_role,
+        patient: record_manager
+            .as_ref()
+            .map(|address| patient_pseudonym(&event_key, address)),
+        record_id_hash: record_manager
+            .as_ref()
+            .zip(record_id)
+            .map(|(address, id)| record_id_hash(&event_key, address, id)),
+    })
+}
+
+fn parse_version(version: &str) -> Result<Vec<u64>, ContractError> {
+    version
+        .split('.')
+        .map(|part| part.parse::<u64>())
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|_| ContractError::InvalidVersion {
+            version: version.to_string(),
+        })
 }
 
 pub fn reply(deps: DepsMut, _env: Env, msg: Reply) -> Result<Response, ContractError> {
@@ -66,9 +432,50 @@ pub fn reply(deps: DepsMut, _env: Env, msg: Reply) -> Result<Response, ContractE
     }
 }
 
+fn check_admin(deps: Deps, sender: &Addr) -> Result<(), ContractError> {
+    if OWNER.load(deps.storage)? != *sender {
+        return Err(ContractError::Unauthorized {
+            sender: sender.clone(),
+        });
+    }
+    Ok(())
+}
+
+fn check_provider_role(deps: Deps, sender: &Addr, role: ProviderRole) -> Result<(), ContractError> {
+    match PROVIDER_STORE.get(deps.storage, sender) {
+        Some(provider) if provider.roles.has(role) => Ok(()),
+        Some(_) => Err(ContractError::MissingRole {
+            sender: sender.clone(),
+            role,
+        }),
+        None => Err(ContractError::Unauthorized {
+            sender: sender.clone(),
+        }),
+    }
+}
+
+/// Checks that `sender` may read patient records, either with the patient's
+/// consent or by breaking the glass in an emergency.
+fn check_reader_role(deps: Deps, sender: &Addr) -> Result<(), ContractError> {
+    check_provider_role(deps, sender, ProviderRole::ReadWithConsent)
+        .or_else(|err| check_provider_role(deps, sender, ProviderRole::BreakGlass).map_err(|_| err))
+}
+
 mod execute {
     use super::*;
-    use crate::msg::{AddRecordMsg, OffspringInstantiateMsg, Record, RecordPermissions};
+    use crate::msg::{
+        AddRecordMsg, AggregateStatsResp, AmendRecordMsg, ConsentPurpose, CreateViewingKeyResp,
+        MigrateRecordManagersResp, OffspringInstantiateMsg, OffspringMigrateMsg, Record,
+        RecordPermissions,
+    };
+    use crate::privacy::{add_laplace_noise, FeatureRange, Statistic};
+    use crate::risk::RiskModel;
+    use crate::state::{
+        ErasureReceipt, ErasureRequester, ModelMetric, ModelStatus, ModelVersion, ACTIVE_MODEL,
+        ERASURE_RECEIPTS, MODEL_STORE, PENDING_OWNER, PRIVACY_BUDGETS,
+    };
+    use cosmwasm_std::{Decimal, Event, Storage, WasmMsg};
+    use cosmwasm_storage::PrefixedStorage;
     use secret_toolkit::{permit::Permit, utils::InitCallback};
 
     pub fn register(
@@ -80,24 +487,24 @@ mod execute {
         pubkey: String,
         key: String,
     ) -> Result<Response, ContractError> {
-        if OWNER.load(deps.storage).unwrap() != info.sender {
-            return Err(ContractError::Unauthorized {
-                sender: info.sender,
-            });
+        if check_admin(deps.as_ref(), &info.sender).is_err() {
+            check_provider_role(deps.as_ref(), &info.sender, ProviderRole::Register)?;
         }
+        check_unregistered(deps.as_ref(), &id, &address)?;
 
         let initmsg = OffspringInstantiateMsg {
             owner: address,
             owner_id: id,
             owner_pubkey: pubkey,
-            key: key,
+            key,
+            registry_key: REGISTRY_KEY.may_load(deps.storage)?,
         };
 
         let offspring = OFFSPRING.load(deps.storage).unwrap();
 
         let init_submsg = SubMsg::reply_always(
             initmsg.to_cosmos_msg(
-                None,
+                Some(env.contract.address.to_string()),
                 env.block.random.unwrap().to_string(),
                 offspring.code_id,
                 offspring.code_hash,
@@ -111,165 +518,3002 @@ mod execute {
 
     pub fn add_record(
         deps: DepsMut,
+        info: MessageInfo,
         patient_id: String,
         record_id: String,
         record: Record,
         permit: Permit<RecordPermissions>,
     ) -> Result<Response, ContractError> {
+        check_provider_role(deps.as_ref(), &info.sender, ProviderRole::WriteRecords)?;
+
         if !PERSON_STORE.contains(deps.storage, &patient_id) {
             return Err(ContractError::NonexistentUser { id: patient_id });
         }
 
-        let offspring = OFFSPRING.load(deps.storage).unwrap();
         let person = PERSON_STORE.get(deps.storage, &patient_id).unwrap();
-
-        let mut permit = permit.clone();
-        permit.params.allowed_tokens = vec![person.contract_address.to_string()];
-        permit.signature.pub_key.value = to_binary(&person.pubkey)?;
+        check_envelope(&record, &person)?;
 
         let execute_msg = AddRecordMsg {
             id: record_id,
             title: record.title,
             description: record.description,
             data: record.data,
-            permit: permit,
+            observation: record.observation,
+            envelope: record.envelope,
+            attachments: record.attachments,
+            category: record.category,
+            permit: scope_write_permit(permit, &person)?,
+            author: Some(info.sender),
         };
 
         let processed_msg = execute_msg
             .clone()
-            .into_cosmos_msg(person.contract_address.to_string(), offspring.code_hash)?;
+            .into_cosmos_msg(person.contract_address.to_string(), person.code_hash)?;
 
         Ok(Response::new().add_message(processed_msg))
     }
-}
 
-mod query {
-    use crate::msg::{OffspringQueryMsg, Record, RecordPermissions};
-    use cosmwasm_std::{QueryRequest, WasmQuery};
-    use secret_toolkit::permit::Permit;
-
-    use super::*;
+    pub fn amend_record(
+        deps: DepsMut,
+        info: MessageInfo,
+        patient_id: String,
+        record_id: String,
+        record: Record,
+        reason: String,
+        permit: Permit<RecordPermissions>,
+    ) -> Result<Response, ContractError> {
+        check_provider_role(deps.as_ref(), &info.sender, ProviderRole::WriteRecords)?;
 
-    pub fn get_info(deps: Deps, id: String, key: String) -> Result<InfoResp, ContractError> {
-        if !PERSON_STORE.contains(deps.storage, &id) {
-            return Err(ContractError::NonexistentUser { id: id });
+        if !PERSON_STORE.contains(deps.storage, &patient_id) {
+            return Err(ContractError::NonexistentUser { id: patient_id });
         }
 
-        let auth = ViewingKey::check(deps.storage, &id, &key);
+        let person = PERSON_STORE.get(deps.storage, &patient_id).unwrap();
+        check_envelope(&record, &person)?;
 
-        match auth.is_ok() {
-            true => {
-                let person = PERSON_STORE.get(deps.storage, &id).unwrap();
-                let resp = InfoResp {
-                    address: person.address,
-                    contract_address: person.contract_address,
-                    pubkey: person.pubkey,
-                };
-                Ok(resp)
-            }
-            false => Err(ContractError::InvalidKey { key: key }),
+        let processed_msg = OffspringExecuteMsg::AmendRecord(AmendRecordMsg {
+            id: record_id,
+            title: record.title,
+            description: record.description,
+            data: record.data,
+            observation: record.observation,
+            envelope: record.envelope,
+            attachments: record.attachments,
+            category: record.category,
+            reason,
+            permit: scope_write_permit(permit, &person)?,
+            author: Some(info.sender),
+        })
+        .into_cosmos_msg(person.contract_address.to_string(), person.code_hash)?;
+
+        Ok(Response::new().add_message(processed_msg))
+    }
+
+    /// An encrypted record must at least be readable by its owner.
+    fn check_envelope(record: &Record, person: &Person) -> Result<(), ContractError> {
+        if let Some(envelope) = &record.envelope {
+            envelope.check_owner(&Binary::from_base64(&person.pubkey)?)?;
         }
+        Ok(())
     }
 
-    pub fn get_record_by_id(
-        deps: Deps,
-        id: String,
+    fn scope_write_permit(
         permit: Permit<RecordPermissions>,
-        record_id: String,
-    ) -> Result<Binary, ContractError> {
-        if !PERSON_STORE.contains(deps.storage, &id) {
-            return Err(ContractError::NonexistentUser { id: id });
-        }
+        person: &Person,
+    ) -> StdResult<Permit<RecordPermissions>> {
+        let mut permit = permit;
+        permit.params.allowed_tokens = vec![person.contract_address.to_string()];
+        permit.signature.pub_key.value = Binary::from_base64(&person.pubkey)?;
+        Ok(permit)
+    }
 
-        let offspring = OFFSPRING.load(deps.storage).unwrap();
-        let person = PERSON_STORE.get(deps.storage, &id).unwrap();
+    /// Forwards a message that only the patient may send to their record manager.
+    pub fn forward_as_patient(
+        deps: DepsMut,
+        info: MessageInfo,
+        patient_id: String,
+        msg: OffspringExecuteMsg,
+    ) -> Result<Response, ContractError> {
+        if !PERSON_STORE.contains(deps.storage, &patient_id) {
+            return Err(ContractError::NonexistentUser { id: patient_id });
+        }
 
-        let mut permit = permit.clone();
-        permit.params.allowed_tokens = vec![person.contract_address.to_string()];
+        let person = PERSON_STORE.get(deps.storage, &patient_id).unwrap();
 
-        let query_msg: OffspringQueryMsg = OffspringQueryMsg::ViewById {
-            permit: permit,
-            record_id: record_id,
-        };
+        if person.address != info.sender {
+            return Err(ContractError::Unauthorized {
+                sender: info.sender,
+            });
+        }
 
-        let query_response: Record = deps.querier.query(&QueryRequest::Wasm(WasmQuery::Smart {
-            contract_addr: person.contract_address.to_string(),
-            code_hash: offspring.code_hash,
-            msg: to_binary(&query_msg)?,
-        }))?;
+        let processed_msg =
+            msg.into_cosmos_msg(person.contract_address.to_string(), person.code_hash)?;
 
-        Ok(to_binary(&query_response).unwrap())
+        Ok(Response::new().add_message(processed_msg))
     }
-}
 
-mod reply {
-    use super::*;
-    use crate::msg::OffspringResp;
-    use cosmwasm_std::{from_binary, SubMsgResult};
+    /// Removes a patient on their own request or the admin's, tells their record
+    /// manager to erase its records and leaves an erasure receipt.
+    pub fn deregister(
+        deps: DepsMut,
+        env: Env,
+        info: MessageInfo,
+        patient_id: String,
+    ) -> Result<Response, ContractError> {
+        let person = match PERSON_STORE.get(deps.storage, &patient_id) {
+            Some(person) => person,
+            None => return Err(ContractError::NonexistentUser { id: patient_id }),
+        };
 
-    pub fn handle_instantiate_reply(deps: DepsMut, msg: Reply) -> Result<Response, ContractError> {
-        match msg.result {
-            SubMsgResult::Ok(s) => match s.data {
-                Some(bin) => {
-                    let resp: OffspringResp = from_binary(&bin)?;
+        let requested_by = if person.address == info.sender {
+            ErasureRequester::Patient
+        } else {
+            check_admin(deps.as_ref(), &info.sender)?;
+            ErasureRequester::Admin
+        };
 
-                    PERSON_STORE.insert(
-                        deps.storage,
-                        &resp.owner_id,
-                        &Person {
-                            address: resp.owner_address,
-                            contract_address: resp.offspring_address,
-                            pubkey: resp.owner_pubkey,
-                        },
-                    )?;
+        // record managers instantiated by 0.1.0 do not understand `Erase`, so
+        // only the registry forgets the patient
+        let mut response = Response::new();
+        if !person.legacy {
+            response = response.add_message(
+                OffspringExecuteMsg::Erase {}
+                    .into_cosmos_msg(person.contract_address.to_string(), person.code_hash)?,
+            );
+        }
 
-                    ViewingKey::set(deps.storage, &resp.owner_id, &resp.key);
+        PERSON_STORE.remove(deps.storage, &patient_id)?;
+        PATIENT_INDEX.remove(deps.storage, &person.address)?;
+        CONTRACT_INDEX.remove(deps.storage, &person.contract_address)?;
+        PrefixedStorage::new(deps.storage, ViewingKey::STORAGE_KEY).remove(patient_id.as_bytes());
 
-                    Ok(Response::new())
-                }
-                None => Err(ContractError::OffspringInstantiationError {}),
+        let id_hash = erased_id_hash(deps.as_ref(), &patient_id)?;
+        ERASURE_RECEIPTS.push(
+            deps.storage,
+            &ErasureReceipt {
+                id_hash,
+                requested_by,
+                time: env.block.time,
+                height: env.block.height,
+                records_retained: person.legacy,
             },
-            SubMsgResult::Err(e) => Err(ContractError::CustomError { val: e }),
+        )?;
+
+        Ok(response)
+    }
+
+    /// Fails if `id` or `address` already belongs to a registered patient, whose
+    /// index entries would otherwise be overwritten.
+    pub fn check_unregistered(deps: Deps, id: &str, address: &Addr) -> Result<(), ContractError> {
+        if PERSON_STORE.contains(deps.storage, &id.to_string()) {
+            return Err(ContractError::UserExists { id: id.to_string() });
+        }
+        if PATIENT_INDEX.contains(deps.storage, address) {
+            return Err(ContractError::AddressRegistered {
+                address: address.clone(),
+            });
         }
+        Ok(())
     }
-}
 
-#[cfg(test)]
-mod tests {
-    use cosmwasm_std::from_binary;
-    use cosmwasm_std::testing::{mock_dependencies, mock_env, mock_info};
+    /// Returns the id of the patient registered with `sender`.
+    fn patient_id(deps: Deps, sender: &Addr) -> Result<String, ContractError> {
+        match PATIENT_INDEX.get(deps.storage, sender) {
+            Some(id) => Ok(id),
+            None => Err(ContractError::Unauthorized {
+                sender: sender.clone(),
+            }),
+        }
+    }
 
-    use super::*;
+    pub fn create_viewing_key(
+        deps: DepsMut,
+        env: Env,
+        info: MessageInfo,
+        entropy: String,
+    ) -> Result<Response, ContractError> {
+        let id = patient_id(deps.as_ref(), &info.sender)?;
 
-    #[test]
-    fn run_instantiate() {
-        let mut deps = mock_dependencies();
-        let env = mock_env();
+        let key = ViewingKey::create(deps.storage, &info, &env, &id, entropy.as_bytes());
 
-        let resp = instantiate(
-            deps.as_mut(),
-            env.clone(),
-            mock_info("sender", &[]),
-            InstantiateMsg {
-                owner: Addr::unchecked("owner"),
-                offspring_id: 1,
-                offspring_hash: "".to_string(),
-            },
-        )
-        .unwrap();
+        Ok(Response::new().set_data(to_binary(&CreateViewingKeyResp { key })?))
+    }
 
-        assert_eq!(0, resp.messages.len());
+    pub fn set_viewing_key(
+        deps: DepsMut,
+        info: MessageInfo,
+        key: String,
+    ) -> Result<Response, ContractError> {
+        let id = patient_id(deps.as_ref(), &info.sender)?;
+
+        ViewingKey::set(deps.storage, &id, &key);
+
+        Ok(Response::new())
     }
 
-    #[test]
-    fn run_register_get_info() {
-        let mut deps = mock_dependencies();
-        let env = mock_env();
-        let sample_id = "John Doe";
-        let sample_address = Addr::unchecked("secret1");
+    pub fn log_view(
+        deps: DepsMut,
+        info: MessageInfo,
+        patient_id: String,
+        record_id: String,
+        permit: Permit<RecordPermissions>,
+    ) -> Result<Response, ContractError> {
+        check_reader_role(deps.as_ref(), &info.sender)?;
 
-        instantiate(
-            deps.as_mut(),
+        if !PERSON_STORE.contains(deps.storage, &patient_id) {
+            return Err(ContractError::NonexistentUser { id: patient_id });
+        }
+
+        let person = PERSON_STORE.get(deps.storage, &patient_id).unwrap();
+
+        let mut permit = permit;
+        permit.params.allowed_tokens = vec![person.contract_address.to_string()];
+
+        let processed_msg = OffspringExecuteMsg::LogView { record_id, permit }
+            .into_cosmos_msg(person.contract_address.to_string(), person.code_hash)?;
+
+        Ok(Response::new().add_message(processed_msg))
+    }
+
+    /// Unlocks a patient's records for an emergency provider. The record manager
+    /// keeps the unlock, with its justification, in a log the patient can review.
+    pub fn break_glass(
+        deps: DepsMut,
+        info: MessageInfo,
+        patient_id: String,
+        justification: String,
+    ) -> Result<Response, ContractError> {
+        check_provider_role(deps.as_ref(), &info.sender, ProviderRole::BreakGlass)?;
+
+        let person = match PERSON_STORE.get(deps.storage, &patient_id) {
+            Some(person) => person,
+            None => return Err(ContractError::NonexistentUser { id: patient_id }),
+        };
+
+        let processed_msg = OffspringExecuteMsg::BreakGlass {
+            provider: info.sender.clone(),
+            justification,
+        }
+        .into_cosmos_msg(person.contract_address.to_string(), person.code_hash)?;
+
+        Ok(Response::new().add_message(processed_msg).add_event(
+            Event::new("break_glass")
+                .add_attribute("provider", info.sender)
+                .add_attribute("contract_address", person.contract_address),
+        ))
+    }
+
+    /// Sets the record-manager code used for new registrations.
+    pub fn update_offspring(
+        deps: DepsMut,
+        info: MessageInfo,
+        code_id: u64,
+        code_hash: String,
+    ) -> Result<Response, ContractError> {
+        check_admin(deps.as_ref(), &info.sender)?;
+
+        OFFSPRING.save(deps.storage, &OffspringInfo { code_id, code_hash })?;
+
+        Ok(Response::new())
+    }
+
+    pub fn migrate_record_managers(
+        deps: DepsMut,
+        info: MessageInfo,
+        start_after: Option<String>,
+        limit: Option<u32>,
+    ) -> Result<Response, ContractError> {
+        check_admin(deps.as_ref(), &info.sender)?;
+
+        let offspring = OFFSPRING.load(deps.storage).unwrap();
+        let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as usize;
+        let person_store = PERSON_STORE;
+        let mut persons = person_store.iter(deps.storage)?;
+
+        if let Some(start_after) = start_after {
+            if !PERSON_STORE.contains(deps.storage, &start_after) {
+                return Err(ContractError::NonexistentUser { id: start_after });
+            }
+            for item in persons.by_ref() {
+                if item?.0 == start_after {
+                    break;
+                }
+            }
+        }
+
+        let persons = persons.take(limit).collect::<StdResult<Vec<_>>>()?;
+
+        let msg = to_binary(&OffspringMigrateMsg {
+            registry_key: REGISTRY_KEY.may_load(deps.storage)?,
+        })?;
+        let mut messages = vec![];
+        let mut skipped = vec![];
+        for (id, mut person) in persons {
+            if person.code_hash == offspring.code_hash {
+                continue;
+            }
+            if person.legacy {
+                skipped.push(id);
+                continue;
+            }
+
+            messages.push(WasmMsg::Migrate {
+                contract_addr: person.contract_address.to_string(),
+                code_hash: offspring.code_hash.clone(),
+                code_id: offspring.code_id,
+                msg: msg.clone(),
+            });
+
+            person.code_hash = offspring.code_hash.clone();
+            PERSON_STORE.insert(deps.storage, &id, &person)?;
+        }
+
+        Ok(Response::new()
+            .add_messages(messages)
+            .set_data(to_binary(&MigrateRecordManagersResp { skipped })?))
+    }
+
+    pub fn propose_new_owner(
+        deps: DepsMut,
+        info: MessageInfo,
+        address: Addr,
+    ) -> Result<Response, ContractError> {
+        check_admin(deps.as_ref(), &info.sender)?;
+
+        let address = deps.api.addr_validate(address.as_str())?;
+        PENDING_OWNER.save(deps.storage, &address)?;
+
+        Ok(Response::new())
+    }
+
+    pub fn accept_ownership(deps: DepsMut, info: MessageInfo) -> Result<Response, ContractError> {
+        match PENDING_OWNER.may_load(deps.storage)? {
+            Some(pending_owner) if pending_owner == info.sender => {}
+            Some(_) => {
+                return Err(ContractError::Unauthorized {
+                    sender: info.sender,
+                })
+            }
+            None => return Err(ContractError::NoPendingOwner {}),
+        }
+
+        OWNER.save(deps.storage, &info.sender)?;
+        PENDING_OWNER.remove(deps.storage);
+
+        Ok(Response::new())
+    }
+
+    pub fn cancel_ownership_proposal(
+        deps: DepsMut,
+        info: MessageInfo,
+    ) -> Result<Response, ContractError> {
+        check_admin(deps.as_ref(), &info.sender)?;
+
+        if PENDING_OWNER.may_load(deps.storage)?.is_none() {
+            return Err(ContractError::NoPendingOwner {});
+        }
+        PENDING_OWNER.remove(deps.storage);
+
+        Ok(Response::new())
+    }
+
+    pub fn add_provider(
+        deps: DepsMut,
+        info: MessageInfo,
+        address: String,
+        name: String,
+        roles: ProviderRoles,
+    ) -> Result<Response, ContractError> {
+        check_admin(deps.as_ref(), &info.sender)?;
+
+        let address = deps.api.addr_validate(&address)?;
+        if PROVIDER_STORE.contains(deps.storage, &address) {
+            return Err(ContractError::ProviderExists { address });
+        }
+
+        PROVIDER_STORE.insert(deps.storage, &address, &Provider { name, roles })?;
+
+        Ok(Response::new())
+    }
+
+    pub fn update_provider(
+        deps: DepsMut,
+        info: MessageInfo,
+        address: String,
+        name: String,
+        roles: ProviderRoles,
+    ) -> Result<Response, ContractError> {
+        check_admin(deps.as_ref(), &info.sender)?;
+
+        let address = deps.api.addr_validate(&address)?;
+        if !PROVIDER_STORE.contains(deps.storage, &address) {
+            return Err(ContractError::NonexistentProvider { address });
+        }
+
+        PROVIDER_STORE.insert(deps.storage, &address, &Provider { name, roles })?;
+
+        Ok(Response::new())
+    }
+
+    pub fn remove_provider(
+        deps: DepsMut,
+        info: MessageInfo,
+        address: Addr,
+    ) -> Result<Response, ContractError> {
+        check_admin(deps.as_ref(), &info.sender)?;
+
+        if !PROVIDER_STORE.contains(deps.storage, &address) {
+            return Err(ContractError::NonexistentProvider { address });
+        }
+
+        PROVIDER_STORE.remove(deps.storage, &address)?;
+
+        Ok(Response::new())
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    pub fn register_model(
+        deps: DepsMut,
+        env: Env,
+        info: MessageInfo,
+        version: String,
+        model: RiskModel,
+        weights_hash: String,
+        dataset_hash: String,
+        metrics: Vec<ModelMetric>,
+    ) -> Result<Response, ContractError> {
+        check_admin(deps.as_ref(), &info.sender)?;
+
+        if MODEL_STORE.contains(deps.storage, &version) {
+            return Err(ContractError::ModelExists { version });
+        }
+        model.validate()?;
+
+        MODEL_STORE.insert(
+            deps.storage,
+            &version,
+            &ModelVersion {
+                version: version.clone(),
+                model,
+                weights_hash,
+                dataset_hash,
+                metrics,
+                status: ModelStatus::Draft,
+                registered_at: env.block.time,
+                activated_at: None,
+                retired_at: None,
+            },
+        )?;
+
+        Ok(Response::new())
+    }
+
+    /// Makes a draft model the one used for predictions, retiring the model it
+    /// replaces.
+    pub fn activate_model(
+        deps: DepsMut,
+        env: Env,
+        info: MessageInfo,
+        version: String,
+    ) -> Result<Response, ContractError> {
+        check_admin(deps.as_ref(), &info.sender)?;
+
+        let mut model = match MODEL_STORE.get(deps.storage, &version) {
+            Some(model) => model,
+            None => return Err(ContractError::NonexistentModel { version }),
+        };
+        if model.status != ModelStatus::Draft {
+            return Err(ContractError::InvalidModelStatus {
+                version,
+                status: model.status,
+                action: "activated".to_string(),
+            });
+        }
+
+        if let Some(active) = ACTIVE_MODEL.may_load(deps.storage)? {
+            let mut previous = MODEL_STORE.get(deps.storage, &active).unwrap();
+            previous.status = ModelStatus::Retired;
+            previous.retired_at = Some(env.block.time);
+            MODEL_STORE.insert(deps.storage, &active, &previous)?;
+        }
+
+        model.status = ModelStatus::Active;
+        model.activated_at = Some(env.block.time);
+        MODEL_STORE.insert(deps.storage, &version, &model)?;
+        ACTIVE_MODEL.save(deps.storage, &version)?;
+
+        Ok(Response::new())
+    }
+
+    pub fn retire_model(
+        deps: DepsMut,
+        env: Env,
+        info: MessageInfo,
+        version: String,
+    ) -> Result<Response, ContractError> {
+        check_admin(deps.as_ref(), &info.sender)?;
+
+        let mut model = match MODEL_STORE.get(deps.storage, &version) {
+            Some(model) => model,
+            None => return Err(ContractError::NonexistentModel { version }),
+        };
+        if model.status == ModelStatus::Retired {
+            return Err(ContractError::InvalidModelStatus {
+                version,
+                status: model.status,
+                action: "retired".to_string(),
+            });
+        }
+
+        if model.status == ModelStatus::Active {
+            ACTIVE_MODEL.remove(deps.storage);
+        }
+
+        model.status = ModelStatus::Retired;
+        model.retired_at = Some(env.block.time);
+        MODEL_STORE.insert(deps.storage, &version, &model)?;
+
+        Ok(Response::new())
+    }
+
+    pub fn set_privacy_budget(
+        deps: DepsMut,
+        info: MessageInfo,
+        account: Addr,
+        epsilon: Decimal,
+    ) -> Result<Response, ContractError> {
+        check_admin(deps.as_ref(), &info.sender)?;
+
+        let mut budget = PRIVACY_BUDGETS
+            .get(deps.storage, &account)
+            .unwrap_or_default();
+        budget.total = epsilon;
+        PRIVACY_BUDGETS.insert(deps.storage, &account, &budget)?;
+
+        Ok(Response::new())
+    }
+
+    /// Evaluates `statistic` over the latest observation of each consenting
+    /// patient in the cohort and returns it with Laplace noise calibrated to
+    /// `epsilon`, which is charged to the sender's privacy budget. Every
+    /// registered patient is visited in this one call, so it is refused once
+    /// the registry holds more than `MAX_AGGREGATE_PATIENTS` patients.
+    pub fn aggregate_stats(
+        deps: DepsMut,
+        env: Env,
+        info: MessageInfo,
+        purpose: ConsentPurpose,
+        statistic: Statistic,
+        cohort: Option<FeatureRange>,
+        epsilon: Decimal,
+    ) -> Result<Response, ContractError> {
+        check_provider_role(deps.as_ref(), &info.sender, ProviderRole::Research)?;
+
+        if epsilon.is_zero() {
+            return Err(ContractError::InvalidEpsilon {});
+        }
+        statistic.validate()?;
+        let random = env
+            .block
+            .random
+            .ok_or(ContractError::MissingRandomness {})?;
+
+        let mut budget = PRIVACY_BUDGETS
+            .get(deps.storage, &info.sender)
+            .unwrap_or_default();
+        if epsilon > budget.remaining() {
+            return Err(ContractError::InsufficientPrivacyBudget {
+                requested: epsilon,
+                remaining: budget.remaining(),
+            });
+        }
+
+        // the noise hides one patient among all of them, so the statistic has
+        // to cover the whole registry in one go
+        let person_store = PERSON_STORE;
+        let patients = person_store.get_len(deps.storage)?;
+        if patients > MAX_AGGREGATE_PATIENTS {
+            return Err(ContractError::CohortTooLarge {
+                patients,
+                max: MAX_AGGREGATE_PATIENTS,
+            });
+        }
+        let persons = person_store
+            .iter(deps.storage)?
+            .collect::<StdResult<Vec<_>>>()?;
+
+        let mut observations = vec![];
+        for (_, person) in persons {
+            // record managers of 0.1.0 cannot answer, and one unreachable
+            // record manager must not block the whole cohort
+            if person.legacy {
+                continue;
+            }
+            let data = match super::query::research_data(deps.as_ref(), &person, purpose.clone()) {
+                Ok(data) => data,
+                Err(_) => continue,
+            };
+            let latest = data
+                .into_iter()
+                .max_by_key(|data| data.period)
+                .map(|data| data.observation);
+            if let Some(observation) = latest {
+                if cohort.iter().all(|cohort| cohort.contains(&observation)) {
+                    observations.push(observation);
+                }
+            }
+        }
+
+        let values = add_laplace_noise(
+            statistic.evaluate(&observations),
+            statistic.sensitivity(),
+            epsilon,
+            &random,
+        );
+
+        budget.spent += epsilon;
+        PRIVACY_BUDGETS.insert(deps.storage, &info.sender, &budget)?;
+
+        Ok(Response::new().set_data(to_binary(&AggregateStatsResp {
+            values,
+            epsilon,
+            remaining_budget: budget.remaining(),
+        })?))
+    }
+}
+
+mod migrate {
+    use super::*;
+    use crate::state::LEGACY_PERSON_STORE;
+
+    /// Creates the registry key and the viewing-key seed, adds the code hash to
+    /// each person and indexes them by address and record manager. 0.1.0
+    /// could not change the record-manager code, so every record manager runs
+    /// the code of the stored template, and instantiated it without an admin.
+    pub fn from_v0_1(deps: DepsMut, env: &Env) -> Result<(), ContractError> {
+        if let Some(random) = &env.block.random {
+            ViewingKey::set_seed(deps.storage, random.as_slice());
+            REGISTRY_KEY.save(deps.storage, &registry_key(random))?;
+        }
+
+        let offspring = OFFSPRING.load(deps.storage)?;
+        let legacy_store = LEGACY_PERSON_STORE;
+        let legacy_persons = legacy_store
+            .iter(deps.storage)?
+            .collect::<StdResult<Vec<_>>>()?;
+
+        for (id, legacy) in legacy_persons {
+            PATIENT_INDEX.insert(deps.storage, &legacy.address, &id)?;
+            CONTRACT_INDEX.insert(deps.storage, &legacy.contract_address, &id)?;
+            PERSON_STORE.insert(
+                deps.storage,
+                &id,
+                &Person {
+                    address: legacy.address,
+                    contract_address: legacy.contract_address,
+                    pubkey: legacy.pubkey,
+                    code_hash: offspring.code_hash.clone(),
+                    legacy: true,
+                },
+            )?;
+        }
+
+        Ok(())
+    }
+}
+
+mod query {
+    use crate::msg::{
+        AccessLogResp, CheckConsentResp, ConsentPurpose, ConsentsResp, EmergencyAccessLogResp,
+        ErasureReceiptsResp, ListByTimeRangeResp, ListRecordsResp, ModelsResp, OffspringQueryMsg,
+        OwnerResp, PrivacyBudgetResp, ProviderResp, ProvidersResp, RecordCategory,
+        RecordHistoryResp, RecordPermissions, RecordResp, RegistrationResp, ResearchDataResp,
+        ResearchExportResp, ResearchObservation, ResearchRow, RiskResp, VerifyAttachmentResp,
+    };
+    use crate::state::{
+        ModelVersion, ACTIVE_MODEL, ERASURE_RECEIPTS, MODEL_STORE, PRIVACY_BUDGETS,
+    };
+    use crate::state::{PENDING_OWNER, PREFIX_REVOKED_PERMITS};
+    use cosmwasm_std::{QueryRequest, Timestamp, WasmQuery};
+    use secret_toolkit::permit::{Permit, TokenPermissions};
+    use serde::de::DeserializeOwned;
+
+    use super::*;
+
+    /// Scopes the permit to the patient's record manager and checks that it was
+    /// signed either by the patient or by a provider allowed to read records.
+    fn authorize_permit(
+        deps: Deps,
+        person: &Person,
+        permit: Permit<RecordPermissions>,
+    ) -> Result<Permit<RecordPermissions>, ContractError> {
+        let mut permit = permit;
+        permit.params.allowed_tokens = vec![person.contract_address.to_string()];
+
+        let account = secret_toolkit::permit::validate(
+            deps,
+            PREFIX_REVOKED_PERMITS,
+            &permit,
+            person.contract_address.to_string(),
+            None,
+        )?;
+        let account = Addr::unchecked(account);
+
+        if account != person.address {
+            check_reader_role(deps, &account)?;
+        }
+
+        Ok(permit)
+    }
+
+    /// Sends the query `msg` builds to the record manager of patient `id`, with
+    /// `permit` authorized and scoped to that record manager. Every query
+    /// relayed under a record permit goes through here.
+    fn forward_query<R: DeserializeOwned>(
+        deps: Deps,
+        id: String,
+        permit: Permit<RecordPermissions>,
+        msg: impl FnOnce(Permit<RecordPermissions>) -> OffspringQueryMsg,
+    ) -> Result<R, ContractError> {
+        let person = match PERSON_STORE.get(deps.storage, &id) {
+            Some(person) => person,
+            None => return Err(ContractError::NonexistentUser { id }),
+        };
+
+        let permit = authorize_permit(deps, &person, permit)?;
+
+        Ok(deps.querier.query(&QueryRequest::Wasm(WasmQuery::Smart {
+            contract_addr: person.contract_address.to_string(),
+            code_hash: person.code_hash,
+            msg: to_binary(&msg(permit))?,
+        }))?)
+    }
+
+    /// Validates a permit issued for the registry itself and returns its signer.
+    pub fn validate_registry_permit(
+        deps: Deps,
+        env: &Env,
+        permit: &Permit,
+    ) -> Result<Addr, ContractError> {
+        let account = secret_toolkit::permit::validate(
+            deps,
+            PREFIX_REVOKED_PERMITS,
+            permit,
+            env.contract.address.to_string(),
+            None,
+        )?;
+
+        if !permit.check_permission(&TokenPermissions::Owner) {
+            return Err(ContractError::Unauthorized {
+                sender: Addr::unchecked(account),
+            });
+        }
+
+        Ok(Addr::unchecked(account))
+    }
+
+    fn registration_resp(deps: Deps, id: String) -> Result<Binary, ContractError> {
+        let person = match PERSON_STORE.get(deps.storage, &id) {
+            Some(person) => person,
+            None => return Err(ContractError::NonexistentUser { id }),
+        };
+
+        Ok(to_binary(&RegistrationResp {
+            id,
+            address: person.address,
+            contract_address: person.contract_address,
+            pubkey: person.pubkey,
+        })
+        .unwrap())
+    }
+
+    pub fn get_registration(deps: Deps, account: &Addr) -> Result<Binary, ContractError> {
+        match PATIENT_INDEX.get(deps.storage, account) {
+            Some(id) => registration_resp(deps, id),
+            None => Err(ContractError::NonexistentRegistration {
+                address: account.clone(),
+            }),
+        }
+    }
+
+    pub fn get_registration_by_contract(
+        deps: Deps,
+        account: &Addr,
+        contract_address: Addr,
+    ) -> Result<Binary, ContractError> {
+        let id = match CONTRACT_INDEX.get(deps.storage, &contract_address) {
+            Some(id) => id,
+            None => {
+                return Err(ContractError::NonexistentRegistration {
+                    address: contract_address,
+                })
+            }
+        };
+
+        if PATIENT_INDEX.get(deps.storage, account) != Some(id.clone()) {
+            check_admin(deps, account)?;
+        }
+
+        registration_resp(deps, id)
+    }
+
+    pub fn list_consents(
+        deps: Deps,
+        id: String,
+        permit: Permit<RecordPermissions>,
+    ) -> Result<Binary, ContractError> {
+        let resp: ConsentsResp = forward_query(deps, id, permit, |permit| {
+            OffspringQueryMsg::Consents { permit }
+        })?;
+        Ok(to_binary(&resp)?)
+    }
+
+    /// Asks a patient's record manager whether they consent to `purpose`.
+    pub fn check_consent(
+        deps: Deps,
+        person: &Person,
+        purpose: ConsentPurpose,
+    ) -> Result<CheckConsentResp, ContractError> {
+        let query_msg = OffspringQueryMsg::CheckConsent {
+            registry_key: REGISTRY_KEY.load(deps.storage)?,
+            purpose,
+        };
+
+        Ok(deps.querier.query(&QueryRequest::Wasm(WasmQuery::Smart {
+            contract_addr: person.contract_address.to_string(),
+            code_hash: person.code_hash.clone(),
+            msg: to_binary(&query_msg)?,
+        }))?)
+    }
+
+    /// De-identified observations of `person`, or none unless they consent to
+    /// `purpose`.
+    pub fn research_data(
+        deps: Deps,
+        person: &Person,
+        purpose: ConsentPurpose,
+    ) -> Result<Vec<ResearchObservation>, ContractError> {
+        if !check_consent(deps, person, purpose.clone())?.active {
+            return Ok(vec![]);
+        }
+
+        let query_msg = OffspringQueryMsg::ResearchData {
+            registry_key: REGISTRY_KEY.load(deps.storage)?,
+            purpose,
+        };
+        let data: ResearchDataResp = deps.querier.query(&QueryRequest::Wasm(WasmQuery::Smart {
+            contract_addr: person.contract_address.to_string(),
+            code_hash: person.code_hash.clone(),
+            msg: to_binary(&query_msg)?,
+        }))?;
+
+        Ok(data.observations)
+    }
+
+    /// Collects the observations of the patients at positions `start..start +
+    /// limit` who consent to `purpose`, keyed by pseudonym.
+    pub fn research_export(
+        deps: Deps,
+        purpose: ConsentPurpose,
+        start: Option<u32>,
+        limit: Option<u32>,
+    ) -> Result<Binary, ContractError> {
+        let start = start.unwrap_or(0);
+        let limit = limit.unwrap_or(DEFAULT_LIMIT).clamp(1, MAX_LIMIT);
+        let registry_key = REGISTRY_KEY.load(deps.storage)?;
+        let purpose_bytes = to_binary(&purpose)?;
+
+        let person_store = PERSON_STORE;
+        let total = person_store.get_len(deps.storage)?;
+        let persons = person_store
+            .iter(deps.storage)?
+            .skip(start as usize)
+            .take(limit as usize)
+            .collect::<StdResult<Vec<_>>>()?;
+
+        let mut rows = vec![];
+        for (id, person) in persons {
+            // record managers of 0.1.0 cannot answer, and one failing record
+            // manager must not hide the rest of the page
+            if person.legacy {
+                continue;
+            }
+            let observations = match research_data(deps, &person, purpose.clone()) {
+                Ok(observations) => observations,
+                Err(_) => continue,
+            };
+            if observations.is_empty() {
+                continue;
+            }
+
+            let pseudonym = research_pseudonym(&[
+                registry_key.as_bytes(),
+                purpose_bytes.as_slice(),
+                id.as_bytes(),
+            ]);
+
+            rows.extend(observations.into_iter().map(|data| ResearchRow {
+                pseudonym: pseudonym.clone(),
+                period: data.period,
+                observation: data.observation,
+            }));
+        }
+
+        let next = Some(start + limit).filter(|next| *next < total);
+
+        Ok(to_binary(&ResearchExportResp { rows, next })?)
+    }
+
+    /// Hashes `fields`, each prefixed with its length so that no two distinct
+    /// combinations of key, purpose and id hash the same input.
+    fn research_pseudonym(fields: &[&[u8]]) -> String {
+        let mut input = vec![];
+        for field in fields {
+            input.extend((field.len() as u32).to_be_bytes());
+            input.extend(*field);
+        }
+        Binary::from(sha_256(&input).as_slice()).to_base64()
+    }
+
+    pub fn get_privacy_budget(deps: Deps, account: &Addr) -> Result<Binary, ContractError> {
+        let budget = PRIVACY_BUDGETS
+            .get(deps.storage, account)
+            .unwrap_or_default();
+
+        Ok(to_binary(&PrivacyBudgetResp {
+            remaining: budget.remaining(),
+            total: budget.total,
+            spent: budget.spent,
+        })
+        .unwrap())
+    }
+
+    pub fn get_consent(
+        deps: Deps,
+        id: String,
+        purpose: ConsentPurpose,
+    ) -> Result<Binary, ContractError> {
+        let person = match PERSON_STORE.get(deps.storage, &id) {
+            Some(person) => person,
+            None => return Err(ContractError::NonexistentUser { id }),
+        };
+
+        Ok(to_binary(&check_consent(deps, &person, purpose)?).unwrap())
+    }
+
+    pub fn get_owner(deps: Deps) -> Result<Binary, ContractError> {
+        Ok(to_binary(&OwnerResp {
+            owner: OWNER.load(deps.storage)?,
+            pending_owner: PENDING_OWNER.may_load(deps.storage)?,
+        })
+        .unwrap())
+    }
+
+    pub fn get_info(deps: Deps, id: String, key: String) -> Result<InfoResp, ContractError> {
+        if !PERSON_STORE.contains(deps.storage, &id) {
+            return Err(ContractError::NonexistentUser { id });
+        }
+
+        let auth = ViewingKey::check(deps.storage, &id, &key);
+
+        match auth.is_ok() {
+            true => {
+                let person = PERSON_STORE.get(deps.storage, &id).unwrap();
+                let resp = InfoResp {
+                    address: person.address,
+                    contract_address: person.contract_address,
+                    pubkey: person.pubkey,
+                };
+                Ok(resp)
+            }
+            false => Err(ContractError::InvalidKey { key }),
+        }
+    }
+
+    pub fn get_record_by_id(
+        deps: Deps,
+        id: String,
+        permit: Permit<RecordPermissions>,
+        record_id: String,
+    ) -> Result<Binary, ContractError> {
+        let resp: RecordResp = forward_query(deps, id, permit, |permit| {
+            OffspringQueryMsg::ViewById { permit, record_id }
+        })?;
+        Ok(to_binary(&resp)?)
+    }
+
+    pub fn predict_risk(
+        deps: Deps,
+        id: String,
+        permit: Permit<RecordPermissions>,
+        record_id: String,
+    ) -> Result<Binary, ContractError> {
+        let record: RecordResp =
+            forward_query(deps, id, permit, |permit| OffspringQueryMsg::ViewById {
+                permit,
+                record_id: record_id.clone(),
+            })?;
+        let model = load_active_model(deps)?;
+
+        let observation = match record.observation {
+            Some(observation) => observation,
+            None => return Err(ContractError::MissingObservation { record_id }),
+        };
+
+        Ok(to_binary(&RiskResp {
+            record_id,
+            probability: model.model.predict(&observation),
+            model_version: model.version,
+        })?)
+    }
+
+    fn load_active_model(deps: Deps) -> Result<ModelVersion, ContractError> {
+        match ACTIVE_MODEL.may_load(deps.storage)? {
+            Some(version) => Ok(MODEL_STORE.get(deps.storage, &version).unwrap()),
+            None => Err(ContractError::NoRiskModel {}),
+        }
+    }
+
+    pub fn get_active_model(deps: Deps) -> Result<Binary, ContractError> {
+        Ok(to_binary(&load_active_model(deps)?).unwrap())
+    }
+
+    pub fn list_models(
+        deps: Deps,
+        start_after: Option<String>,
+        limit: Option<u32>,
+    ) -> Result<Binary, ContractError> {
+        let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as usize;
+        let model_store = MODEL_STORE;
+        let mut models = model_store.iter(deps.storage)?;
+
+        if let Some(start_after) = start_after {
+            if !MODEL_STORE.contains(deps.storage, &start_after) {
+                return Err(ContractError::NonexistentModel {
+                    version: start_after,
+                });
+            }
+            for item in models.by_ref() {
+                if item?.0 == start_after {
+                    break;
+                }
+            }
+        }
+
+        let models = models
+            .take(limit)
+            .map(|item| item.map(|(_, model)| model))
+            .collect::<StdResult<Vec<_>>>()?;
+
+        Ok(to_binary(&ModelsResp { models }).unwrap())
+    }
+
+    pub fn get_record_history(
+        deps: Deps,
+        id: String,
+        permit: Permit<RecordPermissions>,
+        record_id: String,
+    ) -> Result<Binary, ContractError> {
+        let resp: RecordHistoryResp = forward_query(deps, id, permit, |permit| {
+            OffspringQueryMsg::ViewHistory { permit, record_id }
+        })?;
+        Ok(to_binary(&resp)?)
+    }
+
+    pub fn verify_attachment(
+        deps: Deps,
+        id: String,
+        permit: Permit<RecordPermissions>,
+        record_id: String,
+        uri: String,
+        sha256: Binary,
+    ) -> Result<Binary, ContractError> {
+        let resp: VerifyAttachmentResp = forward_query(deps, id, permit, |permit| {
+            OffspringQueryMsg::VerifyAttachment {
+                permit,
+                record_id,
+                uri,
+                sha256,
+            }
+        })?;
+        Ok(to_binary(&resp)?)
+    }
+
+    pub fn list_by_category(
+        deps: Deps,
+        id: String,
+        permit: Permit<RecordPermissions>,
+        category: RecordCategory,
+        start_after: Option<String>,
+        limit: Option<u32>,
+    ) -> Result<Binary, ContractError> {
+        let resp: ListRecordsResp = forward_query(deps, id, permit, |permit| {
+            OffspringQueryMsg::ListByCategory {
+                permit,
+                category,
+                start_after,
+                limit,
+            }
+        })?;
+        Ok(to_binary(&resp)?)
+    }
+
+    pub fn list_by_time_range(
+        deps: Deps,
+        id: String,
+        permit: Permit<RecordPermissions>,
+        from: Timestamp,
+        to: Timestamp,
+        start_after: Option<u32>,
+        limit: Option<u32>,
+    ) -> Result<Binary, ContractError> {
+        let resp: ListByTimeRangeResp = forward_query(deps, id, permit, |permit| {
+            OffspringQueryMsg::ListByTimeRange {
+                permit,
+                from,
+                to,
+                start_after,
+                limit,
+            }
+        })?;
+        Ok(to_binary(&resp)?)
+    }
+
+    pub fn list_records(
+        deps: Deps,
+        id: String,
+        permit: Permit<RecordPermissions>,
+        start_after: Option<String>,
+        limit: Option<u32>,
+    ) -> Result<Binary, ContractError> {
+        let resp: ListRecordsResp =
+            forward_query(deps, id, permit, |permit| OffspringQueryMsg::ListRecords {
+                permit,
+                start_after,
+                limit,
+            })?;
+        Ok(to_binary(&resp)?)
+    }
+
+    pub fn get_access_log(
+        deps: Deps,
+        id: String,
+        permit: Permit<RecordPermissions>,
+        start: Option<u32>,
+        limit: Option<u32>,
+    ) -> Result<Binary, ContractError> {
+        let resp: AccessLogResp =
+            forward_query(deps, id, permit, |permit| OffspringQueryMsg::AccessLog {
+                permit,
+                start,
+                limit,
+            })?;
+        Ok(to_binary(&resp)?)
+    }
+
+    pub fn get_emergency_access_log(
+        deps: Deps,
+        id: String,
+        permit: Permit<RecordPermissions>,
+        start: Option<u32>,
+        limit: Option<u32>,
+    ) -> Result<Binary, ContractError> {
+        let resp: EmergencyAccessLogResp = forward_query(deps, id, permit, |permit| {
+            OffspringQueryMsg::EmergencyAccessLog {
+                permit,
+                start,
+                limit,
+            }
+        })?;
+        Ok(to_binary(&resp)?)
+    }
+
+    pub fn list_erasure_receipts(
+        deps: Deps,
+        start: Option<u32>,
+        limit: Option<u32>,
+    ) -> Result<Binary, ContractError> {
+        let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as usize;
+        let erasure_receipts = ERASURE_RECEIPTS;
+        let total = erasure_receipts.get_len(deps.storage)?;
+        let receipts = erasure_receipts
+            .iter(deps.storage)?
+            .skip(start.unwrap_or(0) as usize)
+            .take(limit)
+            .collect::<StdResult<Vec<_>>>()?;
+
+        Ok(to_binary(&ErasureReceiptsResp { receipts, total }).unwrap())
+    }
+
+    pub fn list_providers(
+        deps: Deps,
+        start_after: Option<Addr>,
+        limit: Option<u32>,
+    ) -> Result<Binary, ContractError> {
+        let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as usize;
+        let provider_store = PROVIDER_STORE;
+        let mut providers = provider_store.iter(deps.storage)?;
+
+        if let Some(start_after) = start_after {
+            if !PROVIDER_STORE.contains(deps.storage, &start_after) {
+                return Err(ContractError::NonexistentProvider {
+                    address: start_after,
+                });
+            }
+            for item in providers.by_ref() {
+                if item?.0 == start_after {
+                    break;
+                }
+            }
+        }
+
+        let providers = providers
+            .take(limit)
+            .map(|item| {
+                item.map(|(address, provider)| ProviderResp {
+                    address,
+                    name: provider.name,
+                    roles: provider.roles,
+                })
+            })
+            .collect::<StdResult<Vec<_>>>()?;
+
+        Ok(to_binary(&ProvidersResp { providers }).unwrap())
+    }
+}
+
+mod reply {
+    use super::*;
+    use crate::msg::OffspringResp;
+    use cosmwasm_std::{from_binary, SubMsgResult};
+
+    pub fn handle_instantiate_reply(deps: DepsMut, msg: Reply) -> Result<Response, ContractError> {
+        match msg.result {
+            SubMsgResult::Ok(s) => match s.data {
+                Some(bin) => {
+                    let resp: OffspringResp = from_binary(&bin)?;
+                    let offspring = OFFSPRING.load(deps.storage)?;
+                    execute::check_unregistered(
+                        deps.as_ref(),
+                        &resp.owner_id,
+                        &resp.owner_address,
+                    )?;
+
+                    PATIENT_INDEX.insert(deps.storage, &resp.owner_address, &resp.owner_id)?;
+                    CONTRACT_INDEX.insert(deps.storage, &resp.offspring_address, &resp.owner_id)?;
+                    PERSON_STORE.insert(
+                        deps.storage,
+                        &resp.owner_id,
+                        &Person {
+                            address: resp.owner_address,
+                            contract_address: resp.offspring_address.clone(),
+                            pubkey: resp.owner_pubkey,
+                            code_hash: offspring.code_hash,
+                            legacy: false,
+                        },
+                    )?;
+
+                    ViewingKey::set(deps.storage, &resp.owner_id, &resp.key);
+
+                    let event = AuditEvent {
+                        action: "record_manager_instantiated",
+                        actor_role: ActorRole::Registry,
+                        patient: Some(patient_pseudonym(
+                            &event_key(deps.as_ref())?,
+                            &resp.offspring_address,
+                        )),
+                        record_id_hash: None,
+                    };
+
+                    Ok(Response::new().add_event(event.into_event(CONTRACT_VERSION)))
+                }
+                None => Err(ContractError::OffspringInstantiationError {}),
+            },
+            SubMsgResult::Err(e) => Err(ContractError::CustomError { val: e }),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use cosmwasm_std::testing::{mock_dependencies, mock_env, mock_info, MockApi};
+    use cosmwasm_std::{
+        from_binary, from_slice, CosmosMsg, ReplyOn, SubMsgResponse, SubMsgResult, WasmMsg,
+    };
+
+    use super::*;
+    use crate::msg::{
+        OffspringInstantiateMsg, OffspringResp, ProvidersResp, Record, RecordPermissions,
+    };
+    use crate::state::PREFIX_REVOKED_PERMITS;
+    use secret_toolkit::crypto::secp256k1::PrivateKey;
+    use secret_toolkit::permit::{Permit, PermitParams, PermitSignature, PubKey, SignedPermit};
+
+    fn dummy_permit(permissions: Vec<RecordPermissions>) -> Permit<RecordPermissions> {
+        Permit {
+            params: PermitParams {
+                allowed_tokens: vec![],
+                permit_name: "test".to_string(),
+                chain_id: "secret-4".to_string(),
+                permissions,
+            },
+            signature: PermitSignature {
+                pub_key: PubKey {
+                    r#type: "tendermint/PubKeySecp256k1".to_string(),
+                    value: Binary::default(),
+                },
+                signature: Binary::default(),
+            },
+        }
+    }
+
+    fn offspring_reply(id: &str, address: &Addr, contract_address: &str, key: &str) -> Reply {
+        let resp = OffspringResp {
+            offspring_address: Addr::unchecked(contract_address),
+            owner_id: id.to_string(),
+            owner_address: address.clone(),
+            owner_pubkey: "".to_string(),
+            key: key.to_string(),
+        };
+
+        Reply {
+            id: 1,
+            result: SubMsgResult::Ok(SubMsgResponse {
+                events: vec![],
+                data: Some(to_binary(&resp).unwrap()),
+            }),
+        }
+    }
+
+    #[test]
+    fn run_instantiate() {
+        let mut deps = mock_dependencies();
+        let env = mock_env();
+
+        let resp = instantiate(
+            deps.as_mut(),
+            env.clone(),
+            mock_info("sender", &[]),
+            InstantiateMsg {
+                owner: Addr::unchecked("owner"),
+                offspring_id: 1,
+                offspring_hash: "".to_string(),
+            },
+        )
+        .unwrap();
+
+        assert_eq!(0, resp.messages.len());
+    }
+
+    #[test]
+    fn run_add_record_signed_permit() {
+        let mut deps = mock_dependencies();
+        let env = mock_env();
+
+        instantiate(
+            deps.as_mut(),
+            env.clone(),
+            mock_info("sender", &[]),
+            InstantiateMsg {
+                owner: Addr::unchecked("owner"),
+                offspring_id: 1,
+                offspring_hash: "".to_string(),
+            },
+        )
+        .unwrap();
+        execute(
+            deps.as_mut(),
+            env.clone(),
+            mock_info("owner", &[]),
+            ExecuteMsg::AddProvider {
+                address: "hospital".to_string(),
+                name: "General Hospital".to_string(),
+                roles: ProviderRoles {
+                    can_write_records: true,
+                    ..Default::default()
+                },
+            },
+        )
+        .unwrap();
+
+        // the patient's wallet signs the permit for their record manager
+        let key = PrivateKey::parse(&[7; 32]).unwrap();
+        let pubkey = Binary::from(key.pubkey().serialize_compressed().as_slice());
+        let params = PermitParams {
+            allowed_tokens: vec!["contract_address".to_string()],
+            permit_name: "add".to_string(),
+            chain_id: "secret-4".to_string(),
+            permissions: vec![RecordPermissions::Add],
+        };
+        let signed_bytes = to_binary(&SignedPermit::from_params(&params)).unwrap();
+        let signature = key.sign(signed_bytes.as_slice(), MockApi::default());
+        let permit = Permit {
+            params,
+            signature: PermitSignature {
+                pub_key: PubKey {
+                    r#type: "tendermint/PubKeySecp256k1".to_string(),
+                    value: Binary::default(),
+                },
+                signature: Binary::from(signature.serialize().as_slice()),
+            },
+        };
+
+        let resp = OffspringResp {
+            offspring_address: Addr::unchecked("contract_address"),
+            owner_id: "John Doe".to_string(),
+            owner_address: Addr::unchecked("secret1"),
+            owner_pubkey: pubkey.to_base64(),
+            key: "".to_string(),
+        };
+        reply(
+            deps.as_mut(),
+            env.clone(),
+            Reply {
+                id: 1,
+                result: SubMsgResult::Ok(SubMsgResponse {
+                    events: vec![],
+                    data: Some(to_binary(&resp).unwrap()),
+                }),
+            },
+        )
+        .unwrap();
+
+        let resp = execute(
+            deps.as_mut(),
+            env,
+            mock_info("hospital", &[]),
+            ExecuteMsg::AddRecord {
+                patient_id: "John Doe".to_string(),
+                record_id: "1".to_string(),
+                record: from_slice(br#"{"title":"ECG","description":"","data":""}"#).unwrap(),
+                permit,
+            },
+        )
+        .unwrap();
+
+        #[derive(serde::Deserialize)]
+        #[serde(rename_all = "snake_case")]
+        enum Forwarded {
+            AddRecord { permit: Permit<RecordPermissions> },
+        }
+        let permit = match &resp.messages[0].msg {
+            CosmosMsg::Wasm(WasmMsg::Execute { msg, .. }) => match from_binary(msg).unwrap() {
+                Forwarded::AddRecord { permit } => permit,
+            },
+            msg => panic!("unexpected message {:?}", msg),
+        };
+
+        // the forwarded permit carries the registered key and verifies
+        // against the record manager
+        assert_eq!(permit.signature.pub_key.value, pubkey);
+        secret_toolkit::permit::validate(
+            deps.as_ref(),
+            PREFIX_REVOKED_PERMITS,
+            &permit,
+            "contract_address".to_string(),
+            None,
+        )
+        .unwrap();
+    }
+
+    #[test]
+    fn run_add_record_envelope() {
+        use crate::envelope::{key_id, EncryptedEnvelope, EnvelopeAlgorithm, EnvelopeRecipient};
+        use crate::msg::Record;
+
+        let mut deps = mock_dependencies();
+        let env = mock_env();
+        let owner_pubkey = Binary::from([2u8; 33]);
+
+        instantiate(
+            deps.as_mut(),
+            env.clone(),
+            mock_info("sender", &[]),
+            InstantiateMsg {
+                owner: Addr::unchecked("owner"),
+                offspring_id: 1,
+                offspring_hash: "".to_string(),
+            },
+        )
+        .unwrap();
+        execute(
+            deps.as_mut(),
+            env.clone(),
+            mock_info("owner", &[]),
+            ExecuteMsg::AddProvider {
+                address: "hospital".to_string(),
+                name: "General Hospital".to_string(),
+                roles: ProviderRoles {
+                    can_write_records: true,
+                    ..Default::default()
+                },
+            },
+        )
+        .unwrap();
+        let resp = OffspringResp {
+            offspring_address: Addr::unchecked("contract_address"),
+            owner_id: "John Doe".to_string(),
+            owner_address: Addr::unchecked("secret1"),
+            owner_pubkey: owner_pubkey.to_base64(),
+            key: "".to_string(),
+        };
+        reply(
+            deps.as_mut(),
+            env.clone(),
+            Reply {
+                id: 1,
+                result: SubMsgResult::Ok(SubMsgResponse {
+                    events: vec![],
+                    data: Some(to_binary(&resp).unwrap()),
+                }),
+            },
+        )
+        .unwrap();
+
+        let add_record = |key_id: String| {
+            let mut record: Record =
+                from_slice(br#"{"title":"","description":"","data":""}"#).unwrap();
+            record.envelope = Some(EncryptedEnvelope {
+                algorithm: EnvelopeAlgorithm::Secp256k1Aes256Gcm,
+                ephemeral_pubkey: Binary::from([3u8; 33]),
+                nonce: Binary::from([0u8; 12]),
+                ciphertext: Binary::from([7u8; 64]),
+                recipients: vec![EnvelopeRecipient {
+                    key_id,
+                    sealed_key: Binary::from([4u8; 48]),
+                }],
+            });
+            ExecuteMsg::AddRecord {
+                patient_id: "John Doe".to_string(),
+                record_id: "1".to_string(),
+                record,
+                permit: from_slice(
+                    br#"{"params":{"allowed_tokens":[],"permit_name":"add","chain_id":"secret-4","permissions":["add"]},"signature":{"pub_key":{"type":"tendermint/PubKeySecp256k1","value":""},"signature":""}}"#,
+                )
+                .unwrap(),
+            }
+        };
+
+        let err = execute(
+            deps.as_mut(),
+            env.clone(),
+            mock_info("hospital", &[]),
+            add_record(key_id(&[3u8; 33])),
+        )
+        .unwrap_err();
+        assert_eq!(
+            err,
+            ContractError::InvalidEnvelope {
+                reason: "envelope is not sealed for the owner".to_string()
+            }
+        );
+
+        let resp = execute(
+            deps.as_mut(),
+            env,
+            mock_info("hospital", &[]),
+            add_record(key_id(owner_pubkey.as_slice())),
+        )
+        .unwrap();
+        assert_eq!(resp.messages.len(), 1);
+    }
+
+    #[test]
+    fn run_register_get_info() {
+        let mut deps = mock_dependencies();
+        let env = mock_env();
+        let sample_id = "John Doe";
+        let sample_address = Addr::unchecked("secret1");
+
+        instantiate(
+            deps.as_mut(),
+            env.clone(),
+            mock_info("sender", &[]),
+            InstantiateMsg {
+                owner: Addr::unchecked("owner"),
+                offspring_id: 1,
+                offspring_hash: "".to_string(),
+            },
+        )
+        .unwrap();
+
+        let err = execute(
+            deps.as_mut(),
+            env.clone(),
+            mock_info("sender", &[]),
+            ExecuteMsg::Register {
+                id: sample_id.to_owned(),
+                address: sample_address.clone(),
+                pubkey: "".to_string(),
+                key: "".to_string(),
+            },
+        )
+        .unwrap_err();
+
+        assert_eq!(
+            ContractError::Unauthorized {
+                sender: Addr::unchecked("sender")
+            },
+            err
+        );
+
+        let resp = execute(
+            deps.as_mut(),
+            env.clone(),
+            mock_info("owner", &[]),
+            ExecuteMsg::Register {
+                id: sample_id.to_owned(),
+                address: sample_address.clone(),
+                pubkey: "A2pubkey".to_string(),
+                key: "password".to_string(),
+            },
+        )
+        .unwrap();
+
+        // the record manager is instantiated with the registration, and the
+        // registry waits for its reply whatever the outcome
+        assert_eq!(resp.messages.len(), 1);
+        assert_eq!(resp.messages[0].id, 1);
+        assert_eq!(resp.messages[0].reply_on, ReplyOn::Always);
+        let initmsg: OffspringInstantiateMsg = match &resp.messages[0].msg {
+            CosmosMsg::Wasm(WasmMsg::Instantiate {
+                admin,
+                code_id,
+                msg,
+                ..
+            }) => {
+                assert_eq!(admin.as_deref(), Some(env.contract.address.as_str()));
+                assert_eq!(*code_id, 1);
+                from_binary(msg).unwrap()
+            }
+            msg => panic!("unexpected message {:?}", msg),
+        };
+        assert_eq!(
+            initmsg,
+            OffspringInstantiateMsg {
+                owner: sample_address.clone(),
+                owner_id: sample_id.to_string(),
+                owner_pubkey: "A2pubkey".to_string(),
+                key: "password".to_string(),
+                registry_key: REGISTRY_KEY.may_load(deps.as_ref().storage).unwrap(),
+            }
+        );
+
+        // the record manager answers with what it was instantiated with
+        let offspring = OffspringResp {
+            offspring_address: Addr::unchecked("contract_address"),
+            owner_id: initmsg.owner_id,
+            owner_address: initmsg.owner,
+            owner_pubkey: initmsg.owner_pubkey,
+            key: initmsg.key,
+        };
+        reply(
+            deps.as_mut(),
+            env.clone(),
+            Reply {
+                id: 1,
+                result: SubMsgResult::Ok(SubMsgResponse {
+                    events: vec![],
+                    data: Some(to_binary(&offspring).unwrap()),
+                }),
+            },
+        )
+        .unwrap();
+
+        let err = query(
+            deps.as_ref(),
+            env.clone(),
+            QueryMsg::Info {
+                id: sample_id.to_owned(),
+                key: "".to_string(),
+            },
+        )
+        .unwrap_err();
+        assert_eq!(
+            err,
+            ContractError::InvalidKey {
+                key: "".to_string()
+            }
+        );
+
+        let resp = query(
+            deps.as_ref(),
+            env,
+            QueryMsg::Info {
+                id: sample_id.to_owned(),
+                key: "password".to_string(),
+            },
+        )
+        .unwrap();
+        let resp: InfoResp = from_binary(&resp).unwrap();
+
+        assert_eq!(
+            resp,
+            InfoResp {
+                address: sample_address,
+                contract_address: Addr::unchecked("contract_address"),
+                pubkey: "A2pubkey".to_string()
+            }
+        );
+    }
+
+    #[test]
+    fn run_register_duplicate() {
+        let mut deps = mock_dependencies();
+        let env = mock_env();
+
+        instantiate(
+            deps.as_mut(),
+            env.clone(),
+            mock_info("sender", &[]),
+            InstantiateMsg {
+                owner: Addr::unchecked("owner"),
+                offspring_id: 1,
+                offspring_hash: "hash".to_string(),
+            },
+        )
+        .unwrap();
+        reply(
+            deps.as_mut(),
+            env.clone(),
+            offspring_reply("alice", &Addr::unchecked("secret1"), "contract1", "key"),
+        )
+        .unwrap();
+
+        let register = |id: &str, address: &str| ExecuteMsg::Register {
+            id: id.to_string(),
+            address: Addr::unchecked(address),
+            pubkey: "".to_string(),
+            key: "".to_string(),
+        };
+        let err = execute(
+            deps.as_mut(),
+            env.clone(),
+            mock_info("owner", &[]),
+            register("alice", "secret2"),
+        )
+        .unwrap_err();
+        assert_eq!(
+            err,
+            ContractError::UserExists {
+                id: "alice".to_string()
+            }
+        );
+        let err = execute(
+            deps.as_mut(),
+            env.clone(),
+            mock_info("owner", &[]),
+            register("bob", "secret1"),
+        )
+        .unwrap_err();
+        assert_eq!(
+            err,
+            ContractError::AddressRegistered {
+                address: Addr::unchecked("secret1")
+            }
+        );
+
+        // a reply for a taken id or address leaves the indexes untouched
+        let err = reply(
+            deps.as_mut(),
+            env.clone(),
+            offspring_reply("alice", &Addr::unchecked("secret2"), "contract2", "key"),
+        )
+        .unwrap_err();
+        assert_eq!(
+            err,
+            ContractError::UserExists {
+                id: "alice".to_string()
+            }
+        );
+        reply(
+            deps.as_mut(),
+            env,
+            offspring_reply("bob", &Addr::unchecked("secret1"), "contract2", "key"),
+        )
+        .unwrap_err();
+
+        let storage = deps.as_ref().storage;
+        assert_eq!(
+            PATIENT_INDEX.get(storage, &Addr::unchecked("secret1")),
+            Some("alice".to_string())
+        );
+        assert!(!PATIENT_INDEX.contains(storage, &Addr::unchecked("secret2")));
+        assert!(!CONTRACT_INDEX.contains(storage, &Addr::unchecked("contract2")));
+        assert!(!PERSON_STORE.contains(storage, &"bob".to_string()));
+    }
+
+    #[test]
+    fn run_revoke_permit() {
+        let mut deps = mock_dependencies();
+        let env = mock_env();
+        let sample_id = "John Doe";
+        let sample_address = Addr::unchecked("secret1");
+
+        instantiate(
+            deps.as_mut(),
+            env.clone(),
+            mock_info("sender", &[]),
+            InstantiateMsg {
+                owner: Addr::unchecked("owner"),
+                offspring_id: 1,
+                offspring_hash: "".to_string(),
+            },
+        )
+        .unwrap();
+
+        reply(
+            deps.as_mut(),
+            env.clone(),
+            offspring_reply(sample_id, &sample_address, "contract_address", ""),
+        )
+        .unwrap();
+
+        let err = execute(
+            deps.as_mut(),
+            env.clone(),
+            mock_info("owner", &[]),
+            ExecuteMsg::RevokePermit {
+                patient_id: sample_id.to_owned(),
+                permit_name: "leaked".to_string(),
+            },
+        )
+        .unwrap_err();
+        assert_eq!(
+            err,
+            ContractError::Unauthorized {
+                sender: Addr::unchecked("owner")
+            }
+        );
+
+        let resp = execute(
+            deps.as_mut(),
+            env,
+            mock_info(sample_address.as_str(), &[]),
+            ExecuteMsg::RevokePermit {
+                patient_id: sample_id.to_owned(),
+                permit_name: "leaked".to_string(),
+            },
+        )
+        .unwrap();
+        assert_eq!(1, resp.messages.len());
+    }
+
+    #[test]
+    fn run_provider_roles() {
+        let mut deps = mock_dependencies();
+        let env = mock_env();
+        let hospital = Addr::unchecked("hospital");
+
+        instantiate(
+            deps.as_mut(),
+            env.clone(),
+            mock_info("sender", &[]),
+            InstantiateMsg {
+                owner: Addr::unchecked("owner"),
+                offspring_id: 1,
+                offspring_hash: "".to_string(),
+            },
+        )
+        .unwrap();
+
+        let add_provider = ExecuteMsg::AddProvider {
+            address: hospital.to_string(),
+            name: "General Hospital".to_string(),
+            roles: ProviderRoles {
+                can_register: true,
+                ..Default::default()
+            },
+        };
+
+        let err = execute(
+            deps.as_mut(),
+            env.clone(),
+            mock_info("hospital", &[]),
+            add_provider.clone(),
+        )
+        .unwrap_err();
+        assert_eq!(
+            err,
+            ContractError::Unauthorized {
+                sender: hospital.clone()
+            }
+        );
+
+        execute(
+            deps.as_mut(),
+            env.clone(),
+            mock_info("owner", &[]),
+            add_provider,
+        )
+        .unwrap();
+
+        let resp = query(
+            deps.as_ref(),
+            env.clone(),
+            QueryMsg::Providers {
+                start_after: None,
+                limit: None,
+            },
+        )
+        .unwrap();
+        let resp: ProvidersResp = from_binary(&resp).unwrap();
+        assert_eq!(resp.providers.len(), 1);
+        assert_eq!(resp.providers[0].address, hospital);
+        assert!(resp.providers[0].roles.can_register);
+
+        // a second add does not silently replace the roles
+        let err = execute(
+            deps.as_mut(),
+            env.clone(),
+            mock_info("owner", &[]),
+            ExecuteMsg::AddProvider {
+                address: hospital.to_string(),
+                name: "General Hospital".to_string(),
+                roles: ProviderRoles::default(),
+            },
+        )
+        .unwrap_err();
+        assert_eq!(
+            err,
+            ContractError::ProviderExists {
+                address: hospital.clone()
+            }
+        );
+
+        // addresses that could never match a sender are rejected
+        for address in ["Hospital", "h"] {
+            execute(
+                deps.as_mut(),
+                env.clone(),
+                mock_info("owner", &[]),
+                ExecuteMsg::AddProvider {
+                    address: address.to_string(),
+                    name: "General Hospital".to_string(),
+                    roles: ProviderRoles::default(),
+                },
+            )
+            .unwrap_err();
+        }
+
+        let err = execute(
+            deps.as_mut(),
+            env.clone(),
+            mock_info("owner", &[]),
+            ExecuteMsg::UpdateProvider {
+                address: "clinic".to_string(),
+                name: "Clinic".to_string(),
+                roles: ProviderRoles::default(),
+            },
+        )
+        .unwrap_err();
+        assert_eq!(
+            err,
+            ContractError::NonexistentProvider {
+                address: Addr::unchecked("clinic")
+            }
+        );
+
+        let register = ExecuteMsg::Register {
+            id: "John Doe".to_string(),
+            address: Addr::unchecked("secret1"),
+            pubkey: "".to_string(),
+            key: "".to_string(),
+        };
+        let resp = execute(
+            deps.as_mut(),
+            env.clone(),
+            mock_info("hospital", &[]),
+            register.clone(),
+        )
+        .unwrap();
+        assert_eq!(1, resp.messages.len());
+
+        let err = execute(
+            deps.as_mut(),
+            env.clone(),
+            mock_info("hospital", &[]),
+            ExecuteMsg::AddRecord {
+                patient_id: "John Doe".to_string(),
+                record_id: "1".to_string(),
+                record: Record {
+                    title: "".to_string(),
+                    timestamp: None,
+                    description: "".to_string(),
+                    data: "".to_string(),
+                    observation: None,
+                    envelope: None,
+                    attachments: vec![],
+                    category: None,
+                },
+                permit: dummy_permit(vec![RecordPermissions::Add]),
+            },
+        )
+        .unwrap_err();
+        assert_eq!(
+            err,
+            ContractError::MissingRole {
+                sender: hospital.clone(),
+                role: ProviderRole::WriteRecords
+            }
+        );
+
+        execute(
+            deps.as_mut(),
+            env.clone(),
+            mock_info("owner", &[]),
+            ExecuteMsg::RemoveProvider {
+                address: hospital.clone(),
+            },
+        )
+        .unwrap();
+
+        let err = execute(deps.as_mut(), env, mock_info("hospital", &[]), register).unwrap_err();
+        assert_eq!(err, ContractError::Unauthorized { sender: hospital });
+    }
+
+    #[test]
+    fn run_amend_record() {
+        let mut deps = mock_dependencies();
+        let env = mock_env();
+        let cardiologist = Addr::unchecked("cardiologist");
+
+        instantiate(
+            deps.as_mut(),
+            env.clone(),
+            mock_info("sender", &[]),
+            InstantiateMsg {
+                owner: Addr::unchecked("owner"),
+                offspring_id: 1,
+                offspring_hash: "hash".to_string(),
+            },
+        )
+        .unwrap();
+
+        reply(
+            deps.as_mut(),
+            env.clone(),
+            offspring_reply(
+                "John Doe",
+                &Addr::unchecked("secret1"),
+                "contract_address",
+                "",
+            ),
+        )
+        .unwrap();
+
+        execute(
+            deps.as_mut(),
+            env.clone(),
+            mock_info("owner", &[]),
+            ExecuteMsg::AddProvider {
+                address: cardiologist.to_string(),
+                name: "Cardiology".to_string(),
+                roles: ProviderRoles {
+                    can_write_records: true,
+                    ..Default::default()
+                },
+            },
+        )
+        .unwrap();
+
+        let resp = execute(
+            deps.as_mut(),
+            env,
+            mock_info("cardiologist", &[]),
+            ExecuteMsg::AmendRecord {
+                patient_id: "John Doe".to_string(),
+                record_id: "1".to_string(),
+                record: Record {
+                    title: "Lipid panel".to_string(),
+                    timestamp: None,
+                    description: "".to_string(),
+                    data: "LDL 106".to_string(),
+                    observation: None,
+                    envelope: None,
+                    attachments: vec![],
+                    category: None,
+                },
+                reason: "Transcription error".to_string(),
+                permit: dummy_permit(vec![RecordPermissions::Amend {
+                    record_id: "1".to_string(),
+                }]),
+            },
+        )
+        .unwrap();
+
+        assert_eq!(1, resp.messages.len());
+        match &resp.messages[0].msg {
+            CosmosMsg::Wasm(WasmMsg::Execute {
+                contract_addr,
+                code_hash,
+                msg,
+                ..
+            }) => {
+                assert_eq!(contract_addr, "contract_address");
+                assert_eq!(code_hash, "hash");
+                match from_binary(msg).unwrap() {
+                    OffspringExecuteMsg::AmendRecord(amend) => {
+                        assert_eq!(amend.reason, "Transcription error");
+                        assert_eq!(amend.author, Some(cardiologist));
+                        assert_eq!(
+                            amend.permit.params.allowed_tokens,
+                            vec!["contract_address".to_string()]
+                        );
+                    }
+                    other => panic!("unexpected message {:?}", other),
+                }
+            }
+            other => panic!("unexpected message {:?}", other),
+        }
+    }
+
+    #[test]
+    fn run_model_registry() {
+        use crate::msg::ModelsResp;
+        use crate::risk::{Feature, RiskModel};
+        use crate::state::{ModelStatus, ModelVersion};
+
+        let mut deps = mock_dependencies();
+        let mut env = mock_env();
+
+        instantiate(
+            deps.as_mut(),
+            env.clone(),
+            mock_info("sender", &[]),
+            InstantiateMsg {
+                owner: Addr::unchecked("owner"),
+                offspring_id: 1,
+                offspring_hash: "".to_string(),
+            },
+        )
+        .unwrap();
+
+        let register = |version: &str| ExecuteMsg::RegisterModel {
+            version: version.to_string(),
+            model: RiskModel {
+                features: vec![Feature::Age, Feature::Cholesterol],
+                weights: vec![50_000, 10_000],
+                intercept: -5_000_000,
+            },
+            weights_hash: "weights".to_string(),
+            dataset_hash: "cleveland".to_string(),
+            metrics: vec![],
+        };
+
+        let err = execute(
+            deps.as_mut(),
+            env.clone(),
+            mock_info("hospital", &[]),
+            register("v1"),
+        )
+        .unwrap_err();
+        assert_eq!(
+            err,
+            ContractError::Unauthorized {
+                sender: Addr::unchecked("hospital")
+            }
+        );
+
+        for version in ["v1", "v2"] {
+            execute(
+                deps.as_mut(),
+                env.clone(),
+                mock_info("owner", &[]),
+                register(version),
+            )
+            .unwrap();
+        }
+        let err = execute(
+            deps.as_mut(),
+            env.clone(),
+            mock_info("owner", &[]),
+            register("v1"),
+        )
+        .unwrap_err();
+        assert_eq!(
+            err,
+            ContractError::ModelExists {
+                version: "v1".to_string()
+            }
+        );
+
+        let err = query(deps.as_ref(), env.clone(), QueryMsg::ActiveModel {}).unwrap_err();
+        assert_eq!(err, ContractError::NoRiskModel {});
+
+        for version in ["v1", "v2"] {
+            env.block.time = env.block.time.plus_seconds(10);
+            execute(
+                deps.as_mut(),
+                env.clone(),
+                mock_info("owner", &[]),
+                ExecuteMsg::ActivateModel {
+                    version: version.to_string(),
+                },
+            )
+            .unwrap();
+        }
+
+        let resp = query(deps.as_ref(), env.clone(), QueryMsg::ActiveModel {}).unwrap();
+        let active: ModelVersion = from_binary(&resp).unwrap();
+        assert_eq!(active.version, "v2");
+        assert_eq!(active.status, ModelStatus::Active);
+        assert_eq!(active.activated_at, Some(env.block.time));
+
+        let resp = query(
+            deps.as_ref(),
+            env.clone(),
+            QueryMsg::Models {
+                start_after: None,
+                limit: None,
+            },
+        )
+        .unwrap();
+        let resp: ModelsResp = from_binary(&resp).unwrap();
+        assert_eq!(resp.models.len(), 2);
+        assert_eq!(resp.models[0].status, ModelStatus::Retired);
+        assert_eq!(resp.models[0].retired_at, Some(env.block.time));
+
+        let err = execute(
+            deps.as_mut(),
+            env.clone(),
+            mock_info("owner", &[]),
+            ExecuteMsg::ActivateModel {
+                version: "v1".to_string(),
+            },
+        )
+        .unwrap_err();
+        assert_eq!(
+            err,
+            ContractError::InvalidModelStatus {
+                version: "v1".to_string(),
+                status: ModelStatus::Retired,
+                action: "activated".to_string(),
+            }
+        );
+
+        execute(
+            deps.as_mut(),
+            env.clone(),
+            mock_info("owner", &[]),
+            ExecuteMsg::RetireModel {
+                version: "v2".to_string(),
+            },
+        )
+        .unwrap();
+        let err = query(deps.as_ref(), env, QueryMsg::ActiveModel {}).unwrap_err();
+        assert_eq!(err, ContractError::NoRiskModel {});
+    }
+
+    #[test]
+    fn run_viewing_keys() {
+        use crate::msg::CreateViewingKeyResp;
+
+        let mut deps = mock_dependencies();
+        let env = mock_env();
+        let sample_id = "John Doe";
+        let sample_address = Addr::unchecked("secret1");
+
+        instantiate(
+            deps.as_mut(),
+            env.clone(),
+            mock_info("sender", &[]),
+            InstantiateMsg {
+                owner: Addr::unchecked("owner"),
+                offspring_id: 1,
+                offspring_hash: "".to_string(),
+            },
+        )
+        .unwrap();
+
+        reply(
+            deps.as_mut(),
+            env.clone(),
+            offspring_reply(sample_id, &sample_address, "contract_address", "admin key"),
+        )
+        .unwrap();
+
+        let info = |key: &str| QueryMsg::Info {
+            id: sample_id.to_owned(),
+            key: key.to_string(),
+        };
+        query(deps.as_ref(), env.clone(), info("admin key")).unwrap();
+
+        let err = execute(
+            deps.as_mut(),
+            env.clone(),
+            mock_info("owner", &[]),
+            ExecuteMsg::SetViewingKey {
+                key: "owner key".to_string(),
+            },
+        )
+        .unwrap_err();
+        assert_eq!(
+            err,
+            ContractError::Unauthorized {
+                sender: Addr::unchecked("owner")
+            }
+        );
+
+        let resp = execute(
+            deps.as_mut(),
+            env.clone(),
+            mock_info(sample_address.as_str(), &[]),
+            ExecuteMsg::CreateViewingKey {
+                entropy: "entropy".to_string(),
+            },
+        )
+        .unwrap();
+        let key = from_binary::<CreateViewingKeyResp>(&resp.data.unwrap())
+            .unwrap()
+            .key;
+
+        let err = query(deps.as_ref(), env.clone(), info("admin key")).unwrap_err();
+        assert_eq!(
+            err,
+            ContractError::InvalidKey {
+                key: "admin key".to_string()
+            }
+        );
+        query(deps.as_ref(), env.clone(), info(&key)).unwrap();
+
+        execute(
+            deps.as_mut(),
+            env.clone(),
+            mock_info(sample_address.as_str(), &[]),
+            ExecuteMsg::SetViewingKey {
+                key: "patient key".to_string(),
+            },
+        )
+        .unwrap();
+        assert!(query(deps.as_ref(), env.clone(), info(&key)).is_err());
+        query(deps.as_ref(), env, info("patient key")).unwrap();
+    }
+
+    #[test]
+    fn run_deregister() {
+        use crate::msg::{ErasureReceiptsResp, OffspringExecuteMsg};
+        use crate::state::ErasureRequester;
+        use secret_toolkit::crypto::sha_256;
+
+        let mut deps = mock_dependencies();
+        let env = mock_env();
+
+        instantiate(
+            deps.as_mut(),
+            env.clone(),
+            mock_info("sender", &[]),
+            InstantiateMsg {
+                owner: Addr::unchecked("owner"),
+                offspring_id: 1,
+                offspring_hash: "".to_string(),
+            },
+        )
+        .unwrap();
+
+        for (id, address) in [
+            ("alice", "secret1"),
+            ("bob", "secret2"),
+            ("carol", "secret3"),
+        ] {
+            reply(
+                deps.as_mut(),
+                env.clone(),
+                offspring_reply(id, &Addr::unchecked(address), address, "key"),
+            )
+            .unwrap();
+        }
+        // carol's record manager was instantiated by 0.1.0
+        let mut carol = PERSON_STORE
+            .get(deps.as_ref().storage, &"carol".to_string())
+            .unwrap();
+        carol.legacy = true;
+        PERSON_STORE
+            .insert(deps.as_mut().storage, &"carol".to_string(), &carol)
+            .unwrap();
+
+        let deregister = |id: &str| ExecuteMsg::Deregister {
+            patient_id: id.to_string(),
+        };
+
+        let err = execute(
+            deps.as_mut(),
+            env.clone(),
+            mock_info("secret2", &[]),
+            deregister("alice"),
+        )
+        .unwrap_err();
+        assert_eq!(
+            err,
+            ContractError::Unauthorized {
+                sender: Addr::unchecked("secret2")
+            }
+        );
+
+        let resp = execute(
+            deps.as_mut(),
+            env.clone(),
+            mock_info("secret1", &[]),
+            deregister("alice"),
+        )
+        .unwrap();
+        match &resp.messages[0].msg {
+            CosmosMsg::Wasm(WasmMsg::Execute {
+                contract_addr, msg, ..
+            }) => {
+                assert_eq!(contract_addr, "secret1");
+                assert_eq!(
+                    from_binary::<OffspringExecuteMsg>(msg).unwrap(),
+                    OffspringExecuteMsg::Erase {}
+                );
+            }
+            msg => panic!("unexpected message {:?}", msg),
+        }
+
+        execute(
+            deps.as_mut(),
+            env.clone(),
+            mock_info("owner", &[]),
+            deregister("bob"),
+        )
+        .unwrap();
+
+        // a legacy record manager cannot erase, so it is only unlinked
+        let resp = execute(
+            deps.as_mut(),
+            env.clone(),
+            mock_info("owner", &[]),
+            deregister("carol"),
+        )
+        .unwrap();
+        assert!(resp.messages.is_empty());
+        assert!(!PERSON_STORE.contains(deps.as_ref().storage, &"carol".to_string()));
+        assert!(!CONTRACT_INDEX.contains(deps.as_ref().storage, &Addr::unchecked("secret3")));
+
+        let err = query(
+            deps.as_ref(),
+            env.clone(),
+            QueryMsg::Info {
+                id: "alice".to_string(),
+                key: "key".to_string(),
+            },
+        )
+        .unwrap_err();
+        assert_eq!(
+            err,
+            ContractError::NonexistentUser {
+                id: "alice".to_string()
+            }
+        );
+        assert!(ViewingKey::check(deps.as_ref().storage, "alice", "key").is_err());
+        let err = execute(
+            deps.as_mut(),
+            env.clone(),
+            mock_info("secret1", &[]),
+            ExecuteMsg::SetViewingKey {
+                key: "key".to_string(),
+            },
+        )
+        .unwrap_err();
+        assert_eq!(
+            err,
+            ContractError::Unauthorized {
+                sender: Addr::unchecked("secret1")
+            }
+        );
+
+        let resp = query(
+            deps.as_ref(),
+            env.clone(),
+            QueryMsg::ErasureReceipts {
+                start: None,
+                limit: None,
+            },
+        )
+        .unwrap();
+        let resp: ErasureReceiptsResp = from_binary(&resp).unwrap();
+        assert_eq!(resp.total, 3);
+        assert_ne!(resp.receipts[0].id_hash.as_slice(), sha_256(b"alice"));
+        assert_eq!(
+            resp.receipts[0].id_hash,
+            erased_id_hash(deps.as_ref(), "alice").unwrap()
+        );
+        assert_eq!(resp.receipts[0].requested_by, ErasureRequester::Patient);
+        assert_eq!(resp.receipts[1].requested_by, ErasureRequester::Admin);
+        assert_eq!(resp.receipts[1].time, env.block.time);
+        assert!(!resp.receipts[1].records_retained);
+        assert!(resp.receipts[2].records_retained);
+    }
+
+    #[test]
+    fn run_migrate() {
+        use crate::msg::MigrateRecordManagersResp;
+        use crate::state::{LegacyPerson, LEGACY_PERSON_STORE};
+
+        let mut deps = mock_dependencies();
+        let mut env = mock_env();
+        env.block.random = Some(Binary::from(&[3; 32]));
+        let sample_address = Addr::unchecked("secret1");
+
+        // state as left by 0.1.0
+        OWNER
+            .save(deps.as_mut().storage, &Addr::unchecked("owner"))
+            .unwrap();
+        OFFSPRING
+            .save(
+                deps.as_mut().storage,
+                &OffspringInfo {
+                    code_id: 1,
+                    code_hash: "hash".to_string(),
+                },
+            )
+            .unwrap();
+        LEGACY_PERSON_STORE
+            .insert(
+                deps.as_mut().storage,
+                &"John Doe".to_string(),
+                &LegacyPerson {
+                    address: sample_address.clone(),
+                    contract_address: Addr::unchecked("contract_address"),
+                    pubkey: "".to_string(),
+                },
+            )
+            .unwrap();
+
+        migrate(deps.as_mut(), env.clone(), MigrateMsg {}).unwrap();
+
+        assert_eq!(
+            PERSON_STORE.get(deps.as_ref().storage, &"John Doe".to_string()),
+            Some(Person {
+                address: sample_address.clone(),
+                contract_address: Addr::unchecked("contract_address"),
+                pubkey: "".to_string(),
+                code_hash: "hash".to_string(),
+                legacy: true,
+            })
+        );
+
+        assert_eq!(
+            PATIENT_INDEX.get(deps.as_ref().storage, &sample_address),
+            Some("John Doe".to_string())
+        );
+        assert_eq!(
+            CONTRACT_INDEX.get(deps.as_ref().storage, &Addr::unchecked("contract_address")),
+            Some("John Doe".to_string())
+        );
+        let info = CONTRACT_INFO.load(deps.as_ref().storage).unwrap();
+        assert_eq!(info.name, "registry");
+        assert_eq!(info.version, CONTRACT_VERSION);
+        assert!(REGISTRY_KEY
+            .may_load(deps.as_ref().storage)
+            .unwrap()
+            .is_some());
+        assert_eq!(
+            deps.as_ref().storage.get(b"viewing_keys::seed"),
+            Some(vec![3; 32])
+        );
+
+        // the record manager has no admin, so it is reported instead of migrated
+        execute(
+            deps.as_mut(),
+            env.clone(),
+            mock_info("owner", &[]),
+            ExecuteMsg::UpdateOffspring {
+                code_id: 2,
+                code_hash: "new".to_string(),
+            },
+        )
+        .unwrap();
+        let resp = execute(
+            deps.as_mut(),
+            env.clone(),
+            mock_info("owner", &[]),
+            ExecuteMsg::MigrateRecordManagers {
+                start_after: None,
+                limit: None,
+            },
+        )
+        .unwrap();
+        assert!(resp.messages.is_empty());
+        let resp: MigrateRecordManagersResp = from_binary(&resp.data.unwrap()).unwrap();
+        assert_eq!(resp.skipped, vec!["John Doe".to_string()]);
+        assert_eq!(
+            PERSON_STORE
+                .get(deps.as_ref().storage, &"John Doe".to_string())
+                .unwrap()
+                .code_hash,
+            "hash"
+        );
+
+        CONTRACT_INFO
+            .save(
+                deps.as_mut().storage,
+                &ContractInfo {
+                    name: CONTRACT_NAME.to_string(),
+                    version: "1.0.0".to_string(),
+                },
+            )
+            .unwrap();
+        let err = migrate(deps.as_mut(), env, MigrateMsg {}).unwrap_err();
+        assert_eq!(
+            err,
+            ContractError::CannotDowngrade {
+                stored: "1.0.0".to_string(),
+                current: CONTRACT_VERSION.to_string(),
+            }
+        );
+    }
+
+    #[test]
+    fn run_migrate_record_managers() {
+        let mut deps = mock_dependencies();
+        let env = mock_env();
+
+        instantiate(
+            deps.as_mut(),
+            env.clone(),
+            mock_info("sender", &[]),
+            InstantiateMsg {
+                owner: Addr::unchecked("owner"),
+                offspring_id: 1,
+                offspring_hash: "old".to_string(),
+            },
+        )
+        .unwrap();
+
+        for (id, address) in [("alice", "secret1"), ("bob", "secret2")] {
+            reply(
+                deps.as_mut(),
+                env.clone(),
+                offspring_reply(id, &Addr::unchecked(address), address, "key"),
+            )
+            .unwrap();
+        }
+
+        let update_offspring = ExecuteMsg::UpdateOffspring {
+            code_id: 2,
+            code_hash: "new".to_string(),
+        };
+        let err = execute(
+            deps.as_mut(),
+            env.clone(),
+            mock_info("secret1", &[]),
+            update_offspring.clone(),
+        )
+        .unwrap_err();
+        assert_eq!(
+            err,
+            ContractError::Unauthorized {
+                sender: Addr::unchecked("secret1")
+            }
+        );
+        execute(
+            deps.as_mut(),
+            env.clone(),
+            mock_info("owner", &[]),
+            update_offspring,
+        )
+        .unwrap();
+
+        // carol is registered from the new template
+        let resp = execute(
+            deps.as_mut(),
+            env.clone(),
+            mock_info("owner", &[]),
+            ExecuteMsg::Register {
+                id: "carol".to_string(),
+                address: Addr::unchecked("secret3"),
+                pubkey: "".to_string(),
+                key: "".to_string(),
+            },
+        )
+        .unwrap();
+        match &resp.messages[0].msg {
+            CosmosMsg::Wasm(WasmMsg::Instantiate {
+                admin,
+                code_id,
+                code_hash,
+                ..
+            }) => {
+                assert_eq!(admin.as_deref(), Some(mock_env().contract.address.as_str()));
+                assert_eq!(*code_id, 2);
+                assert_eq!(code_hash, "new");
+            }
+            msg => panic!("unexpected message {:?}", msg),
+        }
+        reply(
+            deps.as_mut(),
+            env.clone(),
+            offspring_reply("carol", &Addr::unchecked("secret3"), "secret3", "key"),
+        )
+        .unwrap();
+
+        let code_hash = |deps: Deps, id: &str| {
+            PERSON_STORE
+                .get(deps.storage, &id.to_string())
+                .unwrap()
+                .code_hash
+        };
+        assert_eq!(code_hash(deps.as_ref(), "alice"), "old");
+        assert_eq!(code_hash(deps.as_ref(), "carol"), "new");
+
+        // messages to record managers use the hash of each patient's own code
+        let resp = execute(
+            deps.as_mut(),
+            env.clone(),
+            mock_info("secret1", &[]),
+            ExecuteMsg::RevokePermit {
+                patient_id: "alice".to_string(),
+                permit_name: "leaked".to_string(),
+            },
+        )
+        .unwrap();
+        match &resp.messages[0].msg {
+            CosmosMsg::Wasm(WasmMsg::Execute { code_hash, .. }) => assert_eq!(code_hash, "old"),
+            msg => panic!("unexpected message {:?}", msg),
+        }
+
+        let migrate_msg = |start_after: Option<&str>| ExecuteMsg::MigrateRecordManagers {
+            start_after: start_after.map(str::to_string),
+            limit: Some(1),
+        };
+
+        let resp = execute(
+            deps.as_mut(),
+            env.clone(),
+            mock_info("owner", &[]),
+            migrate_msg(None),
+        )
+        .unwrap();
+        assert_eq!(
+            resp.messages[0].msg,
+            CosmosMsg::Wasm(WasmMsg::Migrate {
+                contract_addr: "secret1".to_string(),
+                code_hash: "new".to_string(),
+                code_id: 2,
+                msg: to_binary(&crate::msg::OffspringMigrateMsg {
+                    registry_key: REGISTRY_KEY.may_load(deps.as_ref().storage).unwrap(),
+                })
+                .unwrap(),
+            })
+        );
+        assert_eq!(code_hash(deps.as_ref(), "alice"), "new");
+
+        let resp = execute(
+            deps.as_mut(),
+            env.clone(),
+            mock_info("owner", &[]),
+            migrate_msg(Some("alice")),
+        )
+        .unwrap();
+        assert_eq!(resp.messages.len(), 1);
+
+        // carol already runs the new code
+        let resp = execute(
+            deps.as_mut(),
+            env,
+            mock_info("owner", &[]),
+            migrate_msg(Some("bob")),
+        )
+        .unwrap();
+        assert!(resp.messages.is_empty());
+    }
+
+    #[test]
+    fn run_ownership_transfer() {
+        use crate::msg::OwnerResp;
+
+        let mut deps = mock_dependencies();
+        let env = mock_env();
+        let owner = Addr::unchecked("owner");
+        let successor = Addr::unchecked("successor");
+
+        instantiate(
+            deps.as_mut(),
+            env.clone(),
+            mock_info("sender", &[]),
+            InstantiateMsg {
+                owner: owner.clone(),
+                offspring_id: 1,
+                offspring_hash: "".to_string(),
+            },
+        )
+        .unwrap();
+
+        let owner_resp = |deps: Deps| -> OwnerResp {
+            from_binary(&query(deps, mock_env(), QueryMsg::Owner {}).unwrap()).unwrap()
+        };
+        let propose = ExecuteMsg::ProposeNewOwner {
+            address: successor.clone(),
+        };
+
+        let err = execute(
+            deps.as_mut(),
+            env.clone(),
+            mock_info("successor", &[]),
+            propose.clone(),
+        )
+        .unwrap_err();
+        assert_eq!(
+            err,
+            ContractError::Unauthorized {
+                sender: successor.clone()
+            }
+        );
+        let err = execute(
+            deps.as_mut(),
+            env.clone(),
+            mock_info("successor", &[]),
+            ExecuteMsg::AcceptOwnership {},
+        )
+        .unwrap_err();
+        assert_eq!(err, ContractError::NoPendingOwner {});
+
+        execute(
+            deps.as_mut(),
+            env.clone(),
+            mock_info("owner", &[]),
+            ExecuteMsg::ProposeNewOwner {
+                address: Addr::unchecked("mistyped"),
+            },
+        )
+        .unwrap();
+        execute(
+            deps.as_mut(),
+            env.clone(),
+            mock_info("owner", &[]),
+            ExecuteMsg::CancelOwnershipProposal {},
+        )
+        .unwrap();
+        assert_eq!(
+            owner_resp(deps.as_ref()),
+            OwnerResp {
+                owner: owner.clone(),
+                pending_owner: None
+            }
+        );
+
+        execute(deps.as_mut(), env.clone(), mock_info("owner", &[]), propose).unwrap();
+        assert_eq!(
+            owner_resp(deps.as_ref()).pending_owner,
+            Some(successor.clone())
+        );
+
+        let err = execute(
+            deps.as_mut(),
+            env.clone(),
+            mock_info("stranger", &[]),
+            ExecuteMsg::AcceptOwnership {},
+        )
+        .unwrap_err();
+        assert_eq!(
+            err,
+            ContractError::Unauthorized {
+                sender: Addr::unchecked("stranger")
+            }
+        );
+
+        execute(
+            deps.as_mut(),
+            env.clone(),
+            mock_info("successor", &[]),
+            ExecuteMsg::AcceptOwnership {},
+        )
+        .unwrap();
+        assert_eq!(
+            owner_resp(deps.as_ref()),
+            OwnerResp {
+                owner: successor,
+                pending_owner: None
+            }
+        );
+
+        let err = execute(
+            deps.as_mut(),
+            env,
+            mock_info("owner", &[]),
+            ExecuteMsg::CancelOwnershipProposal {},
+        )
+        .unwrap_err();
+        assert_eq!(err, ContractError::Unauthorized { sender: owner });
+    }
+
+    #[test]
+    fn run_registration_lookup() {
+        use crate::msg::RegistrationResp;
+
+        let mut deps = mock_dependencies();
+        let env = mock_env();
+        let alice = Addr::unchecked("secret1");
+        let bob = Addr::unchecked("secret2");
+
+        instantiate(
+            deps.as_mut(),
+            env.clone(),
+            mock_info("sender", &[]),
+            InstantiateMsg {
+                owner: Addr::unchecked("owner"),
+                offspring_id: 1,
+                offspring_hash: "".to_string(),
+            },
+        )
+        .unwrap();
+
+        for (id, address, contract_address) in [("alice", &alice, "rm1"), ("bob", &bob, "rm2")] {
+            reply(
+                deps.as_mut(),
+                env.clone(),
+                offspring_reply(id, address, contract_address, "key"),
+            )
+            .unwrap();
+        }
+
+        let resp = query::get_registration(deps.as_ref(), &alice).unwrap();
+        let resp: RegistrationResp = from_binary(&resp).unwrap();
+        assert_eq!(resp.id, "alice");
+        assert_eq!(resp.contract_address, Addr::unchecked("rm1"));
+
+        let err = query::get_registration(deps.as_ref(), &Addr::unchecked("secret3")).unwrap_err();
+        assert_eq!(
+            err,
+            ContractError::NonexistentRegistration {
+                address: Addr::unchecked("secret3")
+            }
+        );
+
+        for account in [&bob, &Addr::unchecked("owner")] {
+            let resp =
+                query::get_registration_by_contract(deps.as_ref(), account, Addr::unchecked("rm2"))
+                    .unwrap();
+            let resp: RegistrationResp = from_binary(&resp).unwrap();
+            assert_eq!(resp.id, "bob");
+            assert_eq!(resp.address, bob);
+        }
+
+        let err =
+            query::get_registration_by_contract(deps.as_ref(), &alice, Addr::unchecked("rm2"))
+                .unwrap_err();
+        assert_eq!(
+            err,
+            ContractError::Unauthorized {
+                sender: alice.clone()
+            }
+        );
+
+        execute(
+            deps.as_mut(),
+            env,
+            mock_info("secret2", &[]),
+            ExecuteMsg::Deregister {
+                patient_id: "bob".to_string(),
+            },
+        )
+        .unwrap();
+        let err = query::get_registration_by_contract(
+            deps.as_ref(),
+            &Addr::unchecked("owner"),
+            Addr::unchecked("rm2"),
+        )
+        .unwrap_err();
+        assert_eq!(
+            err,
+            ContractError::NonexistentRegistration {
+                address: Addr::unchecked("rm2")
+            }
+        );
+    }
+
+    #[test]
+    fn run_consent_forwarding() {
+        use crate::msg::{ConsentPurpose, OffspringExecuteMsg};
+        use crate::state::REGISTRY_KEY;
+
+        let mut deps = mock_dependencies();
+        let env = mock_env();
+        let sample_address = Addr::unchecked("secret1");
+
+        instantiate(
+            deps.as_mut(),
             env.clone(),
             mock_info("sender", &[]),
             InstantiateMsg {
@@ -280,57 +3524,655 @@ mod tests {
         )
         .unwrap();
 
-        let err = execute(
+        let resp = execute(
             deps.as_mut(),
             env.clone(),
-            mock_info("sender", &[]),
+            mock_info("owner", &[]),
             ExecuteMsg::Register {
-                id: sample_id.to_owned(),
+                id: "alice".to_string(),
                 address: sample_address.clone(),
                 pubkey: "".to_string(),
                 key: "".to_string(),
             },
         )
-        .unwrap_err();
+        .unwrap();
+        match &resp.messages[0].msg {
+            CosmosMsg::Wasm(WasmMsg::Instantiate { msg, .. }) => {
+                let msg: OffspringInstantiateMsg = from_binary(msg).unwrap();
+                assert!(msg.registry_key.is_some());
+                assert_eq!(
+                    msg.registry_key,
+                    REGISTRY_KEY.may_load(deps.as_ref().storage).unwrap()
+                );
+            }
+            msg => panic!("unexpected message {:?}", msg),
+        }
+        let resp = reply(
+            deps.as_mut(),
+            env.clone(),
+            offspring_reply("alice", &sample_address, "contract_address", ""),
+        )
+        .unwrap();
+        let pseudonym = patient_pseudonym(
+            &event_key(deps.as_ref()).unwrap(),
+            &Addr::unchecked("contract_address"),
+        );
+        assert_eq!(
+            resp.events[0].attributes[0].value,
+            "record_manager_instantiated"
+        );
+        assert_eq!(resp.events[0].attributes[2].value, pseudonym);
 
+        let grant_consent = ExecuteMsg::GrantConsent {
+            patient_id: "alice".to_string(),
+            purpose: ConsentPurpose::ModelTraining,
+            expires: env.block.time.plus_seconds(3600),
+        };
+        let err = execute(
+            deps.as_mut(),
+            env.clone(),
+            mock_info("owner", &[]),
+            grant_consent.clone(),
+        )
+        .unwrap_err();
         assert_eq!(
+            err,
             ContractError::Unauthorized {
-                sender: Addr::unchecked("sender")
+                sender: Addr::unchecked("owner")
+            }
+        );
+
+        let resp = execute(
+            deps.as_mut(),
+            env.clone(),
+            mock_info("secret1", &[]),
+            grant_consent,
+        )
+        .unwrap();
+        let attributes: Vec<_> = resp.events[0]
+            .attributes
+            .iter()
+            .map(|attribute| (attribute.key.as_str(), attribute.value.as_str()))
+            .collect();
+        assert_eq!(
+            attributes,
+            vec![
+                ("action", "grant_consent"),
+                ("actor_role", "patient"),
+                ("patient", pseudonym.as_str()),
+                ("contract_version", CONTRACT_VERSION),
+            ]
+        );
+        match &resp.messages[0].msg {
+            CosmosMsg::Wasm(WasmMsg::Execute {
+                contract_addr, msg, ..
+            }) => {
+                assert_eq!(contract_addr, "contract_address");
+                assert_eq!(
+                    from_binary::<OffspringExecuteMsg>(msg).unwrap(),
+                    OffspringExecuteMsg::GrantConsent {
+                        purpose: ConsentPurpose::ModelTraining,
+                        expires: env.block.time.plus_seconds(3600),
+                    }
+                );
+            }
+            msg => panic!("unexpected message {:?}", msg),
+        }
+    }
+
+    #[test]
+    fn run_break_glass() {
+        use crate::msg::OffspringExecuteMsg;
+
+        let mut deps = mock_dependencies();
+        let env = mock_env();
+        let paramedic = Addr::unchecked("paramedic");
+
+        instantiate(
+            deps.as_mut(),
+            env.clone(),
+            mock_info("sender", &[]),
+            InstantiateMsg {
+                owner: Addr::unchecked("owner"),
+                offspring_id: 1,
+                offspring_hash: "".to_string(),
             },
-            err
+        )
+        .unwrap();
+        reply(
+            deps.as_mut(),
+            env.clone(),
+            offspring_reply("alice", &Addr::unchecked("secret1"), "contract_address", ""),
+        )
+        .unwrap();
+
+        let roles = |can_break_glass: bool| ProviderRoles {
+            can_read_with_consent: !can_break_glass,
+            can_break_glass,
+            ..Default::default()
+        };
+        let break_glass = ExecuteMsg::BreakGlass {
+            patient_id: "alice".to_string(),
+            justification: "Unconscious patient in the emergency room".to_string(),
+        };
+
+        execute(
+            deps.as_mut(),
+            env.clone(),
+            mock_info("owner", &[]),
+            ExecuteMsg::AddProvider {
+                address: paramedic.to_string(),
+                name: "Ambulance Service".to_string(),
+                roles: roles(false),
+            },
+        )
+        .unwrap();
+        let err = execute(
+            deps.as_mut(),
+            env.clone(),
+            mock_info("paramedic", &[]),
+            break_glass.clone(),
+        )
+        .unwrap_err();
+        assert_eq!(
+            err,
+            ContractError::MissingRole {
+                sender: paramedic.clone(),
+                role: ProviderRole::BreakGlass
+            }
         );
 
         execute(
             deps.as_mut(),
             env.clone(),
             mock_info("owner", &[]),
-            ExecuteMsg::Register {
-                id: sample_id.to_owned(),
-                address: sample_address.clone(),
-                pubkey: "".to_string(),
-                key: "".to_string(),
+            ExecuteMsg::UpdateProvider {
+                address: paramedic.to_string(),
+                name: "Ambulance Service".to_string(),
+                roles: roles(true),
             },
         )
         .unwrap();
+        let resp = execute(
+            deps.as_mut(),
+            env.clone(),
+            mock_info("paramedic", &[]),
+            break_glass,
+        )
+        .unwrap();
+        assert_eq!(resp.events[0].ty, "break_glass");
+        match &resp.messages[0].msg {
+            CosmosMsg::Wasm(WasmMsg::Execute {
+                contract_addr, msg, ..
+            }) => {
+                assert_eq!(contract_addr, "contract_address");
+                assert_eq!(
+                    from_binary::<OffspringExecuteMsg>(msg).unwrap(),
+                    OffspringExecuteMsg::BreakGlass {
+                        provider: paramedic.clone(),
+                        justification: "Unconscious patient in the emergency room".to_string(),
+                    }
+                );
+            }
+            msg => panic!("unexpected message {:?}", msg),
+        }
 
-        let resp = query(
+        // only the patient can dispute the unlock
+        let dispute = ExecuteMsg::DisputeEmergencyAccess {
+            patient_id: "alice".to_string(),
+            index: 0,
+            reason: "I was conscious and refused".to_string(),
+        };
+        let err = execute(
+            deps.as_mut(),
+            env.clone(),
+            mock_info("paramedic", &[]),
+            dispute.clone(),
+        )
+        .unwrap_err();
+        assert_eq!(err, ContractError::Unauthorized { sender: paramedic });
+        let resp = execute(deps.as_mut(), env, mock_info("secret1", &[]), dispute).unwrap();
+        assert_eq!(1, resp.messages.len());
+    }
+
+    #[test]
+    fn run_research_export() {
+        use crate::msg::{
+            CheckConsentResp, ConsentPurpose, OffspringQueryMsg, ResearchDataResp,
+            ResearchExportResp, ResearchObservation,
+        };
+        use crate::observation::{ChestPainType, CvdObservation, RestEcg, Sex, StSlope, Thal};
+        use cosmwasm_std::{ContractResult, SystemResult, WasmQuery};
+
+        let mut deps = mock_dependencies();
+        let env = mock_env();
+
+        instantiate(
+            deps.as_mut(),
+            env.clone(),
+            mock_info("sender", &[]),
+            InstantiateMsg {
+                owner: Addr::unchecked("owner"),
+                offspring_id: 1,
+                offspring_hash: "".to_string(),
+            },
+        )
+        .unwrap();
+
+        for (id, address, contract_address) in [
+            ("alice", "secret1", "rm1"),
+            ("bob", "secret2", "rm2"),
+            ("carol", "secret3", "rm3"),
+            ("dave", "secret4", "rm4"),
+            ("erin", "secret5", "rm5"),
+        ] {
+            reply(
+                deps.as_mut(),
+                env.clone(),
+                offspring_reply(id, &Addr::unchecked(address), contract_address, ""),
+            )
+            .unwrap();
+        }
+        // dave's record manager was instantiated by 0.1.0
+        let mut dave = PERSON_STORE
+            .get(deps.as_ref().storage, &"dave".to_string())
+            .unwrap();
+        dave.legacy = true;
+        PERSON_STORE
+            .insert(deps.as_mut().storage, &"dave".to_string(), &dave)
+            .unwrap();
+
+        let observation = CvdObservation {
+            age: 63,
+            sex: Sex::Male,
+            chest_pain_type: ChestPainType::TypicalAngina,
+            resting_bp: 145,
+            cholesterol: 233,
+            fasting_blood_sugar: true,
+            rest_ecg: RestEcg::LeftVentricularHypertrophy,
+            max_heart_rate: 150,
+            exercise_angina: false,
+            oldpeak: 23,
+            slope: StSlope::Downsloping,
+            ca: 0,
+            thal: Thal::FixedDefect,
+        };
+        let period = env.block.time;
+        let data = ResearchDataResp {
+            observations: vec![ResearchObservation {
+                period,
+                observation: observation.clone(),
+            }],
+        };
+
+        // Only bob has withdrawn his consent, dave's record manager does not
+        // know the queries and erin's fails.
+        deps.querier.update_wasm(move |query| match query {
+            WasmQuery::Smart {
+                contract_addr, msg, ..
+            } => {
+                if contract_addr == "rm4" {
+                    panic!("queried a legacy record manager");
+                }
+                if contract_addr == "rm5" {
+                    return SystemResult::Ok(ContractResult::Err("out of gas".to_string()));
+                }
+                let resp = match from_binary(msg).unwrap() {
+                    OffspringQueryMsg::CheckConsent { .. } => to_binary(&CheckConsentResp {
+                        active: contract_addr != "rm2",
+                        expires: None,
+                    }),
+                    OffspringQueryMsg::ResearchData { .. } => to_binary(&data),
+                    _ => panic!("unexpected query"),
+                };
+                SystemResult::Ok(ContractResult::Ok(resp.unwrap()))
+            }
+            _ => panic!("unexpected query"),
+        });
+
+        let resp =
+            query::research_export(deps.as_ref(), ConsentPurpose::ModelTraining, None, Some(2))
+                .unwrap();
+        let first: ResearchExportResp = from_binary(&resp).unwrap();
+        assert_eq!(first.rows.len(), 1);
+        assert_eq!(first.rows[0].period, period);
+        assert_eq!(first.rows[0].observation, observation);
+        assert_eq!(first.next, Some(2));
+
+        let resp = query::research_export(
             deps.as_ref(),
-            env,
-            QueryMsg::Info {
-                id: sample_id.to_owned(),
-                key: "".to_string(),
+            ConsentPurpose::ModelTraining,
+            first.next,
+            Some(2),
+        )
+        .unwrap();
+        let second: ResearchExportResp = from_binary(&resp).unwrap();
+        assert_eq!(second.rows.len(), 1);
+        // dave shared the page with carol without failing it
+        assert_eq!(second.next, Some(4));
+
+        let resp = query::research_export(
+            deps.as_ref(),
+            ConsentPurpose::ModelTraining,
+            second.next,
+            Some(2),
+        )
+        .unwrap();
+        let last: ResearchExportResp = from_binary(&resp).unwrap();
+        assert!(last.rows.is_empty());
+        assert_eq!(last.next, None);
+
+        // an empty page would never move the cursor
+        let resp =
+            query::research_export(deps.as_ref(), ConsentPurpose::ModelTraining, None, Some(0))
+                .unwrap();
+        let resp: ResearchExportResp = from_binary(&resp).unwrap();
+        assert_eq!(resp.rows.len(), 1);
+        assert_eq!(resp.next, Some(1));
+        assert_ne!(first.rows[0].pseudonym, second.rows[0].pseudonym);
+        for row in first.rows.iter().chain(second.rows.iter()) {
+            assert!(!row.pseudonym.contains("alice"));
+            assert!(!row.pseudonym.contains("carol"));
+        }
+
+        // Pseudonyms are stable within a purpose and differ across purposes.
+        let resp =
+            query::research_export(deps.as_ref(), ConsentPurpose::ModelTraining, None, Some(1))
+                .unwrap();
+        let again: ResearchExportResp = from_binary(&resp).unwrap();
+        assert_eq!(again.rows[0].pseudonym, first.rows[0].pseudonym);
+
+        let resp = query::research_export(
+            deps.as_ref(),
+            ConsentPurpose::Study {
+                study_id: "cvd-2026".to_string(),
             },
+            None,
+            Some(1),
         )
         .unwrap();
-        let resp: InfoResp = from_binary(&resp).unwrap();
+        let study: ResearchExportResp = from_binary(&resp).unwrap();
+        assert_ne!(study.rows[0].pseudonym, first.rows[0].pseudonym);
+    }
+
+    #[test]
+    fn provider_roles_stored_before_research() {
+        let roles: ProviderRoles = from_slice(
+            br#"{"can_register":true,"can_write_records":false,"can_read_with_consent":false}"#,
+        )
+        .unwrap();
+        assert!(roles.can_register);
+        assert!(!roles.can_research);
+        assert!(!roles.can_break_glass);
+    }
+
+    #[test]
+    fn run_aggregate_stats() {
+        use crate::msg::{
+            AggregateStatsResp, CheckConsentResp, ConsentPurpose, OffspringQueryMsg,
+            PrivacyBudgetResp, ResearchDataResp, ResearchObservation,
+        };
+        use crate::observation::{ChestPainType, CvdObservation, RestEcg, Sex, StSlope, Thal};
+        use crate::privacy::{FeatureRange, Statistic};
+        use crate::risk::{Feature, SCALE};
+        use cosmwasm_std::{ContractResult, Decimal, SystemResult, WasmQuery};
+
+        let mut deps = mock_dependencies();
+        let env = mock_env();
+        let researcher = Addr::unchecked("researcher");
+
+        instantiate(
+            deps.as_mut(),
+            env.clone(),
+            mock_info("sender", &[]),
+            InstantiateMsg {
+                owner: Addr::unchecked("owner"),
+                offspring_id: 1,
+                offspring_hash: "".to_string(),
+            },
+        )
+        .unwrap();
+
+        for (id, address, contract_address) in [
+            ("alice", "secret1", "rm1"),
+            ("bob", "secret2", "rm2"),
+            ("carol", "secret3", "rm3"),
+            ("dave", "secret4", "rm4"),
+            ("erin", "secret5", "rm5"),
+        ] {
+            reply(
+                deps.as_mut(),
+                env.clone(),
+                offspring_reply(id, &Addr::unchecked(address), contract_address, ""),
+            )
+            .unwrap();
+        }
+        // erin's record manager was instantiated by 0.1.0
+        let mut erin = PERSON_STORE
+            .get(deps.as_ref().storage, &"erin".to_string())
+            .unwrap();
+        erin.legacy = true;
+        PERSON_STORE
+            .insert(deps.as_mut().storage, &"erin".to_string(), &erin)
+            .unwrap();
+
+        deps.querier.update_wasm(move |query| match query {
+            WasmQuery::Smart { contract_addr, .. } if contract_addr == "rm4" => {
+                SystemResult::Ok(ContractResult::Err("out of gas".to_string()))
+            }
+            WasmQuery::Smart { contract_addr, .. } if contract_addr == "rm5" => {
+                panic!("queried a legacy record manager")
+            }
+            WasmQuery::Smart {
+                contract_addr, msg, ..
+            } => {
+                let age = match contract_addr.as_str() {
+                    "rm1" => 45,
+                    "rm2" => 63,
+                    _ => 70,
+                };
+                let resp = match from_binary(msg).unwrap() {
+                    OffspringQueryMsg::CheckConsent { .. } => to_binary(&CheckConsentResp {
+                        active: true,
+                        expires: None,
+                    }),
+                    OffspringQueryMsg::ResearchData { .. } => to_binary(&ResearchDataResp {
+                        observations: vec![ResearchObservation {
+                            period: mock_env().block.time,
+                            observation: CvdObservation {
+                                age,
+                                sex: Sex::Male,
+                                chest_pain_type: ChestPainType::TypicalAngina,
+                                resting_bp: 145,
+                                cholesterol: 233,
+                                fasting_blood_sugar: true,
+                                rest_ecg: RestEcg::LeftVentricularHypertrophy,
+                                max_heart_rate: 150,
+                                exercise_angina: false,
+                                oldpeak: 23,
+                                slope: StSlope::Downsloping,
+                                ca: 0,
+                                thal: Thal::FixedDefect,
+                            },
+                        }],
+                    }),
+                    _ => panic!("unexpected query"),
+                };
+                SystemResult::Ok(ContractResult::Ok(resp.unwrap()))
+            }
+            _ => panic!("unexpected query"),
+        });
+
+        let aggregate_stats = ExecuteMsg::AggregateStats {
+            purpose: ConsentPurpose::ModelTraining,
+            statistic: Statistic::Count {},
+            cohort: Some(FeatureRange {
+                feature: Feature::Age,
+                min: 60 * SCALE as i64,
+                max: 80 * SCALE as i64,
+            }),
+            epsilon: Decimal::percent(50),
+        };
 
+        let err = execute(
+            deps.as_mut(),
+            env.clone(),
+            mock_info("researcher", &[]),
+            aggregate_stats.clone(),
+        )
+        .unwrap_err();
         assert_eq!(
-            resp,
-            InfoResp {
-                address: sample_address,
-                contract_address: Addr::unchecked("contract_address"),
-                pubkey: "".to_string()
+            err,
+            ContractError::Unauthorized {
+                sender: researcher.clone()
+            }
+        );
+
+        execute(
+            deps.as_mut(),
+            env.clone(),
+            mock_info("owner", &[]),
+            ExecuteMsg::AddProvider {
+                address: researcher.to_string(),
+                name: "CVD Study".to_string(),
+                roles: ProviderRoles {
+                    can_research: true,
+                    ..Default::default()
+                },
+            },
+        )
+        .unwrap();
+
+        let err = execute(
+            deps.as_mut(),
+            env.clone(),
+            mock_info("researcher", &[]),
+            aggregate_stats.clone(),
+        )
+        .unwrap_err();
+        assert_eq!(
+            err,
+            ContractError::InsufficientPrivacyBudget {
+                requested: Decimal::percent(50),
+                remaining: Decimal::zero(),
+            }
+        );
+
+        let set_budget = ExecuteMsg::SetPrivacyBudget {
+            account: researcher.clone(),
+            epsilon: Decimal::one(),
+        };
+        let err = execute(
+            deps.as_mut(),
+            env.clone(),
+            mock_info("researcher", &[]),
+            set_budget.clone(),
+        )
+        .unwrap_err();
+        assert_eq!(
+            err,
+            ContractError::Unauthorized {
+                sender: researcher.clone()
+            }
+        );
+        execute(
+            deps.as_mut(),
+            env.clone(),
+            mock_info("owner", &[]),
+            set_budget,
+        )
+        .unwrap();
+
+        let resp = execute(
+            deps.as_mut(),
+            env.clone(),
+            mock_info("researcher", &[]),
+            aggregate_stats.clone(),
+        )
+        .unwrap();
+        let resp: AggregateStatsResp = from_binary(&resp.data.unwrap()).unwrap();
+        assert_eq!(resp.values.len(), 1);
+        // two patients in the cohort, plus Laplace noise of scale 2; dave's
+        // record manager fails and erin's is legacy, so both are left out
+        assert!((resp.values[0] - 2 * SCALE as i64).abs() < 30 * SCALE as i64);
+        assert_eq!(resp.remaining_budget, Decimal::percent(50));
+
+        let resp = execute(
+            deps.as_mut(),
+            env.clone(),
+            mock_info("researcher", &[]),
+            ExecuteMsg::AggregateStats {
+                purpose: ConsentPurpose::ModelTraining,
+                statistic: Statistic::Histogram {
+                    feature: Feature::Age,
+                    edges: vec![40 * SCALE as i64, 60 * SCALE as i64, 80 * SCALE as i64],
+                },
+                cohort: None,
+                epsilon: Decimal::percent(30),
+            },
+        )
+        .unwrap();
+        let resp: AggregateStatsResp = from_binary(&resp.data.unwrap()).unwrap();
+        assert_eq!(resp.values.len(), 2);
+        assert_eq!(resp.remaining_budget, Decimal::percent(20));
+
+        let err = execute(
+            deps.as_mut(),
+            env.clone(),
+            mock_info("researcher", &[]),
+            aggregate_stats,
+        )
+        .unwrap_err();
+        assert_eq!(
+            err,
+            ContractError::InsufficientPrivacyBudget {
+                requested: Decimal::percent(50),
+                remaining: Decimal::percent(20),
+            }
+        );
+
+        let resp = query::get_privacy_budget(deps.as_ref(), &researcher).unwrap();
+        let resp: PrivacyBudgetResp = from_binary(&resp).unwrap();
+        assert_eq!(resp.total, Decimal::one());
+        assert_eq!(resp.spent, Decimal::percent(80));
+        assert_eq!(resp.remaining, Decimal::percent(20));
+
+        // a registry too large for one call is refused without charging
+        for n in 5..=MAX_AGGREGATE_PATIENTS {
+            let address = format!("secret{}", n + 1);
+            reply(
+                deps.as_mut(),
+                env.clone(),
+                offspring_reply(
+                    &format!("patient{}", n),
+                    &Addr::unchecked(&address),
+                    &format!("rm{}", n + 1),
+                    "",
+                ),
+            )
+            .unwrap();
+        }
+        let err = execute(
+            deps.as_mut(),
+            env,
+            mock_info("researcher", &[]),
+            ExecuteMsg::AggregateStats {
+                purpose: ConsentPurpose::ModelTraining,
+                statistic: Statistic::Count {},
+                cohort: None,
+                epsilon: Decimal::percent(10),
+            },
+        )
+        .unwrap_err();
+        assert_eq!(
+            err,
+            ContractError::CohortTooLarge {
+                patients: MAX_AGGREGATE_PATIENTS + 1,
+                max: MAX_AGGREGATE_PATIENTS,
             }
         );
+        let resp = query::get_privacy_budget(deps.as_ref(), &researcher).unwrap();
+        let resp: PrivacyBudgetResp = from_binary(&resp).unwrap();
+        assert_eq!(resp.spent, Decimal::percent(80));
     }
 }
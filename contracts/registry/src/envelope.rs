@@ -0,0 +1,57 @@
+//! Mirror of the record-manager envelope types, used to relay encrypted records
+//! to the patient record managers unchanged once the owner is found among the
+//! recipients.
+
+use cosmwasm_std::Binary;
+use schemars::JsonSchema;
+use secret_toolkit::crypto::sha_256;
+use serde::{Deserialize, Serialize};
+
+use crate::error::ContractError;
+
+#[derive(Serialize, Deserialize, JsonSchema, PartialEq, Eq, Debug, Clone, Copy)]
+#[serde(rename_all = "snake_case")]
+pub enum EnvelopeAlgorithm {
+    Secp256k1Aes256Gcm,
+    X25519XChaCha20Poly1305,
+}
+
+#[derive(Serialize, Deserialize, JsonSchema, PartialEq, Eq, Debug, Clone)]
+#[serde(rename_all = "snake_case")]
+pub struct EnvelopeRecipient {
+    pub key_id: String,
+    pub sealed_key: Binary,
+}
+
+#[derive(Serialize, Deserialize, JsonSchema, PartialEq, Eq, Debug, Clone)]
+#[serde(rename_all = "snake_case")]
+pub struct EncryptedEnvelope {
+    pub algorithm: EnvelopeAlgorithm,
+    pub ephemeral_pubkey: Binary,
+    pub nonce: Binary,
+    pub ciphertext: Binary,
+    pub recipients: Vec<EnvelopeRecipient>,
+}
+
+impl EncryptedEnvelope {
+    /// Checks that the owner, identified by their registered public key, is
+    /// among the recipients. The record managers validate the rest.
+    pub fn check_owner(&self, owner_pubkey: &[u8]) -> Result<(), ContractError> {
+        let owner_key_id = key_id(owner_pubkey);
+        if !self
+            .recipients
+            .iter()
+            .any(|recipient| recipient.key_id == owner_key_id)
+        {
+            return Err(ContractError::InvalidEnvelope {
+                reason: "envelope is not sealed for the owner".to_string(),
+            });
+        }
+        Ok(())
+    }
+}
+
+/// Key id of a recipient: the base64 SHA-256 of their public key.
+pub fn key_id(pubkey: &[u8]) -> String {
+    Binary::from(sha_256(pubkey).as_slice()).to_base64()
+}
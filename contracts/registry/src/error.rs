@@ -1,20 +1,73 @@
-use cosmwasm_std::{Addr, StdError};
+use cosmwasm_std::{Addr, Decimal, StdError};
 use thiserror::Error;
 
+use crate::state::{ModelStatus, ProviderRole};
+
 #[derive(Error, Debug, PartialEq)]
 pub enum ContractError {
     #[error("{0}")]
     StdError(#[from] StdError),
     #[error("{sender} is not authorized to perform the requested action")]
     Unauthorized { sender: Addr },
+    #[error("{sender} does not hold the {role:?} provider role")]
+    MissingRole { sender: Addr, role: ProviderRole },
+    #[error("No ownership transfer has been proposed")]
+    NoPendingOwner {},
+    #[error("No provider with address {address} found")]
+    NonexistentProvider { address: Addr },
+    #[error("A provider with address {address} already exists")]
+    ProviderExists { address: Addr },
     #[error("Reply id {id} was not expected")]
     UnexpectedReplyId { id: u64 },
     #[error("Failed to instantiate a Record Manager contract")]
     OffspringInstantiationError {},
     #[error("No user with id {id} found")]
     NonexistentUser { id: String },
+    #[error("A user with id {id} is already registered")]
+    UserExists { id: String },
+    #[error("{address} is already registered")]
+    AddressRegistered { address: Addr },
+    #[error("No registration for {address} found")]
+    NonexistentRegistration { address: Addr },
     #[error("Key {key} is not valid for this query")]
     InvalidKey { key: String },
+    #[error("Risk model has {features} features but {weights} weights")]
+    InvalidRiskModel { features: usize, weights: usize },
+    #[error("No risk model is active")]
+    NoRiskModel {},
+    #[error("No model with version {version} found")]
+    NonexistentModel { version: String },
+    #[error("A model with version {version} already exists")]
+    ModelExists { version: String },
+    #[error("Model {version} is {status:?} and cannot be {action}")]
+    InvalidModelStatus {
+        version: String,
+        status: ModelStatus,
+        action: String,
+    },
+    #[error("Invalid encrypted envelope: {reason}")]
+    InvalidEnvelope { reason: String },
+    #[error("Record {record_id} has no structured observation")]
+    MissingObservation { record_id: String },
+    #[error("Invalid statistic: {reason}")]
+    InvalidStatistic { reason: String },
+    #[error("Epsilon must be positive")]
+    InvalidEpsilon {},
+    #[error("Epsilon {requested} exceeds the remaining privacy budget of {remaining}")]
+    InsufficientPrivacyBudget {
+        requested: Decimal,
+        remaining: Decimal,
+    },
+    #[error("Aggregate statistics cover at most {max} patients, {patients} are registered")]
+    CohortTooLarge { patients: u32, max: u32 },
+    #[error("No block randomness is available")]
+    MissingRandomness {},
+    #[error("Cannot migrate {found} to {expected}")]
+    ContractNameMismatch { expected: String, found: String },
+    #[error("Cannot migrate from version {stored} to older version {current}")]
+    CannotDowngrade { stored: String, current: String },
+    #[error("Invalid contract version {version}")]
+    InvalidVersion { version: String },
     #[error("Error: {val:?}")]
     CustomError { val: String },
 }
@@ -1,8 +1,15 @@
-use cosmwasm_std::{to_binary, Addr, Binary, CosmosMsg, StdResult, Timestamp, WasmMsg};
+use cosmwasm_std::{to_binary, Addr, Binary, CosmosMsg, Decimal, StdResult, Timestamp, WasmMsg};
 use schemars::JsonSchema;
 use secret_toolkit::{permit::Permit, utils::InitCallback};
 use serde::{Deserialize, Serialize};
 
+use crate::attachment::Attachment;
+use crate::envelope::EncryptedEnvelope;
+use crate::observation::CvdObservation;
+use crate::privacy::{FeatureRange, Statistic};
+use crate::risk::RiskModel;
+use crate::state::{ErasureReceipt, ModelMetric, ModelVersion, ProviderRoles};
+
 #[derive(Serialize, Deserialize, PartialEq, Debug, Clone)]
 #[serde(rename_all = "snake_case")]
 pub struct InstantiateMsg {
@@ -13,10 +20,17 @@ pub struct InstantiateMsg {
 
 #[derive(Serialize, Deserialize, PartialEq, Debug, Clone)]
 #[serde(rename_all = "snake_case")]
+pub struct MigrateMsg {}
+
+#[derive(Serialize, Deserialize, PartialEq, Debug, Clone)]
+#[serde(rename_all = "snake_case")]
+#[allow(clippy::large_enum_variant)]
 pub enum ExecuteMsg {
     Register {
         id: String,
         address: Addr,
+        /// Base64 of the patient's compressed secp256k1 public key. Write
+        /// permits are checked against it.
         pubkey: String,
         key: String,
     },
@@ -26,6 +40,167 @@ pub enum ExecuteMsg {
         record: Record,
         permit: Permit<RecordPermissions>,
     },
+    AmendRecord {
+        patient_id: String,
+        record_id: String,
+        record: Record,
+        reason: String,
+        permit: Permit<RecordPermissions>,
+    },
+    RevokePermit {
+        patient_id: String,
+        permit_name: String,
+    },
+    GrantAccess {
+        patient_id: String,
+        grantee: Addr,
+        record_ids: Option<Vec<String>>,
+        expires: Option<Timestamp>,
+    },
+    RevokeAccess {
+        patient_id: String,
+        grantee: Addr,
+    },
+    /// Proposes a new owner, which takes over only once it accepts.
+    ProposeNewOwner {
+        address: Addr,
+    },
+    AcceptOwnership {},
+    CancelOwnershipProposal {},
+    /// Adds a provider that is not registered yet.
+    AddProvider {
+        address: String,
+        name: String,
+        roles: ProviderRoles,
+    },
+    /// Replaces the name and roles of a registered provider.
+    UpdateProvider {
+        address: String,
+        name: String,
+        roles: ProviderRoles,
+    },
+    RemoveProvider {
+        address: Addr,
+    },
+    /// Sets the record-manager code instantiated for new registrations.
+    UpdateOffspring {
+        code_id: u64,
+        code_hash: String,
+    },
+    /// Migrates a page of record managers that still run older code to the
+    /// current record-manager code. Only record managers instantiated with the
+    /// registry as admin can be migrated; those registered by 0.1.0 are
+    /// skipped and listed in `MigrateRecordManagersResp`.
+    MigrateRecordManagers {
+        start_after: Option<String>,
+        limit: Option<u32>,
+    },
+    GrantConsent {
+        patient_id: String,
+        purpose: ConsentPurpose,
+        expires: Timestamp,
+    },
+    WithdrawConsent {
+        patient_id: String,
+        purpose: ConsentPurpose,
+    },
+    /// Erases the patient's records and removes them from the registry. The
+    /// records of a record manager instantiated by 0.1.0 cannot be erased and
+    /// are only unlinked, which the erasure receipt records.
+    Deregister {
+        patient_id: String,
+    },
+    CreateViewingKey {
+        entropy: String,
+    },
+    SetViewingKey {
+        key: String,
+    },
+    /// Logs a view of a patient record in the patient's access log, attested by
+    /// the viewer's permit.
+    LogView {
+        patient_id: String,
+        record_id: String,
+        permit: Permit<RecordPermissions>,
+    },
+    RegisterModel {
+        version: String,
+        model: RiskModel,
+        weights_hash: String,
+        dataset_hash: String,
+        metrics: Vec<ModelMetric>,
+    },
+    ActivateModel {
+        version: String,
+    },
+    RetireModel {
+        version: String,
+    },
+    /// Sets the total epsilon a research account may spend on aggregate
+    /// statistics. What it already spent is kept.
+    SetPrivacyBudget {
+        account: Addr,
+        epsilon: Decimal,
+    },
+    /// Computes a differentially private statistic over the latest observation
+    /// of every registered patient who consents to `purpose`, optionally
+    /// restricted to a cohort, and spends `epsilon` of the sender's privacy
+    /// budget. Patients whose record manager fails to answer are left out. The
+    /// result is returned as `AggregateStatsResp` in the response data.
+    AggregateStats {
+        purpose: ConsentPurpose,
+        statistic: Statistic,
+        cohort: Option<FeatureRange>,
+        epsilon: Decimal,
+    },
+    /// Unlocks a patient's records for the sender, an emergency provider, for
+    /// a short fixed window. The justification is kept for the patient to
+    /// review.
+    BreakGlass {
+        patient_id: String,
+        justification: String,
+    },
+    /// Lets the patient dispute an emergency unlock of their records.
+    DisputeEmergencyAccess {
+        patient_id: String,
+        index: u32,
+        reason: String,
+    },
+}
+
+impl ExecuteMsg {
+    /// Name of the message in emitted events.
+    pub fn action(&self) -> &'static str {
+        match self {
+            ExecuteMsg::Register { .. } => "register",
+            ExecuteMsg::AddRecord { .. } => "add_record",
+            ExecuteMsg::AmendRecord { .. } => "amend_record",
+            ExecuteMsg::RevokePermit { .. } => "revoke_permit",
+            ExecuteMsg::GrantAccess { .. } => "grant_access",
+            ExecuteMsg::RevokeAccess { .. } => "revoke_access",
+            ExecuteMsg::ProposeNewOwner { .. } => "propose_new_owner",
+            ExecuteMsg::AcceptOwnership { .. } => "accept_ownership",
+            ExecuteMsg::CancelOwnershipProposal { .. } => "cancel_ownership_proposal",
+            ExecuteMsg::AddProvider { .. } => "add_provider",
+            ExecuteMsg::UpdateProvider { .. } => "update_provider",
+            ExecuteMsg::RemoveProvider { .. } => "remove_provider",
+            ExecuteMsg::UpdateOffspring { .. } => "update_offspring",
+            ExecuteMsg::MigrateRecordManagers { .. } => "migrate_record_managers",
+            ExecuteMsg::GrantConsent { .. } => "grant_consent",
+            ExecuteMsg::WithdrawConsent { .. } => "withdraw_consent",
+            ExecuteMsg::Deregister { .. } => "deregister",
+            ExecuteMsg::CreateViewingKey { .. } => "create_viewing_key",
+            ExecuteMsg::SetViewingKey { .. } => "set_viewing_key",
+            ExecuteMsg::LogView { .. } => "log_view",
+            ExecuteMsg::RegisterModel { .. } => "register_model",
+            ExecuteMsg::ActivateModel { .. } => "activate_model",
+            ExecuteMsg::RetireModel { .. } => "retire_model",
+            ExecuteMsg::SetPrivacyBudget { .. } => "set_privacy_budget",
+            ExecuteMsg::AggregateStats { .. } => "aggregate_stats",
+            ExecuteMsg::BreakGlass { .. } => "break_glass",
+            ExecuteMsg::DisputeEmergencyAccess { .. } => "dispute_emergency_access",
+        }
+    }
 }
 
 #[derive(Serialize, Deserialize, PartialEq, Debug, Clone)]
@@ -40,6 +215,48 @@ pub enum QueryMsg {
         permit: Permit<RecordPermissions>,
         query: QueryWithPermit,
     },
+    /// Consent of a patient for `purpose`, for providers reading with consent.
+    CheckConsent {
+        id: String,
+        purpose: ConsentPurpose,
+        permit: Permit,
+    },
+    /// De-identified observations of a page of patients who consented to
+    /// `purpose`, for approved research accounts. `start` is the position of
+    /// the first patient to scan.
+    ResearchExport {
+        purpose: ConsentPurpose,
+        permit: Permit,
+        start: Option<u32>,
+        limit: Option<u32>,
+    },
+    /// Registration of the permit signer.
+    Registration {
+        permit: Permit,
+    },
+    /// Registration owning a record manager, for its patient or the owner.
+    RegistrationByContract {
+        contract_address: Addr,
+        permit: Permit,
+    },
+    Owner {},
+    Providers {
+        start_after: Option<Addr>,
+        limit: Option<u32>,
+    },
+    ErasureReceipts {
+        start: Option<u32>,
+        limit: Option<u32>,
+    },
+    ActiveModel {},
+    /// Privacy budget of the permit signer.
+    PrivacyBudget {
+        permit: Permit,
+    },
+    Models {
+        start_after: Option<String>,
+        limit: Option<u32>,
+    },
 }
 
 #[derive(Serialize, Deserialize, PartialEq, Debug, Clone)]
@@ -50,6 +267,89 @@ pub struct InfoResp {
     pub pubkey: String,
 }
 
+#[derive(Serialize, Deserialize, PartialEq, Debug, Clone)]
+#[serde(rename_all = "snake_case")]
+pub struct CreateViewingKeyResp {
+    pub key: String,
+}
+
+/// Response data of `MigrateRecordManagers`.
+#[derive(Serialize, Deserialize, PartialEq, Debug, Clone)]
+#[serde(rename_all = "snake_case")]
+pub struct MigrateRecordManagersResp {
+    /// Patients in the page whose record managers have no admin and were left
+    /// on their code
+    pub skipped: Vec<String>,
+}
+
+#[derive(Serialize, Deserialize, PartialEq, Debug, Clone)]
+#[serde(rename_all = "snake_case")]
+pub struct ErasureReceiptsResp {
+    pub receipts: Vec<ErasureReceipt>,
+    pub total: u32,
+}
+
+#[derive(Serialize, Deserialize, PartialEq, Debug, Clone)]
+#[serde(rename_all = "snake_case")]
+pub struct RiskResp {
+    pub record_id: String,
+    pub probability: Decimal,
+    pub model_version: String,
+}
+
+#[derive(Serialize, Deserialize, PartialEq, Debug, Clone)]
+#[serde(rename_all = "snake_case")]
+pub struct ModelsResp {
+    pub models: Vec<ModelVersion>,
+}
+
+#[derive(Serialize, Deserialize, PartialEq, Debug, Clone)]
+#[serde(rename_all = "snake_case")]
+pub struct AggregateStatsResp {
+    /// Noisy values scaled by 1_000_000, one per histogram bin
+    pub values: Vec<i64>,
+    pub epsilon: Decimal,
+    pub remaining_budget: Decimal,
+}
+
+#[derive(Serialize, Deserialize, PartialEq, Debug, Clone)]
+#[serde(rename_all = "snake_case")]
+pub struct PrivacyBudgetResp {
+    pub total: Decimal,
+    pub spent: Decimal,
+    pub remaining: Decimal,
+}
+
+#[derive(Serialize, Deserialize, PartialEq, Debug, Clone)]
+#[serde(rename_all = "snake_case")]
+pub struct RegistrationResp {
+    pub id: String,
+    pub address: Addr,
+    pub contract_address: Addr,
+    pub pubkey: String,
+}
+
+#[derive(Serialize, Deserialize, PartialEq, Debug, Clone)]
+#[serde(rename_all = "snake_case")]
+pub struct OwnerResp {
+    pub owner: Addr,
+    pub pending_owner: Option<Addr>,
+}
+
+#[derive(Serialize, Deserialize, PartialEq, Debug, Clone)]
+#[serde(rename_all = "snake_case")]
+pub struct ProviderResp {
+    pub address: Addr,
+    pub name: String,
+    pub roles: ProviderRoles,
+}
+
+#[derive(Serialize, Deserialize, PartialEq, Debug, Clone)]
+#[serde(rename_all = "snake_case")]
+pub struct ProvidersResp {
+    pub providers: Vec<ProviderResp>,
+}
+
 #[derive(Serialize, Deserialize, PartialEq, Debug, Clone)]
 #[serde(rename_all = "snake_case")]
 pub struct OffspringInstantiateMsg {
@@ -57,12 +357,19 @@ pub struct OffspringInstantiateMsg {
     pub owner_id: String,
     pub owner_pubkey: String,
     pub key: String,
+    pub registry_key: Option<String>,
 }
 
 impl InitCallback for OffspringInstantiateMsg {
     const BLOCK_SIZE: usize = 256;
 }
 
+#[derive(Serialize, Deserialize, PartialEq, Debug, Clone)]
+#[serde(rename_all = "snake_case")]
+pub struct OffspringMigrateMsg {
+    pub registry_key: Option<String>,
+}
+
 #[derive(Serialize, Deserialize, PartialEq, Debug, Clone)]
 #[serde(rename_all = "snake_case")]
 pub struct OffspringResp {
@@ -75,8 +382,61 @@ pub struct OffspringResp {
 
 #[derive(Serialize, Deserialize, PartialEq, Debug, Clone)]
 #[serde(rename_all = "snake_case")]
+#[allow(clippy::large_enum_variant)]
 pub enum OffspringExecuteMsg {
     AddRecord(AddRecordMsg),
+    AmendRecord(AmendRecordMsg),
+    RevokePermit {
+        permit_name: String,
+    },
+    GrantAccess {
+        grantee: Addr,
+        record_ids: Option<Vec<String>>,
+        expires: Option<Timestamp>,
+    },
+    RevokeAccess {
+        grantee: Addr,
+    },
+    GrantConsent {
+        purpose: ConsentPurpose,
+        expires: Timestamp,
+    },
+    WithdrawConsent {
+        purpose: ConsentPurpose,
+    },
+    Erase {},
+    LogView {
+        record_id: String,
+        permit: Permit<RecordPermissions>,
+    },
+    BreakGlass {
+        provider: Addr,
+        justification: String,
+    },
+    DisputeEmergencyAccess {
+        index: u32,
+        reason: String,
+    },
+}
+
+impl OffspringExecuteMsg {
+    pub fn into_cosmos_msg<T: Into<String>, C>(
+        self,
+        contract_addr: T,
+        code_hash: String,
+    ) -> StdResult<CosmosMsg<C>>
+    where
+        C: Clone + std::fmt::Debug + PartialEq,
+    {
+        let msg = to_binary(&self)?;
+        let execute = WasmMsg::Execute {
+            contract_addr: contract_addr.into(),
+            code_hash,
+            msg,
+            funds: vec![],
+        };
+        Ok(execute.into())
+    }
 }
 
 #[derive(Serialize, Deserialize, PartialEq, Debug, Clone)]
@@ -86,7 +446,13 @@ pub struct AddRecordMsg {
     pub title: String,
     pub description: String,
     pub data: String,
+    pub observation: Option<CvdObservation>,
+    pub envelope: Option<EncryptedEnvelope>,
+    #[serde(default)]
+    pub attachments: Vec<Attachment>,
+    pub category: Option<RecordCategory>,
     pub permit: Permit<RecordPermissions>,
+    pub author: Option<Addr>,
 }
 
 impl AddRecordMsg {
@@ -106,7 +472,7 @@ impl AddRecordMsg {
         let msg = self.into_binary()?;
         let execute = WasmMsg::Execute {
             contract_addr: contract_addr.into(),
-            code_hash: code_hash,
+            code_hash,
             msg,
             funds: vec![],
         };
@@ -114,6 +480,23 @@ impl AddRecordMsg {
     }
 }
 
+#[derive(Serialize, Deserialize, PartialEq, Debug, Clone)]
+#[serde(rename_all = "snake_case")]
+pub struct AmendRecordMsg {
+    pub id: String,
+    pub title: String,
+    pub description: String,
+    pub data: String,
+    pub observation: Option<CvdObservation>,
+    pub envelope: Option<EncryptedEnvelope>,
+    #[serde(default)]
+    pub attachments: Vec<Attachment>,
+    pub category: Option<RecordCategory>,
+    pub reason: String,
+    pub permit: Permit<RecordPermissions>,
+    pub author: Option<Addr>,
+}
+
 #[derive(Serialize, Deserialize, PartialEq, Debug, Clone)]
 #[serde(rename_all = "snake_case")]
 pub enum OffspringQueryMsg {
@@ -121,6 +504,55 @@ pub enum OffspringQueryMsg {
         permit: Permit<RecordPermissions>,
         record_id: String,
     },
+    ListRecords {
+        permit: Permit<RecordPermissions>,
+        start_after: Option<String>,
+        limit: Option<u32>,
+    },
+    ViewHistory {
+        permit: Permit<RecordPermissions>,
+        record_id: String,
+    },
+    ListByCategory {
+        permit: Permit<RecordPermissions>,
+        category: RecordCategory,
+        start_after: Option<String>,
+        limit: Option<u32>,
+    },
+    ListByTimeRange {
+        permit: Permit<RecordPermissions>,
+        from: Timestamp,
+        to: Timestamp,
+        start_after: Option<u32>,
+        limit: Option<u32>,
+    },
+    VerifyAttachment {
+        permit: Permit<RecordPermissions>,
+        record_id: String,
+        uri: String,
+        sha256: Binary,
+    },
+    AccessLog {
+        permit: Permit<RecordPermissions>,
+        start: Option<u32>,
+        limit: Option<u32>,
+    },
+    Consents {
+        permit: Permit<RecordPermissions>,
+    },
+    EmergencyAccessLog {
+        permit: Permit<RecordPermissions>,
+        start: Option<u32>,
+        limit: Option<u32>,
+    },
+    CheckConsent {
+        registry_key: String,
+        purpose: ConsentPurpose,
+    },
+    ResearchData {
+        registry_key: String,
+        purpose: ConsentPurpose,
+    },
 }
 
 #[derive(Serialize, Deserialize, PartialEq, Debug, Clone)]
@@ -130,18 +562,246 @@ pub struct Record {
     pub timestamp: Option<Timestamp>,
     pub description: String,
     pub data: String,
+    pub observation: Option<CvdObservation>,
+    pub envelope: Option<EncryptedEnvelope>,
+    #[serde(default)]
+    pub attachments: Vec<Attachment>,
+    pub category: Option<RecordCategory>,
+}
+
+#[derive(Serialize, Deserialize, PartialEq, Debug, Clone)]
+#[serde(rename_all = "snake_case")]
+pub struct RecordResp {
+    pub title: String,
+    pub timestamp: Timestamp,
+    pub description: String,
+    pub data: String,
+    pub observation: Option<CvdObservation>,
+    pub envelope: Option<EncryptedEnvelope>,
+    pub attachments: Vec<Attachment>,
+    pub category: Option<RecordCategory>,
+    pub version: u32,
+    pub author: Addr,
+    pub reason: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, PartialEq, Debug, Clone)]
+#[serde(rename_all = "snake_case")]
+pub struct VerifyAttachmentResp {
+    pub matches: bool,
+    pub attachment: Attachment,
+}
+
+#[derive(Serialize, Deserialize, PartialEq, Debug, Clone)]
+#[serde(rename_all = "snake_case")]
+pub struct RecordHistoryResp {
+    pub versions: Vec<RecordResp>,
+}
+
+#[derive(Serialize, Deserialize, PartialEq, Debug, Clone)]
+#[serde(rename_all = "snake_case")]
+pub struct RecordSummary {
+    pub id: String,
+    pub title: String,
+    pub timestamp: Timestamp,
+    pub category: Option<RecordCategory>,
+}
+
+#[derive(Serialize, Deserialize, PartialEq, Debug, Clone)]
+#[serde(rename_all = "snake_case")]
+pub struct ListRecordsResp {
+    pub records: Vec<RecordSummary>,
+    pub next: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, PartialEq, Debug, Clone)]
+#[serde(rename_all = "snake_case")]
+pub struct ListByTimeRangeResp {
+    pub records: Vec<RecordSummary>,
+    pub next: Option<u32>,
+}
+
+#[derive(Serialize, Deserialize, JsonSchema, PartialEq, Eq, Debug, Clone, Copy)]
+#[serde(rename_all = "snake_case")]
+pub enum RecordCategory {
+    Lab,
+    Vitals,
+    Ecg,
+    Imaging,
+    Medication,
+    Diagnosis,
+    Prediction,
+}
+
+#[derive(Serialize, Deserialize, PartialEq, Eq, Debug, Clone, Copy)]
+#[serde(rename_all = "snake_case")]
+pub enum AccessAction {
+    Add,
+    View,
+    Amend,
+    Grant,
+    Revoke,
+    Erase,
+    BreakGlass,
+    Dispute,
+}
+
+#[derive(Serialize, Deserialize, PartialEq, Debug, Clone)]
+#[serde(rename_all = "snake_case")]
+pub struct AccessEvent {
+    pub actor: Addr,
+    pub action: AccessAction,
+    pub record_id: Option<String>,
+    pub grantee: Option<Addr>,
+    pub time: Timestamp,
+    pub height: u64,
+}
+
+#[derive(Serialize, Deserialize, PartialEq, Debug, Clone)]
+#[serde(rename_all = "snake_case")]
+pub struct AccessLogResp {
+    pub entries: Vec<AccessEvent>,
+    pub total: u32,
+}
+
+#[derive(Serialize, Deserialize, PartialEq, Debug, Clone)]
+#[serde(rename_all = "snake_case")]
+pub struct Dispute {
+    pub reason: String,
+    pub time: Timestamp,
+}
+
+#[derive(Serialize, Deserialize, PartialEq, Debug, Clone)]
+#[serde(rename_all = "snake_case")]
+pub struct EmergencyAccess {
+    pub provider: Addr,
+    pub justification: String,
+    pub time: Timestamp,
+    pub height: u64,
+    pub expires: Timestamp,
+    pub dispute: Option<Dispute>,
+}
+
+#[derive(Serialize, Deserialize, PartialEq, Debug, Clone)]
+#[serde(rename_all = "snake_case")]
+pub struct EmergencyAccessLogResp {
+    pub entries: Vec<EmergencyAccess>,
+    pub total: u32,
+}
+
+#[derive(Serialize, Deserialize, JsonSchema, PartialEq, Eq, Debug, Clone)]
+#[serde(rename_all = "snake_case")]
+pub enum ConsentPurpose {
+    ModelTraining,
+    Study { study_id: String },
+    CommercialUse,
+}
+
+#[derive(Serialize, Deserialize, PartialEq, Debug, Clone)]
+#[serde(rename_all = "snake_case")]
+pub struct Consent {
+    pub purpose: ConsentPurpose,
+    pub granted_at: Timestamp,
+    pub expires: Timestamp,
+}
+
+#[derive(Serialize, Deserialize, PartialEq, Debug, Clone)]
+#[serde(rename_all = "snake_case")]
+pub struct ConsentsResp {
+    pub consents: Vec<Consent>,
+}
+
+#[derive(Serialize, Deserialize, PartialEq, Debug, Clone)]
+#[serde(rename_all = "snake_case")]
+pub struct CheckConsentResp {
+    pub active: bool,
+    pub expires: Option<Timestamp>,
+}
+
+#[derive(Serialize, Deserialize, PartialEq, Debug, Clone)]
+#[serde(rename_all = "snake_case")]
+pub struct ResearchObservation {
+    pub period: Timestamp,
+    pub observation: CvdObservation,
+}
+
+#[derive(Serialize, Deserialize, PartialEq, Debug, Clone)]
+#[serde(rename_all = "snake_case")]
+pub struct ResearchDataResp {
+    pub observations: Vec<ResearchObservation>,
+}
+
+/// Observation of a patient known only by a pseudonym that is stable within
+/// one purpose and unlinkable across purposes.
+#[derive(Serialize, Deserialize, PartialEq, Debug, Clone)]
+#[serde(rename_all = "snake_case")]
+pub struct ResearchRow {
+    pub pseudonym: String,
+    pub period: Timestamp,
+    pub observation: CvdObservation,
+}
+
+#[derive(Serialize, Deserialize, PartialEq, Debug, Clone)]
+#[serde(rename_all = "snake_case")]
+pub struct ResearchExportResp {
+    pub rows: Vec<ResearchRow>,
+    /// Value of `start` for the next page, if any patients are left
+    pub next: Option<u32>,
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, JsonSchema)]
 #[serde(rename_all = "snake_case")]
 pub enum RecordPermissions {
     ViewById { record_id: String },
+    ViewAll,
     Add,
+    Amend { record_id: String },
+    ViewAccessLog,
+    ViewConsents,
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, JsonSchema)]
 #[serde(rename_all = "snake_case")]
 pub enum QueryWithPermit {
-    ViewById { record_id: String },
+    ViewById {
+        record_id: String,
+    },
+    ListRecords {
+        start_after: Option<String>,
+        limit: Option<u32>,
+    },
+    ViewHistory {
+        record_id: String,
+    },
+    ListByCategory {
+        category: RecordCategory,
+        start_after: Option<String>,
+        limit: Option<u32>,
+    },
+    /// Records whose latest version was written in `[from, to)`.
+    ListByTimeRange {
+        from: Timestamp,
+        to: Timestamp,
+        start_after: Option<u32>,
+        limit: Option<u32>,
+    },
+    VerifyAttachment {
+        record_id: String,
+        uri: String,
+        sha256: Binary,
+    },
+    PredictRisk {
+        record_id: String,
+    },
+    AccessLog {
+        start: Option<u32>,
+        limit: Option<u32>,
+    },
+    /// Emergency unlocks of the patient's records, for the patient.
+    EmergencyAccessLog {
+        start: Option<u32>,
+        limit: Option<u32>,
+    },
+    Consents {},
     Add,
 }
@@ -0,0 +1,73 @@
+//! Mirror of the record-manager observation types, used to relay structured
+//! observations to the patient record managers and to score them.
+
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+#[derive(Serialize, Deserialize, JsonSchema, PartialEq, Eq, Debug, Clone, Copy)]
+#[serde(rename_all = "snake_case")]
+pub enum Sex {
+    Female,
+    Male,
+}
+
+#[derive(Serialize, Deserialize, JsonSchema, PartialEq, Eq, Debug, Clone, Copy)]
+#[serde(rename_all = "snake_case")]
+pub enum ChestPainType {
+    TypicalAngina,
+    AtypicalAngina,
+    NonAnginalPain,
+    Asymptomatic,
+}
+
+#[derive(Serialize, Deserialize, JsonSchema, PartialEq, Eq, Debug, Clone, Copy)]
+#[serde(rename_all = "snake_case")]
+pub enum RestEcg {
+    Normal,
+    StTWaveAbnormality,
+    LeftVentricularHypertrophy,
+}
+
+#[derive(Serialize, Deserialize, JsonSchema, PartialEq, Eq, Debug, Clone, Copy)]
+#[serde(rename_all = "snake_case")]
+pub enum StSlope {
+    Upsloping,
+    Flat,
+    Downsloping,
+}
+
+#[derive(Serialize, Deserialize, JsonSchema, PartialEq, Eq, Debug, Clone, Copy)]
+#[serde(rename_all = "snake_case")]
+pub enum Thal {
+    Normal,
+    FixedDefect,
+    ReversibleDefect,
+}
+
+/// Structured heart-disease observation following the features of the UCI
+/// Cleveland dataset. Contracts cannot use floating point, so `oldpeak` is kept
+/// in tenths of a millimetre.
+#[derive(Serialize, Deserialize, JsonSchema, PartialEq, Eq, Debug, Clone)]
+#[serde(rename_all = "snake_case")]
+pub struct CvdObservation {
+    /// Age in years
+    pub age: u8,
+    pub sex: Sex,
+    pub chest_pain_type: ChestPainType,
+    /// Resting blood pressure in mm Hg
+    pub resting_bp: u16,
+    /// Serum cholesterol in mg/dl
+    pub cholesterol: u16,
+    /// Fasting blood sugar above 120 mg/dl
+    pub fasting_blood_sugar: bool,
+    pub rest_ecg: RestEcg,
+    /// Maximum heart rate achieved in beats per minute
+    pub max_heart_rate: u16,
+    pub exercise_angina: bool,
+    /// ST depression induced by exercise relative to rest, in tenths of a mm
+    pub oldpeak: i16,
+    pub slope: StSlope,
+    /// Number of major vessels coloured by fluoroscopy
+    pub ca: u8,
+    pub thal: Thal,
+}
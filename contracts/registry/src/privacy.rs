@@ -0,0 +1,274 @@
+use cosmwasm_std::{Binary, Decimal};
+use schemars::JsonSchema;
+use secret_toolkit::crypto::sha_256;
+use serde::{Deserialize, Serialize};
+
+use crate::error::ContractError;
+use crate::observation::CvdObservation;
+use crate::risk::{Feature, SCALE};
+
+/// Internal precision of the noise, equal to the atomics scale of `Decimal`.
+const PRECISION: i128 = 1_000_000_000_000_000_000;
+const LN_2: i128 = 693_147_180_559_945_309;
+
+/// Half-open range `[min, max)` of a feature, scaled by `SCALE`.
+#[derive(Serialize, Deserialize, JsonSchema, PartialEq, Eq, Debug, Clone)]
+#[serde(rename_all = "snake_case")]
+pub struct FeatureRange {
+    pub feature: Feature,
+    pub min: i64,
+    pub max: i64,
+}
+
+impl FeatureRange {
+    pub fn contains(&self, observation: &CvdObservation) -> bool {
+        let value = self.feature.value(observation);
+        self.min as i128 <= value && value < self.max as i128
+    }
+}
+
+/// Statistic over one observation per patient. Values are scaled by `SCALE`.
+#[derive(Serialize, Deserialize, JsonSchema, PartialEq, Eq, Debug, Clone)]
+#[serde(rename_all = "snake_case")]
+pub enum Statistic {
+    /// Number of patients
+    Count {},
+    /// Sum of `feature` with each patient's value clamped to `[lower, upper]`
+    Sum {
+        feature: Feature,
+        lower: i64,
+        upper: i64,
+    },
+    /// Number of patients in each bin `[edges[i], edges[i + 1])`
+    Histogram { feature: Feature, edges: Vec<i64> },
+}
+
+impl Statistic {
+    pub fn validate(&self) -> Result<(), ContractError> {
+        match self {
+            Statistic::Count {} => Ok(()),
+            Statistic::Sum { lower, upper, .. } if lower > upper => {
+                Err(ContractError::InvalidStatistic {
+                    reason: "lower bound is above the upper bound".to_string(),
+                })
+            }
+            Statistic::Sum { .. } => Ok(()),
+            Statistic::Histogram { edges, .. } if edges.len() < 2 => {
+                Err(ContractError::InvalidStatistic {
+                    reason: "a histogram needs at least two edges".to_string(),
+                })
+            }
+            Statistic::Histogram { edges, .. } if edges.windows(2).any(|w| w[0] >= w[1]) => {
+                Err(ContractError::InvalidStatistic {
+                    reason: "histogram edges must be strictly increasing".to_string(),
+                })
+            }
+            Statistic::Histogram { .. } => Ok(()),
+        }
+    }
+
+    /// Largest change in the L1 norm of the result from adding or removing one
+    /// patient.
+    pub fn sensitivity(&self) -> i128 {
+        match self {
+            Statistic::Count {} | Statistic::Histogram { .. } => SCALE,
+            Statistic::Sum { lower, upper, .. } => {
+                (*lower as i128).abs().max((*upper as i128).abs())
+            }
+        }
+    }
+
+    /// Exact value of the statistic over `observations`.
+    pub fn evaluate(&self, observations: &[CvdObservation]) -> Vec<i128> {
+        match self {
+            Statistic::Count {} => vec![observations.len() as i128 * SCALE],
+            Statistic::Sum {
+                feature,
+                lower,
+                upper,
+            } => vec![observations
+                .iter()
+                .map(|observation| {
+                    feature
+                        .value(observation)
+                        .clamp(*lower as i128, *upper as i128)
+                })
+                .sum()],
+            Statistic::Histogram { feature, edges } => edges
+                .windows(2)
+                .map(|bin| {
+                    let range = FeatureRange {
+                        feature: *feature,
+                        min: bin[0],
+                        max: bin[1],
+                    };
+                    observations
+                        .iter()
+                        .filter(|observation| range.contains(observation))
+                        .count() as i128
+                        * SCALE
+                })
+                .collect(),
+        }
+    }
+}
+
+/// Adds independent Laplace noise of scale `sensitivity / epsilon` to every
+/// value. The noise is derived from `random` with integer arithmetic only, so
+/// every node computes the same result.
+pub fn add_laplace_noise(
+    values: Vec<i128>,
+    sensitivity: i128,
+    epsilon: Decimal,
+    random: &Binary,
+) -> Vec<i64> {
+    let scale = sensitivity.saturating_mul(PRECISION) / epsilon.atomics().u128() as i128;
+
+    values
+        .into_iter()
+        .enumerate()
+        .map(|(i, value)| {
+            let seed = sha_256(&[random.as_slice(), &(i as u64).to_be_bytes()].concat());
+            let bits = u64::from_be_bytes(seed[..8].try_into().unwrap());
+            // uniform in (0, 1]
+            let u = ((bits as i128 + 1) * PRECISION) >> 64;
+
+            let noise = scale.saturating_mul(neg_ln(u)) / PRECISION;
+            let noisy = if seed[8] & 1 == 0 {
+                value.saturating_add(noise)
+            } else {
+                value.saturating_sub(noise)
+            };
+            noisy.clamp(i64::MIN as i128, i64::MAX as i128) as i64
+        })
+        .collect()
+}
+
+/// `-ln(x)` for `0 < x <= 1`, both scaled by `PRECISION`.
+fn neg_ln(x: i128) -> i128 {
+    // x = m * 2^-k with 1/2 <= m <= 1
+    let mut m = x;
+    let mut k = 0;
+    while m < PRECISION / 2 {
+        m *= 2;
+        k += 1;
+    }
+
+    // ln(m) = 2 * atanh(s) with s = (m - 1) / (m + 1) in [-1/3, 0]
+    let s = (m - PRECISION) * PRECISION / (m + PRECISION);
+    let s2 = s * s / PRECISION;
+    let mut term = s;
+    let mut sum = 0;
+    let mut n = 1;
+    while term != 0 {
+        sum += term / n;
+        term = term * s2 / PRECISION;
+        n += 2;
+    }
+
+    k * LN_2 - 2 * sum
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::observation::{ChestPainType, RestEcg, Sex, StSlope, Thal};
+
+    fn observation(age: u8, cholesterol: u16) -> CvdObservation {
+        CvdObservation {
+            age,
+            sex: Sex::Male,
+            chest_pain_type: ChestPainType::TypicalAngina,
+            resting_bp: 145,
+            cholesterol,
+            fasting_blood_sugar: true,
+            rest_ecg: RestEcg::LeftVentricularHypertrophy,
+            max_heart_rate: 150,
+            exercise_angina: false,
+            oldpeak: 23,
+            slope: StSlope::Downsloping,
+            ca: 0,
+            thal: Thal::FixedDefect,
+        }
+    }
+
+    #[test]
+    fn neg_ln_values() {
+        assert_eq!(neg_ln(PRECISION), 0);
+        assert!((neg_ln(PRECISION / 2) - LN_2).abs() < 1_000);
+        // -ln(0.1) = 2.302585092994045684
+        assert!((neg_ln(PRECISION / 10) - 2_302_585_092_994_045_684).abs() < 1_000);
+        // -ln(1e-18) = 41.446531673892822312
+        assert!((neg_ln(1) - 41_446_531_673_892_822_312).abs() < 1_000);
+    }
+
+    #[test]
+    fn evaluate_statistics() {
+        let observations = vec![
+            observation(45, 180),
+            observation(63, 233),
+            observation(70, 320),
+        ];
+
+        assert_eq!(Statistic::Count {}.evaluate(&observations), vec![3 * SCALE]);
+
+        let sum = Statistic::Sum {
+            feature: Feature::Cholesterol,
+            lower: 0,
+            upper: 300 * SCALE as i64,
+        };
+        assert_eq!(sum.evaluate(&observations), vec![713 * SCALE]);
+        assert_eq!(sum.sensitivity(), 300 * SCALE);
+
+        let histogram = Statistic::Histogram {
+            feature: Feature::Age,
+            edges: vec![40 * SCALE as i64, 60 * SCALE as i64, 70 * SCALE as i64],
+        };
+        assert_eq!(histogram.evaluate(&observations), vec![SCALE, SCALE]);
+        assert_eq!(histogram.sensitivity(), SCALE);
+
+        let histogram = Statistic::Histogram {
+            feature: Feature::Age,
+            edges: vec![60 * SCALE as i64, 40 * SCALE as i64],
+        };
+        assert!(histogram.validate().is_err());
+    }
+
+    #[test]
+    fn laplace_noise() {
+        let random = Binary::from([7u8; 32]);
+        let values = vec![100 * SCALE; 200];
+
+        let noisy = add_laplace_noise(values.clone(), SCALE, Decimal::one(), &random);
+        assert_eq!(
+            noisy,
+            add_laplace_noise(values.clone(), SCALE, Decimal::one(), &random)
+        );
+        assert_ne!(
+            noisy,
+            add_laplace_noise(
+                values.clone(),
+                SCALE,
+                Decimal::one(),
+                &Binary::from([8u8; 32])
+            )
+        );
+
+        // Laplace(1) has mean absolute deviation 1
+        let deviation = noisy
+            .iter()
+            .map(|value| (*value as i128 - 100 * SCALE).abs())
+            .sum::<i128>()
+            / noisy.len() as i128;
+        assert!(SCALE / 2 < deviation && deviation < 2 * SCALE);
+
+        // a smaller epsilon adds more noise
+        let noisier = add_laplace_noise(values, SCALE, Decimal::percent(10), &random);
+        let deviation_noisier = noisier
+            .iter()
+            .map(|value| (*value as i128 - 100 * SCALE).abs())
+            .sum::<i128>()
+            / noisier.len() as i128;
+        assert!(deviation_noisier > 5 * deviation);
+    }
+}
@@ -0,0 +1,213 @@
+use cosmwasm_std::Decimal;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+use crate::error::ContractError;
+use crate::observation::{ChestPainType, CvdObservation, RestEcg, Sex, StSlope, Thal};
+
+/// Fixed-point scale of model weights, intercept and feature values.
+pub const SCALE: i128 = 1_000_000;
+
+/// Internal precision used while evaluating the sigmoid.
+const PRECISION: i128 = 1_000_000_000_000_000_000;
+const LN_2: i128 = 693_147_180_559_945_309;
+
+/// Beyond this `|z|` the probability rounds to 0 or 1 at `SCALE` resolution.
+const MAX_EXPONENT: i128 = 40 * SCALE;
+
+/// Model input features. Categorical features use the integer codes of the UCI
+/// heart disease dataset so that models trained on it can be loaded unchanged.
+#[derive(Serialize, Deserialize, JsonSchema, PartialEq, Eq, Debug, Clone, Copy)]
+#[serde(rename_all = "snake_case")]
+pub enum Feature {
+    Age,
+    Sex,
+    ChestPainType,
+    RestingBp,
+    Cholesterol,
+    FastingBloodSugar,
+    RestEcg,
+    MaxHeartRate,
+    ExerciseAngina,
+    Oldpeak,
+    Slope,
+    Ca,
+    Thal,
+}
+
+impl Feature {
+    /// Value of the feature for `observation`, scaled by `SCALE`.
+    pub fn value(&self, observation: &CvdObservation) -> i128 {
+        let value = match self {
+            Feature::Age => observation.age as i128,
+            Feature::Sex => match observation.sex {
+                Sex::Female => 0,
+                Sex::Male => 1,
+            },
+            Feature::ChestPainType => match observation.chest_pain_type {
+                ChestPainType::TypicalAngina => 1,
+                ChestPainType::AtypicalAngina => 2,
+                ChestPainType::NonAnginalPain => 3,
+                ChestPainType::Asymptomatic => 4,
+            },
+            Feature::RestingBp => observation.resting_bp as i128,
+            Feature::Cholesterol => observation.cholesterol as i128,
+            Feature::FastingBloodSugar => observation.fasting_blood_sugar as i128,
+            Feature::RestEcg => match observation.rest_ecg {
+                RestEcg::Normal => 0,
+                RestEcg::StTWaveAbnormality => 1,
+                RestEcg::LeftVentricularHypertrophy => 2,
+            },
+            Feature::MaxHeartRate => observation.max_heart_rate as i128,
+            Feature::ExerciseAngina => observation.exercise_angina as i128,
+            // stored in tenths of a millimetre
+            Feature::Oldpeak => return observation.oldpeak as i128 * SCALE / 10,
+            Feature::Slope => match observation.slope {
+                StSlope::Upsloping => 1,
+                StSlope::Flat => 2,
+                StSlope::Downsloping => 3,
+            },
+            Feature::Ca => observation.ca as i128,
+            Feature::Thal => match observation.thal {
+                Thal::Normal => 3,
+                Thal::FixedDefect => 6,
+                Thal::ReversibleDefect => 7,
+            },
+        };
+        value * SCALE
+    }
+}
+
+/// Logistic-regression model with weights and intercept scaled by `SCALE`.
+/// `weights[i]` applies to `features[i]`.
+#[derive(Serialize, Deserialize, JsonSchema, PartialEq, Eq, Debug, Clone)]
+#[serde(rename_all = "snake_case")]
+pub struct RiskModel {
+    pub features: Vec<Feature>,
+    pub weights: Vec<i64>,
+    pub intercept: i64,
+}
+
+impl RiskModel {
+    pub fn validate(&self) -> Result<(), ContractError> {
+        if self.features.is_empty() || self.features.len() != self.weights.len() {
+            return Err(ContractError::InvalidRiskModel {
+                features: self.features.len(),
+                weights: self.weights.len(),
+            });
+        }
+        Ok(())
+    }
+
+    /// Probability of CVD for `observation`. Only integer arithmetic is used, so
+    /// the result is identical on every node.
+    pub fn predict(&self, observation: &CvdObservation) -> Decimal {
+        let z = self
+            .features
+            .iter()
+            .zip(&self.weights)
+            .fold(self.intercept as i128, |z, (feature, weight)| {
+                z + *weight as i128 * feature.value(observation) / SCALE
+            });
+
+        Decimal::from_ratio(sigmoid(z) as u128, SCALE as u128)
+    }
+}
+
+/// `1 / (1 + e^-z)` for `z` scaled by `SCALE`, rounded to `SCALE`.
+fn sigmoid(z: i128) -> i128 {
+    if z >= MAX_EXPONENT {
+        return SCALE;
+    }
+    if z <= -MAX_EXPONENT {
+        return 0;
+    }
+
+    let e = exp_neg(z.abs() * (PRECISION / SCALE));
+    let p = if z >= 0 {
+        PRECISION * PRECISION / (PRECISION + e)
+    } else {
+        e * PRECISION / (PRECISION + e)
+    };
+
+    (p * SCALE + PRECISION / 2) / PRECISION
+}
+
+/// `e^-x` for `0 <= x`, both scaled by `PRECISION`.
+fn exp_neg(x: i128) -> i128 {
+    // e^-x = 2^-k * e^-r with x = k * ln(2) + r and 0 <= r < ln(2)
+    let k = x / LN_2;
+    let r = x - k * LN_2;
+
+    let mut term = PRECISION;
+    let mut sum = PRECISION;
+    let mut n = 1;
+    while term != 0 {
+        term = -term * r / PRECISION / n;
+        sum += term;
+        n += 1;
+    }
+
+    sum >> k
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sigmoid_values() {
+        assert_eq!(sigmoid(0), 500_000);
+        assert_eq!(sigmoid(SCALE), 731_059);
+        assert_eq!(sigmoid(-SCALE), 268_941);
+        assert_eq!(sigmoid(5 * SCALE), 993_307);
+        assert_eq!(sigmoid(-10 * SCALE), 45);
+        assert_eq!(sigmoid(MAX_EXPONENT), SCALE);
+        assert_eq!(sigmoid(-MAX_EXPONENT), 0);
+    }
+
+    #[test]
+    fn predict_observation() {
+        let observation = CvdObservation {
+            age: 63,
+            sex: Sex::Male,
+            chest_pain_type: ChestPainType::TypicalAngina,
+            resting_bp: 145,
+            cholesterol: 233,
+            fasting_blood_sugar: true,
+            rest_ecg: RestEcg::LeftVentricularHypertrophy,
+            max_heart_rate: 150,
+            exercise_angina: false,
+            oldpeak: 23,
+            slope: StSlope::Downsloping,
+            ca: 0,
+            thal: Thal::FixedDefect,
+        };
+
+        assert_eq!(Feature::Oldpeak.value(&observation), 2_300_000);
+        assert_eq!(Feature::Thal.value(&observation), 6_000_000);
+
+        let model = RiskModel {
+            features: vec![Feature::Age, Feature::Cholesterol],
+            weights: vec![50_000, 10_000],
+            intercept: -5_000_000,
+        };
+        assert_eq!(model.validate(), Ok(()));
+        assert_eq!(
+            model.predict(&observation),
+            Decimal::from_ratio(617_748u128, 1_000_000u128)
+        );
+
+        let model = RiskModel {
+            weights: vec![50_000],
+            ..model
+        };
+        assert_eq!(
+            model.validate(),
+            Err(ContractError::InvalidRiskModel {
+                features: 2,
+                weights: 1
+            })
+        );
+    }
+}
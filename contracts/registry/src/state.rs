@@ -1,13 +1,38 @@
-use cosmwasm_std::Addr;
-use secret_toolkit::storage::{Item, Keymap};
+use cosmwasm_std::{Addr, Binary, Decimal, Timestamp};
+use schemars::JsonSchema;
+use secret_toolkit::storage::{AppendStore, Item, Keymap};
 use serde::{Deserialize, Serialize};
 
+use crate::risk::RiskModel;
+
 #[derive(Serialize, Deserialize, PartialEq, Debug, Clone)]
 #[serde(rename_all = "snake_case")]
 pub struct Person {
     pub address: Addr,
     pub contract_address: Addr,
     pub pubkey: String,
+    /// Code hash of the record manager at `contract_address`
+    pub code_hash: String,
+    /// Set for record managers instantiated by 0.1.0, which have no admin and
+    /// so cannot be migrated by the registry
+    #[serde(default)]
+    pub legacy: bool,
+}
+
+/// Person layout of 0.1.0.
+#[derive(Serialize, Deserialize, PartialEq, Debug, Clone)]
+#[serde(rename_all = "snake_case")]
+pub struct LegacyPerson {
+    pub address: Addr,
+    pub contract_address: Addr,
+    pub pubkey: String,
+}
+
+#[derive(Serialize, Deserialize, PartialEq, Debug, Clone)]
+#[serde(rename_all = "snake_case")]
+pub struct ContractInfo {
+    pub name: String,
+    pub version: String,
 }
 
 #[derive(Serialize, Deserialize, PartialEq, Debug, Clone)]
@@ -17,6 +42,134 @@ pub struct OffspringInfo {
     pub code_hash: String,
 }
 
+#[derive(Serialize, Deserialize, JsonSchema, PartialEq, Eq, Debug, Clone, Default)]
+#[serde(rename_all = "snake_case")]
+pub struct ProviderRoles {
+    pub can_register: bool,
+    pub can_write_records: bool,
+    pub can_read_with_consent: bool,
+    /// Approved research account that may export de-identified data
+    #[serde(default)]
+    pub can_research: bool,
+    /// Emergency provider that may unlock a patient's records without consent
+    #[serde(default)]
+    pub can_break_glass: bool,
+}
+
+#[derive(Serialize, Deserialize, PartialEq, Eq, Debug, Clone, Copy)]
+#[serde(rename_all = "snake_case")]
+pub enum ProviderRole {
+    Register,
+    WriteRecords,
+    ReadWithConsent,
+    Research,
+    BreakGlass,
+}
+
+impl ProviderRoles {
+    pub fn has(&self, role: ProviderRole) -> bool {
+        match role {
+            ProviderRole::Register => self.can_register,
+            ProviderRole::WriteRecords => self.can_write_records,
+            ProviderRole::ReadWithConsent => self.can_read_with_consent,
+            ProviderRole::Research => self.can_research,
+            ProviderRole::BreakGlass => self.can_break_glass,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, PartialEq, Debug, Clone)]
+#[serde(rename_all = "snake_case")]
+pub struct Provider {
+    pub name: String,
+    pub roles: ProviderRoles,
+}
+
+#[derive(Serialize, Deserialize, JsonSchema, PartialEq, Eq, Debug, Clone, Copy)]
+#[serde(rename_all = "snake_case")]
+pub enum ModelStatus {
+    Draft,
+    Active,
+    Retired,
+}
+
+#[derive(Serialize, Deserialize, JsonSchema, PartialEq, Eq, Debug, Clone)]
+#[serde(rename_all = "snake_case")]
+pub struct ModelMetric {
+    pub name: String,
+    pub value: Decimal,
+}
+
+#[derive(Serialize, Deserialize, PartialEq, Debug, Clone)]
+#[serde(rename_all = "snake_case")]
+pub struct ModelVersion {
+    pub version: String,
+    pub model: RiskModel,
+    /// Hash of the exported weights file the on-chain model was built from
+    pub weights_hash: String,
+    /// Hash of the dataset the model was trained on
+    pub dataset_hash: String,
+    pub metrics: Vec<ModelMetric>,
+    pub status: ModelStatus,
+    pub registered_at: Timestamp,
+    pub activated_at: Option<Timestamp>,
+    pub retired_at: Option<Timestamp>,
+}
+
+#[derive(Serialize, Deserialize, JsonSchema, PartialEq, Eq, Debug, Clone, Copy)]
+#[serde(rename_all = "snake_case")]
+pub enum ErasureRequester {
+    Patient,
+    Admin,
+}
+
+/// Proof that a patient was erased. Only a hash of the patient id keyed with
+/// the registry key is kept, so the receipt reveals nothing by itself.
+#[derive(Serialize, Deserialize, PartialEq, Debug, Clone)]
+#[serde(rename_all = "snake_case")]
+pub struct ErasureReceipt {
+    pub id_hash: Binary,
+    pub requested_by: ErasureRequester,
+    pub time: Timestamp,
+    pub height: u64,
+    /// Set when the record manager was instantiated by 0.1.0, which cannot
+    /// erase, so its records are still stored on-chain
+    #[serde(default)]
+    pub records_retained: bool,
+}
+
+/// Differential-privacy budget of a research account, as a total epsilon.
+#[derive(Serialize, Deserialize, JsonSchema, PartialEq, Eq, Debug, Clone, Default)]
+#[serde(rename_all = "snake_case")]
+pub struct PrivacyBudget {
+    pub total: Decimal,
+    pub spent: Decimal,
+}
+
+impl PrivacyBudget {
+    pub fn remaining(&self) -> Decimal {
+        self.total.saturating_sub(self.spent)
+    }
+}
+
+pub const PREFIX_REVOKED_PERMITS: &str = "revoked_permits";
+
+pub static CONTRACT_INFO: Item<ContractInfo> = Item::new(b"contract_info");
 pub static OWNER: Item<Addr> = Item::new(b"owner");
+/// Address the owner proposed to hand over to, until it accepts.
+pub static PENDING_OWNER: Item<Addr> = Item::new(b"pending_owner");
 pub static OFFSPRING: Item<OffspringInfo> = Item::new(b"offspring");
-pub static PERSON_STORE: Keymap<String, Person> = Keymap::new(b"person");
+/// Key the registry authenticates its queries to record managers with.
+pub static REGISTRY_KEY: Item<String> = Item::new(b"registry_key");
+pub static ACTIVE_MODEL: Item<String> = Item::new(b"active_model");
+pub const PERSON_STORE: Keymap<String, Person> = Keymap::new(b"person");
+/// `PERSON_STORE` as written by 0.1.0; only read while migrating.
+pub const LEGACY_PERSON_STORE: Keymap<String, LegacyPerson> = Keymap::new(b"person");
+/// Patient id registered for each patient address.
+pub const PATIENT_INDEX: Keymap<Addr, String> = Keymap::new(b"patient_index");
+/// Patient id owning each record-manager address.
+pub const CONTRACT_INDEX: Keymap<Addr, String> = Keymap::new(b"contract_index");
+pub const PROVIDER_STORE: Keymap<Addr, Provider> = Keymap::new(b"provider");
+pub const MODEL_STORE: Keymap<String, ModelVersion> = Keymap::new(b"model");
+pub const PRIVACY_BUDGETS: Keymap<Addr, PrivacyBudget> = Keymap::new(b"privacy_budget");
+pub const ERASURE_RECEIPTS: AppendStore<ErasureReceipt> = AppendStore::new(b"erasure_receipt");
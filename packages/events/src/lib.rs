@@ -0,0 +1,144 @@
+//! Audit event schema of the registry and the record managers, so that
+//! indexers see the same attributes and the same pseudonyms from both.
+
+use cosmwasm_std::{Addr, Binary, Event};
+use schemars::JsonSchema;
+use secret_toolkit::crypto::sha_256;
+use serde::{Deserialize, Serialize};
+
+/// Type of the event every execute emits. The chain reports it as
+/// `wasm-medical_records`.
+pub const EVENT_TYPE: &str = "medical_records";
+
+/// Role in which the sender acted.
+#[derive(Serialize, Deserialize, JsonSchema, PartialEq, Eq, Debug, Clone, Copy)]
+#[serde(rename_all = "snake_case")]
+pub enum ActorRole {
+    Patient,
+    Admin,
+    Provider,
+    /// Account reading under a grant or an emergency unlock
+    Reader,
+    Registry,
+    Other,
+}
+
+impl ActorRole {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ActorRole::Patient => "patient",
+            ActorRole::Admin => "admin",
+            ActorRole::Provider => "provider",
+            ActorRole::Reader => "reader",
+            ActorRole::Registry => "registry",
+            ActorRole::Other => "other",
+        }
+    }
+}
+
+/// State change as reported to indexers. Attributes are public, so patients
+/// only appear under a pseudonym and records only as a keyed hash of their id.
+/// `patient` and `record_id_hash` are left out when they do not apply.
+#[derive(PartialEq, Eq, Debug, Clone)]
+pub struct AuditEvent {
+    pub action: &'static str,
+    pub actor_role: ActorRole,
+    pub patient: Option<String>,
+    pub record_id_hash: Option<String>,
+}
+
+impl AuditEvent {
+    pub fn into_event(self, contract_version: &str) -> Event {
+        let mut event = Event::new(EVENT_TYPE)
+            .add_attribute_plaintext("action", self.action)
+            .add_attribute_plaintext("actor_role", self.actor_role.as_str());
+        if let Some(patient) = self.patient {
+            event = event.add_attribute_plaintext("patient", patient);
+        }
+        if let Some(record_id_hash) = self.record_id_hash {
+            event = event.add_attribute_plaintext("record_id_hash", record_id_hash);
+        }
+        event.add_attribute_plaintext("contract_version", contract_version)
+    }
+}
+
+/// Pseudonym of the patient owning `record_manager`. `event_key` is the hash
+/// of the registry key, known to the registry and its record managers only,
+/// so both report the same pseudonym and nobody else can link it to an
+/// address.
+pub fn patient_pseudonym(event_key: &[u8], record_manager: &Addr) -> String {
+    keyed_hash(event_key, b"patient", &[record_manager.as_bytes()])
+}
+
+pub fn record_id_hash(event_key: &[u8], record_manager: &Addr, record_id: &str) -> String {
+    keyed_hash(
+        event_key,
+        b"record",
+        &[record_manager.as_bytes(), record_id.as_bytes()],
+    )
+}
+
+fn keyed_hash(key: &[u8], domain: &[u8], parts: &[&[u8]]) -> String {
+    let mut input = [key, domain].concat();
+    for part in parts {
+        // length prefixes keep ("ab", "c") and ("a", "bc") apart
+        input.extend((part.len() as u32).to_be_bytes());
+        input.extend(*part);
+    }
+    Binary::from(sha_256(&input).as_slice()).to_base64()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn pseudonyms() {
+        let record_manager = Addr::unchecked("secret1rm");
+        let pseudonym = patient_pseudonym(b"key", &record_manager);
+
+        assert_eq!(pseudonym, patient_pseudonym(b"key", &record_manager));
+        assert_ne!(pseudonym, patient_pseudonym(b"other key", &record_manager));
+        assert_ne!(
+            pseudonym,
+            patient_pseudonym(b"key", &Addr::unchecked("secret2rm"))
+        );
+        assert!(!pseudonym.contains("secret1rm"));
+
+        assert_ne!(
+            record_id_hash(b"key", &record_manager, "1"),
+            record_id_hash(b"key", &Addr::unchecked("secret2rm"), "1")
+        );
+    }
+
+    #[test]
+    fn event_attributes() {
+        let event = AuditEvent {
+            action: "erase",
+            actor_role: ActorRole::Registry,
+            patient: Some("pseudonym".to_string()),
+            record_id_hash: None,
+        }
+        .into_event("0.2.0");
+
+        assert_eq!(event.ty, EVENT_TYPE);
+        let attributes: Vec<_> = event
+            .attributes
+            .iter()
+            .map(|attribute| (attribute.key.as_str(), attribute.value.as_str()))
+            .collect();
+        assert_eq!(
+            attributes,
+            vec![
+                ("action", "erase"),
+                ("actor_role", "registry"),
+                ("patient", "pseudonym"),
+                ("contract_version", "0.2.0"),
+            ]
+        );
+        assert!(event
+            .attributes
+            .iter()
+            .all(|attribute| !attribute.encrypted));
+    }
+}
@@ -0,0 +1,527 @@
+use std::collections::BTreeMap;
+
+use bech32::{ToBase32, Variant};
+use cosmwasm_std::testing::MockApi;
+use cosmwasm_std::{
+    from_binary, from_slice, to_binary, Addr, Binary, BlockInfo, ContractInfo, ContractResult,
+    CosmosMsg, Deps, DepsMut, Empty, Env, Event, MessageInfo, Querier, QuerierResult,
+    QuerierWrapper, QueryRequest, Reply, ReplyOn, Response, SubMsgResponse, SubMsgResult,
+    SystemError, SystemResult, Timestamp, WasmMsg, WasmQuery,
+};
+use secret_toolkit::crypto::sha_256;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+
+use crate::contracts::Contract;
+use crate::error::HarnessError;
+use crate::storage::ContractStorage;
+
+const CHAIN_ID: &str = "secret-4";
+const ADDRESS_HRP: &str = "secret";
+/// Seconds between two blocks
+const BLOCK_TIME: u64 = 6;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StoredCode {
+    pub code_id: u64,
+    pub code_hash: String,
+}
+
+/// Events and data of a message together with every submessage and reply it
+/// caused, in execution order.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct AppResponse {
+    pub events: Vec<Event>,
+    pub data: Option<Binary>,
+}
+
+impl AppResponse {
+    /// Events of type `ty`, e.g. `wasm-medical_records`.
+    pub fn events_of_type<'a>(&'a self, ty: &'a str) -> impl Iterator<Item = &'a Event> {
+        self.events.iter().filter(move |event| event.ty == ty)
+    }
+}
+
+struct Code {
+    contract: Box<dyn Contract>,
+    code_hash: String,
+}
+
+#[derive(Clone)]
+struct Instance {
+    code_id: u64,
+    admin: Option<Addr>,
+    storage: ContractStorage,
+}
+
+/// Chain with a single block producer, running every contract in process.
+///
+/// A message sent with `instantiate`, `execute` or `migrate` runs as one
+/// transaction: if it or any submessage without a reply on error fails, the
+/// storage of every contract is rolled back.
+pub struct App {
+    api: MockApi,
+    block: BlockInfo,
+    codes: Vec<Code>,
+    contracts: BTreeMap<Addr, Instance>,
+    instantiated: u64,
+    /// Contract whose storage is lent to a running entry point
+    executing: Option<Addr>,
+}
+
+impl Default for App {
+    fn default() -> Self {
+        let mut app = App {
+            api: MockApi::default(),
+            block: BlockInfo {
+                height: 1,
+                time: Timestamp::from_seconds(1_700_000_000),
+                chain_id: CHAIN_ID.to_string(),
+                random: None,
+            },
+            codes: vec![],
+            contracts: BTreeMap::new(),
+            instantiated: 0,
+            executing: None,
+        };
+        app.update_random();
+        app
+    }
+}
+
+impl App {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn block(&self) -> &BlockInfo {
+        &self.block
+    }
+
+    pub fn chain_id(&self) -> &str {
+        &self.block.chain_id
+    }
+
+    /// Moves `seconds` ahead, producing a block every `BLOCK_TIME` seconds.
+    pub fn advance_time(&mut self, seconds: u64) {
+        self.block.height += (seconds / BLOCK_TIME).max(1);
+        self.block.time = self.block.time.plus_seconds(seconds);
+        self.update_random();
+    }
+
+    pub fn store_code(&mut self, contract: impl Contract + 'static) -> StoredCode {
+        let code_id = self.codes.len() as u64 + 1;
+        let code_hash = sha_256(&[b"code".as_slice(), &code_id.to_be_bytes()].concat())
+            .iter()
+            .map(|byte| format!("{:02x}", byte))
+            .collect::<String>();
+
+        self.codes.push(Code {
+            contract: Box::new(contract),
+            code_hash: code_hash.clone(),
+        });
+        StoredCode { code_id, code_hash }
+    }
+
+    /// Code a contract currently runs.
+    pub fn code_id(&self, address: &Addr) -> Option<u64> {
+        self.contracts.get(address).map(|instance| instance.code_id)
+    }
+
+    pub fn instantiate<T: Serialize>(
+        &mut self,
+        code_id: u64,
+        sender: &Addr,
+        msg: &T,
+        admin: Option<&Addr>,
+    ) -> Result<(Addr, AppResponse), HarnessError> {
+        let msg = to_binary(msg)?;
+        self.transact(|app| {
+            app.instantiate_contract(sender.clone(), admin.cloned(), code_id, None, msg)
+        })
+    }
+
+    pub fn execute<T: Serialize>(
+        &mut self,
+        sender: &Addr,
+        contract: &Addr,
+        msg: &T,
+    ) -> Result<AppResponse, HarnessError> {
+        let msg = to_binary(msg)?;
+        self.transact(|app| app.execute_contract(sender.clone(), contract.clone(), None, msg))
+    }
+
+    pub fn migrate<T: Serialize>(
+        &mut self,
+        sender: &Addr,
+        contract: &Addr,
+        code_id: u64,
+        msg: &T,
+    ) -> Result<AppResponse, HarnessError> {
+        let msg = to_binary(msg)?;
+        self.transact(|app| {
+            app.migrate_contract(sender.clone(), contract.clone(), code_id, None, msg)
+        })
+    }
+
+    pub fn query<T: Serialize, R: DeserializeOwned>(
+        &self,
+        contract: &Addr,
+        msg: &T,
+    ) -> Result<R, HarnessError> {
+        let resp = self.query_contract(contract.as_str(), None, &to_binary(msg)?)?;
+        Ok(from_binary(&resp)?)
+    }
+
+    fn transact<R>(
+        &mut self,
+        f: impl FnOnce(&mut Self) -> Result<R, HarnessError>,
+    ) -> Result<R, HarnessError> {
+        let snapshot = self.contracts.clone();
+        let result = f(self);
+        if result.is_err() {
+            self.contracts = snapshot;
+        }
+        result
+    }
+
+    fn instantiate_contract(
+        &mut self,
+        sender: Addr,
+        admin: Option<Addr>,
+        code_id: u64,
+        code_hash: Option<&str>,
+        msg: Binary,
+    ) -> Result<(Addr, AppResponse), HarnessError> {
+        self.check_code_hash(code_id, code_hash)?;
+
+        let address = self.next_address(code_id);
+        self.contracts.insert(
+            address.clone(),
+            Instance {
+                code_id,
+                admin,
+                storage: ContractStorage::default(),
+            },
+        );
+
+        let info = MessageInfo {
+            sender,
+            funds: vec![],
+        };
+        let response = self.call(&address, |contract, deps, env| {
+            contract.instantiate(deps, env, info, &msg)
+        })?;
+        let response = self.process_response(&address, response)?;
+
+        Ok((address, response))
+    }
+
+    fn execute_contract(
+        &mut self,
+        sender: Addr,
+        address: Addr,
+        code_hash: Option<&str>,
+        msg: Binary,
+    ) -> Result<AppResponse, HarnessError> {
+        let code_id = self.instance(&address)?.code_id;
+        self.check_code_hash(code_id, code_hash)?;
+
+        let info = MessageInfo {
+            sender,
+            funds: vec![],
+        };
+        let response = self.call(&address, |contract, deps, env| {
+            contract.execute(deps, env, info, &msg)
+        })?;
+        self.process_response(&address, response)
+    }
+
+    fn migrate_contract(
+        &mut self,
+        sender: Addr,
+        address: Addr,
+        code_id: u64,
+        code_hash: Option<&str>,
+        msg: Binary,
+    ) -> Result<AppResponse, HarnessError> {
+        if self.instance(&address)?.admin.as_ref() != Some(&sender) {
+            return Err(HarnessError::Unauthorized { sender, address });
+        }
+        self.check_code_hash(code_id, code_hash)?;
+
+        self.contracts.get_mut(&address).unwrap().code_id = code_id;
+        let response = self.call(&address, |contract, deps, env| {
+            contract.migrate(deps, env, &msg)
+        })?;
+        self.process_response(&address, response)
+    }
+
+    /// Runs the submessages of `response` and the replies they ask for.
+    fn process_response(
+        &mut self,
+        address: &Addr,
+        response: Response,
+    ) -> Result<AppResponse, HarnessError> {
+        let mut events = contract_events(address, &response);
+        let mut data = response.data;
+
+        for submsg in response.messages {
+            let snapshot = self.contracts.clone();
+            let result = match self.dispatch(address.clone(), submsg.msg) {
+                Ok(sub) => {
+                    events.extend(sub.events.clone());
+                    if !matches!(submsg.reply_on, ReplyOn::Success | ReplyOn::Always) {
+                        continue;
+                    }
+                    SubMsgResult::Ok(SubMsgResponse {
+                        events: sub.events,
+                        data: sub.data,
+                    })
+                }
+                Err(err) => {
+                    self.contracts = snapshot;
+                    if !matches!(submsg.reply_on, ReplyOn::Error | ReplyOn::Always) {
+                        return Err(err);
+                    }
+                    SubMsgResult::Err(err.to_string())
+                }
+            };
+
+            let reply = Reply {
+                id: submsg.id,
+                result,
+            };
+            let response = self.call(address, |contract, deps, env| {
+                contract.reply(deps, env, reply)
+            })?;
+            let replied = self.process_response(address, response)?;
+            events.extend(replied.events);
+            if replied.data.is_some() {
+                data = replied.data;
+            }
+        }
+
+        Ok(AppResponse { events, data })
+    }
+
+    fn dispatch(&mut self, sender: Addr, msg: CosmosMsg) -> Result<AppResponse, HarnessError> {
+        match msg {
+            CosmosMsg::Wasm(WasmMsg::Execute {
+                contract_addr,
+                code_hash,
+                msg,
+                ..
+            }) => self.execute_contract(
+                sender,
+                Addr::unchecked(contract_addr),
+                Some(&code_hash),
+                msg,
+            ),
+            CosmosMsg::Wasm(WasmMsg::Instantiate {
+                admin,
+                code_id,
+                code_hash,
+                msg,
+                ..
+            }) => {
+                let admin = admin.map(Addr::unchecked);
+                self.instantiate_contract(sender, admin, code_id, Some(&code_hash), msg)
+                    .map(|(_, response)| response)
+            }
+            CosmosMsg::Wasm(WasmMsg::Migrate {
+                contract_addr,
+                code_hash,
+                code_id,
+                msg,
+            }) => self.migrate_contract(
+                sender,
+                Addr::unchecked(contract_addr),
+                code_id,
+                Some(&code_hash),
+                msg,
+            ),
+            msg => Err(HarnessError::Unsupported {
+                kind: format!("{:?}", msg),
+            }),
+        }
+    }
+
+    /// Runs an entry point of the contract at `address` on its own storage.
+    fn call<R>(
+        &mut self,
+        address: &Addr,
+        f: impl FnOnce(&dyn Contract, DepsMut, Env) -> Result<R, String>,
+    ) -> Result<R, HarnessError> {
+        let instance = self.contracts.get_mut(address).unwrap();
+        let code_id = instance.code_id;
+        let mut storage = std::mem::take(&mut instance.storage);
+
+        self.executing = Some(address.clone());
+        let result = {
+            let code = &self.codes[code_id as usize - 1];
+            let querier = AppQuerier { app: self };
+            let deps = DepsMut {
+                storage: &mut storage,
+                api: &self.api,
+                querier: QuerierWrapper::new(&querier),
+            };
+            f(
+                code.contract.as_ref(),
+                deps,
+                self.env(address, &code.code_hash),
+            )
+        };
+        self.executing = None;
+
+        self.contracts.get_mut(address).unwrap().storage = storage;
+        result.map_err(|error| HarnessError::Contract {
+            address: address.clone(),
+            error,
+        })
+    }
+
+    fn query_contract(
+        &self,
+        address: &str,
+        code_hash: Option<&str>,
+        msg: &Binary,
+    ) -> Result<Binary, HarnessError> {
+        let address = Addr::unchecked(address);
+        let instance = self.instance(&address)?;
+        if self.executing.as_ref() == Some(&address) {
+            return Err(HarnessError::Unsupported {
+                kind: "query of the executing contract".to_string(),
+            });
+        }
+        self.check_code_hash(instance.code_id, code_hash)?;
+
+        let code = &self.codes[instance.code_id as usize - 1];
+        let querier = AppQuerier { app: self };
+        let deps = Deps {
+            storage: &instance.storage,
+            api: &self.api,
+            querier: QuerierWrapper::new(&querier),
+        };
+        code.contract
+            .query(deps, self.env(&address, &code.code_hash), msg)
+            .map_err(|error| HarnessError::Contract { address, error })
+    }
+
+    fn instance(&self, address: &Addr) -> Result<&Instance, HarnessError> {
+        self.contracts
+            .get(address)
+            .ok_or_else(|| HarnessError::NonexistentContract {
+                address: address.to_string(),
+            })
+    }
+
+    /// Checks that `code_hash`, if the message carries one, is that of the code.
+    fn check_code_hash(&self, code_id: u64, code_hash: Option<&str>) -> Result<(), HarnessError> {
+        let code = match code_id
+            .checked_sub(1)
+            .and_then(|index| self.codes.get(index as usize))
+        {
+            Some(code) => code,
+            None => return Err(HarnessError::NonexistentCode { code_id }),
+        };
+
+        match code_hash {
+            Some(code_hash) if code_hash != code.code_hash => Err(HarnessError::CodeHashMismatch {
+                expected: code.code_hash.clone(),
+                found: code_hash.to_string(),
+            }),
+            _ => Ok(()),
+        }
+    }
+
+    fn env(&self, address: &Addr, code_hash: &str) -> Env {
+        Env {
+            block: self.block.clone(),
+            transaction: None,
+            contract: ContractInfo {
+                address: address.clone(),
+                code_hash: code_hash.to_string(),
+            },
+        }
+    }
+
+    fn next_address(&mut self, code_id: u64) -> Addr {
+        self.instantiated += 1;
+        let seed = [
+            b"contract".as_slice(),
+            &code_id.to_be_bytes(),
+            &self.instantiated.to_be_bytes(),
+        ]
+        .concat();
+        let address = bech32::encode(
+            ADDRESS_HRP,
+            (&sha_256(&seed)[..20]).to_base32(),
+            Variant::Bech32,
+        )
+        .unwrap();
+        Addr::unchecked(address)
+    }
+
+    fn update_random(&mut self) {
+        let random = sha_256(&self.block.height.to_be_bytes());
+        self.block.random = Some(Binary::from(random.as_slice()));
+    }
+}
+
+/// Events of a response as the chain reports them: attributes under `wasm`,
+/// custom events prefixed with `wasm-`, each tagged with the contract.
+fn contract_events(address: &Addr, response: &Response) -> Vec<Event> {
+    let mut events = vec![];
+    if !response.attributes.is_empty() {
+        events.push(
+            Event::new("wasm")
+                .add_attribute("_contract_address", address)
+                .add_attributes(response.attributes.clone()),
+        );
+    }
+    events.extend(response.events.iter().map(|event| {
+        Event::new(format!("wasm-{}", event.ty))
+            .add_attribute("_contract_address", address)
+            .add_attributes(event.attributes.clone())
+    }));
+    events
+}
+
+struct AppQuerier<'a> {
+    app: &'a App,
+}
+
+impl Querier for AppQuerier<'_> {
+    fn raw_query(&self, bin_request: &[u8]) -> QuerierResult {
+        let request: QueryRequest<Empty> = match from_slice(bin_request) {
+            Ok(request) => request,
+            Err(err) => {
+                return SystemResult::Err(SystemError::InvalidRequest {
+                    error: err.to_string(),
+                    request: bin_request.into(),
+                })
+            }
+        };
+
+        match request {
+            QueryRequest::Wasm(WasmQuery::Smart {
+                contract_addr,
+                code_hash,
+                msg,
+            }) => match self
+                .app
+                .query_contract(&contract_addr, Some(&code_hash), &msg)
+            {
+                Ok(resp) => SystemResult::Ok(ContractResult::Ok(resp)),
+                Err(HarnessError::NonexistentContract { address }) => {
+                    SystemResult::Err(SystemError::NoSuchContract { addr: address })
+                }
+                Err(err) => SystemResult::Ok(ContractResult::Err(err.to_string())),
+            },
+            request => SystemResult::Err(SystemError::UnsupportedRequest {
+                kind: format!("{:?}", request),
+            }),
+        }
+    }
+}
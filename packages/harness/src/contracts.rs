@@ -0,0 +1,111 @@
+use cosmwasm_std::{from_binary, Binary, Deps, DepsMut, Env, MessageInfo, Reply, Response};
+
+/// Entry points of a contract, taking their messages as JSON. Errors are
+/// reported as their display text, like the chain does.
+pub trait Contract {
+    fn instantiate(
+        &self,
+        deps: DepsMut,
+        env: Env,
+        info: MessageInfo,
+        msg: &Binary,
+    ) -> Result<Response, String>;
+
+    fn execute(
+        &self,
+        deps: DepsMut,
+        env: Env,
+        info: MessageInfo,
+        msg: &Binary,
+    ) -> Result<Response, String>;
+
+    fn query(&self, deps: Deps, env: Env, msg: &Binary) -> Result<Binary, String>;
+
+    fn migrate(&self, deps: DepsMut, env: Env, msg: &Binary) -> Result<Response, String>;
+
+    fn reply(&self, deps: DepsMut, env: Env, reply: Reply) -> Result<Response, String>;
+}
+
+pub struct RegistryContract;
+
+impl Contract for RegistryContract {
+    fn instantiate(
+        &self,
+        deps: DepsMut,
+        env: Env,
+        info: MessageInfo,
+        msg: &Binary,
+    ) -> Result<Response, String> {
+        let msg = from_binary(msg).map_err(|err| err.to_string())?;
+        registry::instantiate(deps, env, info, msg).map_err(|err| err.to_string())
+    }
+
+    fn execute(
+        &self,
+        deps: DepsMut,
+        env: Env,
+        info: MessageInfo,
+        msg: &Binary,
+    ) -> Result<Response, String> {
+        let msg = from_binary(msg).map_err(|err| err.to_string())?;
+        registry::execute(deps, env, info, msg).map_err(|err| err.to_string())
+    }
+
+    fn query(&self, deps: Deps, env: Env, msg: &Binary) -> Result<Binary, String> {
+        let msg = from_binary(msg).map_err(|err| err.to_string())?;
+        registry::query(deps, env, msg).map_err(|err| err.to_string())
+    }
+
+    fn migrate(&self, deps: DepsMut, env: Env, msg: &Binary) -> Result<Response, String> {
+        let msg = from_binary(msg).map_err(|err| err.to_string())?;
+        registry::migrate(deps, env, msg).map_err(|err| err.to_string())
+    }
+
+    fn reply(&self, deps: DepsMut, env: Env, reply: Reply) -> Result<Response, String> {
+        registry::reply(deps, env, reply).map_err(|err| err.to_string())
+    }
+}
+
+pub struct RecordManagerContract;
+
+impl Contract for RecordManagerContract {
+    fn instantiate(
+        &self,
+        deps: DepsMut,
+        env: Env,
+        info: MessageInfo,
+        msg: &Binary,
+    ) -> Result<Response, String> {
+        let msg = from_binary(msg).map_err(|err| err.to_string())?;
+        record_manager::instantiate(deps, env, info, msg).map_err(|err| err.to_string())
+    }
+
+    fn execute(
+        &self,
+        deps: DepsMut,
+        env: Env,
+        info: MessageInfo,
+        msg: &Binary,
+    ) -> Result<Response, String> {
+        let msg = from_binary(msg).map_err(|err| err.to_string())?;
+        record_manager::execute(deps, env, info, msg).map_err(|err| err.to_string())
+    }
+
+    fn query(&self, deps: Deps, env: Env, msg: &Binary) -> Result<Binary, String> {
+        let msg = from_binary(msg).map_err(|err| err.to_string())?;
+        record_manager::query(deps, env, msg).map_err(|err| err.to_string())
+    }
+
+    fn migrate(&self, deps: DepsMut, env: Env, msg: &Binary) -> Result<Response, String> {
+        let msg = from_binary(msg).map_err(|err| err.to_string())?;
+        record_manager::migrate(deps, env, msg).map_err(|err| err.to_string())
+    }
+
+    /// Record managers send no submessages, so they have no reply entry point.
+    fn reply(&self, _deps: DepsMut, _env: Env, reply: Reply) -> Result<Response, String> {
+        Err(format!(
+            "record-manager has no reply entry point (reply {})",
+            reply.id
+        ))
+    }
+}
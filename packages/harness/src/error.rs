@@ -0,0 +1,20 @@
+use cosmwasm_std::{Addr, StdError};
+use thiserror::Error;
+
+#[derive(Error, Debug, PartialEq)]
+pub enum HarnessError {
+    #[error("{0}")]
+    Std(#[from] StdError),
+    #[error("Contract {address} failed: {error}")]
+    Contract { address: Addr, error: String },
+    #[error("No contract at {address}")]
+    NonexistentContract { address: String },
+    #[error("No code with id {code_id} stored")]
+    NonexistentCode { code_id: u64 },
+    #[error("Code hash {found} does not match {expected}")]
+    CodeHashMismatch { expected: String, found: String },
+    #[error("{sender} is not the admin of {address}")]
+    Unauthorized { sender: Addr, address: Addr },
+    #[error("Unsupported message: {kind}")]
+    Unsupported { kind: String },
+}
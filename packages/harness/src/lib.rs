@@ -0,0 +1,20 @@
+//! In-process harness that runs the registry and record managers together.
+//!
+//! [`App`] keeps the storage of every contract it instantiated, dispatches the
+//! `WasmMsg::Instantiate`, `Execute` and `Migrate` submessages contracts return,
+//! calls their `reply` entry point and answers `WasmQuery::Smart` queries
+//! between them. [`Signer`], re-exported from the SDK, signs permits with real
+//! secp256k1 keys.
+
+pub mod app;
+pub mod contracts;
+pub mod error;
+mod storage;
+
+pub use app::{App, AppResponse, StoredCode};
+pub use contracts::{Contract, RecordManagerContract, RegistryContract};
+pub use error::HarnessError;
+pub use sdk::Signer;
+
+#[cfg(test)]
+mod tests;
@@ -0,0 +1,24 @@
+use std::collections::BTreeMap;
+
+use cosmwasm_std::Storage;
+
+/// Storage of one contract. Unlike `MemoryStorage` it can be cloned, which is
+/// how the app takes a snapshot to roll back a failed message.
+#[derive(Default, Clone, Debug)]
+pub struct ContractStorage {
+    data: BTreeMap<Vec<u8>, Vec<u8>>,
+}
+
+impl Storage for ContractStorage {
+    fn get(&self, key: &[u8]) -> Option<Vec<u8>> {
+        self.data.get(key).cloned()
+    }
+
+    fn set(&mut self, key: &[u8], value: &[u8]) {
+        self.data.insert(key.to_vec(), value.to_vec());
+    }
+
+    fn remove(&mut self, key: &[u8]) {
+        self.data.remove(key);
+    }
+}
@@ -0,0 +1,486 @@
+use cosmwasm_std::{from_binary, Addr, Binary, Deps, DepsMut, Env, MessageInfo, Reply, Response};
+use registry::msg::{
+    ErasureReceiptsResp, ExecuteMsg, InfoResp, InstantiateMsg, MigrateMsg, QueryMsg,
+    QueryWithPermit, Record, RecordPermissions, RecordResp,
+};
+use registry::state::{
+    LegacyPerson, OffspringInfo, ProviderRoles, LEGACY_PERSON_STORE, OFFSPRING, OWNER,
+};
+use secret_toolkit::crypto::sha_256;
+
+use crate::{
+    App, Contract, HarnessError, RecordManagerContract, RegistryContract, Signer, StoredCode,
+};
+
+struct Setup {
+    app: App,
+    registry: Addr,
+    offspring: StoredCode,
+    admin: Addr,
+}
+
+fn setup() -> Setup {
+    let mut app = App::new();
+    let admin = Addr::unchecked("admin");
+
+    let registry_code = app.store_code(RegistryContract);
+    let offspring = app.store_code(RecordManagerContract);
+
+    let (registry, _) = app
+        .instantiate(
+            registry_code.code_id,
+            &admin,
+            &InstantiateMsg {
+                owner: admin.clone(),
+                offspring_id: offspring.code_id,
+                offspring_hash: offspring.code_hash.clone(),
+            },
+            Some(&admin),
+        )
+        .unwrap();
+
+    Setup {
+        app,
+        registry,
+        offspring,
+        admin,
+    }
+}
+
+/// Registers `patient` and returns the address of their record manager.
+fn register(setup: &mut Setup, id: &str, patient: &Signer) -> Addr {
+    setup
+        .app
+        .execute(
+            &setup.admin,
+            &setup.registry,
+            &ExecuteMsg::Register {
+                id: id.to_string(),
+                address: patient.address(),
+                pubkey: patient.pubkey().to_base64(),
+                key: "password".to_string(),
+            },
+        )
+        .unwrap();
+
+    let resp: InfoResp = setup
+        .app
+        .query(
+            &setup.registry,
+            &QueryMsg::Info {
+                id: id.to_string(),
+                key: "password".to_string(),
+            },
+        )
+        .unwrap();
+    resp.contract_address
+}
+
+/// Derives the key from `seed`, so that tests get the same address on every
+/// run.
+fn signer(seed: &str) -> Signer {
+    Signer::from_secret_key(&sha_256(seed.as_bytes())).unwrap()
+}
+
+fn sample_record(title: &str) -> Record {
+    Record {
+        title: title.to_string(),
+        timestamp: None,
+        description: "Resting ECG".to_string(),
+        data: "sinus rhythm".to_string(),
+        observation: None,
+        envelope: None,
+        attachments: vec![],
+        category: None,
+    }
+}
+
+/// Registry as 0.1.0 left it: its instantiate message is the state to start
+/// from, which `migrate` then upgrades.
+struct LegacyRegistryContract;
+
+impl Contract for LegacyRegistryContract {
+    fn instantiate(
+        &self,
+        deps: DepsMut,
+        _env: Env,
+        info: MessageInfo,
+        msg: &Binary,
+    ) -> Result<Response, String> {
+        let (offspring, persons): (OffspringInfo, Vec<(String, LegacyPerson)>) =
+            from_binary(msg).map_err(|err| err.to_string())?;
+        OWNER
+            .save(deps.storage, &info.sender)
+            .map_err(|err| err.to_string())?;
+        OFFSPRING
+            .save(deps.storage, &offspring)
+            .map_err(|err| err.to_string())?;
+        let legacy_store = LEGACY_PERSON_STORE;
+        for (id, person) in persons {
+            legacy_store
+                .insert(deps.storage, &id, &person)
+                .map_err(|err| err.to_string())?;
+        }
+        Ok(Response::new())
+    }
+
+    fn execute(&self, _: DepsMut, _: Env, _: MessageInfo, _: &Binary) -> Result<Response, String> {
+        Err("unsupported".to_string())
+    }
+
+    fn query(&self, _: Deps, _: Env, _: &Binary) -> Result<Binary, String> {
+        Err("unsupported".to_string())
+    }
+
+    fn migrate(&self, _: DepsMut, _: Env, _: &Binary) -> Result<Response, String> {
+        Err("unsupported".to_string())
+    }
+
+    fn reply(&self, _: DepsMut, _: Env, _: Reply) -> Result<Response, String> {
+        Err("unsupported".to_string())
+    }
+}
+
+/// Record manager of 0.1.0, which knows none of the messages added since.
+struct LegacyRecordManagerContract;
+
+impl Contract for LegacyRecordManagerContract {
+    fn instantiate(
+        &self,
+        _: DepsMut,
+        _: Env,
+        _: MessageInfo,
+        _: &Binary,
+    ) -> Result<Response, String> {
+        Ok(Response::new())
+    }
+
+    fn execute(&self, _: DepsMut, _: Env, _: MessageInfo, _: &Binary) -> Result<Response, String> {
+        Err("unknown variant".to_string())
+    }
+
+    fn query(&self, _: Deps, _: Env, _: &Binary) -> Result<Binary, String> {
+        Err("unknown variant".to_string())
+    }
+
+    fn migrate(&self, _: DepsMut, _: Env, _: &Binary) -> Result<Response, String> {
+        Err("unsupported".to_string())
+    }
+
+    fn reply(&self, _: DepsMut, _: Env, _: Reply) -> Result<Response, String> {
+        Err("unsupported".to_string())
+    }
+}
+
+#[test]
+fn register_get_info() {
+    let mut setup = setup();
+    let patient = signer("alice");
+
+    let resp = setup
+        .app
+        .execute(
+            &setup.admin,
+            &setup.registry,
+            &ExecuteMsg::Register {
+                id: "Alice".to_string(),
+                address: patient.address(),
+                pubkey: patient.pubkey().to_base64(),
+                key: "password".to_string(),
+            },
+        )
+        .unwrap();
+
+    // the record manager was instantiated and the registry handled the reply
+    let actions: Vec<_> = resp
+        .events_of_type("wasm-medical_records")
+        .map(|event| event.attributes[1].value.as_str())
+        .collect();
+    assert_eq!(actions, vec!["register", "record_manager_instantiated"]);
+
+    let resp: InfoResp = setup
+        .app
+        .query(
+            &setup.registry,
+            &QueryMsg::Info {
+                id: "Alice".to_string(),
+                key: "password".to_string(),
+            },
+        )
+        .unwrap();
+    assert_eq!(resp.address, patient.address());
+    assert_eq!(resp.pubkey, patient.pubkey().to_base64());
+    assert_eq!(
+        setup.app.code_id(&resp.contract_address),
+        Some(setup.offspring.code_id)
+    );
+
+    let err = setup
+        .app
+        .query::<_, InfoResp>(
+            &setup.registry,
+            &QueryMsg::Info {
+                id: "Alice".to_string(),
+                key: "wrong".to_string(),
+            },
+        )
+        .unwrap_err();
+    assert!(matches!(err, HarnessError::Contract { .. }));
+}
+
+#[test]
+fn register_add_view() {
+    let mut setup = setup();
+    let patient = signer("alice");
+    let clinic = Addr::unchecked("clinic");
+
+    setup
+        .app
+        .execute(
+            &setup.admin,
+            &setup.registry,
+            &ExecuteMsg::AddProvider {
+                address: clinic.to_string(),
+                name: "Heart Clinic".to_string(),
+                roles: ProviderRoles {
+                    can_write_records: true,
+                    ..Default::default()
+                },
+            },
+        )
+        .unwrap();
+    let record_manager = register(&mut setup, "Alice", &patient);
+    let chain_id = setup.app.chain_id().to_string();
+
+    let add_permit = patient
+        .sign_record_permit(
+            "add",
+            &chain_id,
+            &record_manager,
+            vec![RecordPermissions::Add],
+        )
+        .unwrap();
+    let resp = setup
+        .app
+        .execute(
+            &clinic,
+            &setup.registry,
+            &ExecuteMsg::AddRecord {
+                patient_id: "Alice".to_string(),
+                record_id: "1".to_string(),
+                record: sample_record("ECG"),
+                permit: add_permit,
+            },
+        )
+        .unwrap();
+
+    // only the registry names the patient, and both contracts report the
+    // record under the same hash
+    let attribute = |key: &str| -> Vec<_> {
+        resp.events_of_type("wasm-medical_records")
+            .map(|event| {
+                event
+                    .attributes
+                    .iter()
+                    .find(|attribute| attribute.key == key)
+                    .map(|attribute| attribute.value.clone())
+            })
+            .collect()
+    };
+    let pseudonyms = attribute("patient");
+    assert_eq!(pseudonyms.len(), 2);
+    assert_eq!(pseudonyms.iter().flatten().count(), 1);
+    let record_id_hashes = attribute("record_id_hash");
+    assert!(record_id_hashes[0].is_some());
+    assert_eq!(record_id_hashes[0], record_id_hashes[1]);
+
+    let view = |app: &App, signer: &Signer| {
+        app.query::<_, RecordResp>(
+            &setup.registry,
+            &QueryMsg::WithPermit {
+                id: "Alice".to_string(),
+                permit: signer
+                    .sign_record_permit(
+                        "view",
+                        &chain_id,
+                        &record_manager,
+                        vec![RecordPermissions::ViewById {
+                            record_id: "1".to_string(),
+                        }],
+                    )
+                    .unwrap(),
+                query: QueryWithPermit::ViewById {
+                    record_id: "1".to_string(),
+                },
+            },
+        )
+    };
+
+    let record = view(&setup.app, &patient).unwrap();
+    assert_eq!(record.title, "ECG");
+    assert_eq!(record.author, clinic);
+    assert_eq!(record.version, 1);
+
+    // someone else's signature neither writes nor reads the records
+    let stranger = signer("mallory");
+    let forged_permit = stranger
+        .sign_record_permit(
+            "add",
+            &chain_id,
+            &record_manager,
+            vec![RecordPermissions::Add],
+        )
+        .unwrap();
+    let err = setup
+        .app
+        .execute(
+            &clinic,
+            &setup.registry,
+            &ExecuteMsg::AddRecord {
+                patient_id: "Alice".to_string(),
+                record_id: "2".to_string(),
+                record: sample_record("Forged"),
+                permit: forged_permit,
+            },
+        )
+        .unwrap_err();
+    assert!(matches!(err, HarnessError::Contract { address, .. } if address == record_manager));
+
+    assert!(view(&setup.app, &stranger).is_err());
+}
+
+#[test]
+fn register_with_wrong_code_hash() {
+    let mut setup = setup();
+    let patient = signer("alice");
+
+    // the registry is pointed at a code hash no stored code has
+    setup
+        .app
+        .execute(
+            &setup.admin,
+            &setup.registry,
+            &ExecuteMsg::UpdateOffspring {
+                code_id: setup.offspring.code_id,
+                code_hash: "0".repeat(64),
+            },
+        )
+        .unwrap();
+
+    let err = setup
+        .app
+        .execute(
+            &setup.admin,
+            &setup.registry,
+            &ExecuteMsg::Register {
+                id: "Alice".to_string(),
+                address: patient.address(),
+                pubkey: patient.pubkey().to_base64(),
+                key: "password".to_string(),
+            },
+        )
+        .unwrap_err();
+    assert!(matches!(err, HarnessError::Contract { address, .. } if address == setup.registry));
+
+    let err = setup
+        .app
+        .query::<_, InfoResp>(
+            &setup.registry,
+            &QueryMsg::Info {
+                id: "Alice".to_string(),
+                key: "password".to_string(),
+            },
+        )
+        .unwrap_err();
+    assert!(matches!(err, HarnessError::Contract { .. }));
+}
+
+#[test]
+fn deregister_legacy_patient() {
+    let mut app = App::new();
+    let admin = Addr::unchecked("admin");
+    let patient = signer("alice");
+
+    let registry_code = app.store_code(RegistryContract);
+    let legacy_registry_code = app.store_code(LegacyRegistryContract);
+    let legacy_offspring = app.store_code(LegacyRecordManagerContract);
+
+    // 0.1.0 instantiated record managers without an admin
+    let (record_manager, _) = app
+        .instantiate(legacy_offspring.code_id, &admin, &(), None)
+        .unwrap();
+    let (registry, _) = app
+        .instantiate(
+            legacy_registry_code.code_id,
+            &admin,
+            &(
+                OffspringInfo {
+                    code_id: legacy_offspring.code_id,
+                    code_hash: legacy_offspring.code_hash.clone(),
+                },
+                vec![(
+                    "Alice".to_string(),
+                    LegacyPerson {
+                        address: patient.address(),
+                        contract_address: record_manager.clone(),
+                        pubkey: patient.pubkey().to_base64(),
+                    },
+                )],
+            ),
+            Some(&admin),
+        )
+        .unwrap();
+    app.migrate(&admin, &registry, registry_code.code_id, &MigrateMsg {})
+        .unwrap();
+
+    // the record manager would reject `Erase`, so it is not sent at all
+    let resp = app
+        .execute(
+            &admin,
+            &registry,
+            &ExecuteMsg::Deregister {
+                patient_id: "Alice".to_string(),
+            },
+        )
+        .unwrap();
+    assert!(resp
+        .events
+        .iter()
+        .flat_map(|event| &event.attributes)
+        .all(|attribute| attribute.value != record_manager.as_str()));
+
+    let receipts: ErasureReceiptsResp = app
+        .query(
+            &registry,
+            &QueryMsg::ErasureReceipts {
+                start: None,
+                limit: None,
+            },
+        )
+        .unwrap();
+    assert_eq!(receipts.total, 1);
+    assert!(receipts.receipts[0].records_retained);
+
+    // the patient can register again with a record manager that can erase
+    let offspring = app.store_code(RecordManagerContract);
+    app.execute(
+        &admin,
+        &registry,
+        &ExecuteMsg::UpdateOffspring {
+            code_id: offspring.code_id,
+            code_hash: offspring.code_hash,
+        },
+    )
+    .unwrap();
+    app.execute(
+        &admin,
+        &registry,
+        &ExecuteMsg::Register {
+            id: "Alice".to_string(),
+            address: patient.address(),
+            pubkey: patient.pubkey().to_base64(),
+            key: "password".to_string(),
+        },
+    )
+    .unwrap();
+}
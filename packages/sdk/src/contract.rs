@@ -0,0 +1,99 @@
+use std::marker::PhantomData;
+
+use cosmwasm_std::{
+    from_slice, to_binary, to_vec, Addr, Binary, CosmosMsg, Empty, QueryRequest, StdError,
+    StdResult, WasmMsg, WasmQuery,
+};
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+
+/// Address and code hash of a deployed contract, which every message to it
+/// carries.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ContractRef {
+    pub address: Addr,
+    pub code_hash: String,
+}
+
+impl ContractRef {
+    pub fn new(address: Addr, code_hash: String) -> Self {
+        ContractRef { address, code_hash }
+    }
+}
+
+/// Execute message addressed to a contract.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Execute<M> {
+    pub contract: ContractRef,
+    pub msg: M,
+}
+
+impl<M: Serialize> Execute<M> {
+    pub fn to_binary(&self) -> StdResult<Binary> {
+        to_binary(&self.msg)
+    }
+
+    /// The message as the JSON a wallet or `secretcli` sends.
+    pub fn to_json(&self) -> StdResult<String> {
+        json(&self.msg)
+    }
+
+    pub fn into_cosmos_msg(self) -> StdResult<CosmosMsg> {
+        Ok(WasmMsg::Execute {
+            contract_addr: self.contract.address.into_string(),
+            code_hash: self.contract.code_hash,
+            msg: to_binary(&self.msg)?,
+            funds: vec![],
+        }
+        .into())
+    }
+}
+
+/// Query addressed to a contract, which knows the type it is answered with.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Query<M, R> {
+    pub contract: ContractRef,
+    pub msg: M,
+    resp: PhantomData<fn() -> R>,
+}
+
+impl<M: Serialize, R: DeserializeOwned> Query<M, R> {
+    pub fn new(contract: ContractRef, msg: M) -> Self {
+        Query {
+            contract,
+            msg,
+            resp: PhantomData,
+        }
+    }
+
+    pub fn to_binary(&self) -> StdResult<Binary> {
+        to_binary(&self.msg)
+    }
+
+    /// The query as the JSON a wallet or `secretcli` sends.
+    pub fn to_json(&self) -> StdResult<String> {
+        json(&self.msg)
+    }
+
+    pub fn into_request(self) -> StdResult<QueryRequest<Empty>> {
+        Ok(QueryRequest::Wasm(WasmQuery::Smart {
+            contract_addr: self.contract.address.into_string(),
+            code_hash: self.contract.code_hash,
+            msg: to_binary(&self.msg)?,
+        }))
+    }
+
+    /// Decodes the JSON the contract answered the query with.
+    pub fn decode(&self, resp: &[u8]) -> StdResult<R> {
+        decode(resp)
+    }
+}
+
+/// Decodes a contract response, or the data an execute message returns.
+pub fn decode<R: DeserializeOwned>(resp: &[u8]) -> StdResult<R> {
+    from_slice(resp)
+}
+
+fn json<M: Serialize>(msg: &M) -> StdResult<String> {
+    String::from_utf8(to_vec(msg)?).map_err(StdError::from)
+}
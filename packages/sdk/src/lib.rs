@@ -0,0 +1,19 @@
+//! Typed client for the registry and record-manager contracts.
+//!
+//! [`Registry`] and [`RecordManager`] build every message a client sends to
+//! either contract, as JSON or as a `CosmosMsg`. Each query knows its response
+//! type and decodes the contract's answer into it. [`Signer`] signs permits
+//! offline with a secp256k1 key, the way a wallet does.
+
+pub mod contract;
+pub mod record_manager;
+pub mod registry;
+pub mod signer;
+
+pub use contract::{decode, ContractRef, Execute, Query};
+pub use record_manager::RecordManager;
+pub use registry::Registry;
+pub use signer::Signer;
+
+#[cfg(test)]
+mod tests;
@@ -0,0 +1,213 @@
+//! Messages of a patient's record manager, and a client building those a
+//! patient or reader sends it directly.
+//!
+//! Adding and amending records, emergency unlocks and the consent queries
+//! across patients are accepted from the registry only, which builds them
+//! itself; send those through `Registry` instead.
+
+use cosmwasm_std::{Addr, Binary, Timestamp};
+use secret_toolkit::permit::Permit;
+use serde::de::DeserializeOwned;
+
+pub use ::record_manager::attachment::Attachment;
+pub use ::record_manager::consent::{Consent, ConsentPurpose};
+pub use ::record_manager::envelope::{EncryptedEnvelope, EnvelopeAlgorithm, EnvelopeRecipient};
+pub use ::record_manager::msg::{
+    AccessLogResp, CheckConsentResp, ConsentsResp, EmergencyAccessLogResp, ExecuteMsg,
+    ListByTimeRangeResp, ListRecordsResp, QueryMsg, RecordHistoryResp, RecordPermissions,
+    RecordSummary, VerifyAttachmentResp,
+};
+pub use ::record_manager::observation::{
+    ChestPainType, CvdObservation, RestEcg, Sex, StSlope, Thal,
+};
+pub use ::record_manager::state::{
+    AccessAction, AccessEvent, Dispute, EmergencyAccess, Record, RecordCategory,
+};
+
+use crate::contract::{ContractRef, Execute, Query};
+
+/// Builds the messages of a deployed record manager. Queries carry the type
+/// of their response, which `Query::decode` reads.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RecordManager {
+    contract: ContractRef,
+}
+
+impl RecordManager {
+    /// The address is the `contract_address` of the patient's `InfoResp`.
+    pub fn new(address: Addr, code_hash: String) -> Self {
+        RecordManager {
+            contract: ContractRef::new(address, code_hash),
+        }
+    }
+
+    pub fn contract(&self) -> &ContractRef {
+        &self.contract
+    }
+
+    pub fn revoke_permit(&self, permit_name: String) -> Execute<ExecuteMsg> {
+        self.execute(ExecuteMsg::RevokePermit { permit_name })
+    }
+
+    pub fn grant_access(
+        &self,
+        grantee: Addr,
+        record_ids: Option<Vec<String>>,
+        expires: Option<Timestamp>,
+    ) -> Execute<ExecuteMsg> {
+        self.execute(ExecuteMsg::GrantAccess {
+            grantee,
+            record_ids,
+            expires,
+        })
+    }
+
+    pub fn revoke_access(&self, grantee: Addr) -> Execute<ExecuteMsg> {
+        self.execute(ExecuteMsg::RevokeAccess { grantee })
+    }
+
+    pub fn grant_consent(
+        &self,
+        purpose: ConsentPurpose,
+        expires: Timestamp,
+    ) -> Execute<ExecuteMsg> {
+        self.execute(ExecuteMsg::GrantConsent { purpose, expires })
+    }
+
+    pub fn withdraw_consent(&self, purpose: ConsentPurpose) -> Execute<ExecuteMsg> {
+        self.execute(ExecuteMsg::WithdrawConsent { purpose })
+    }
+
+    pub fn erase(&self) -> Execute<ExecuteMsg> {
+        self.execute(ExecuteMsg::Erase {})
+    }
+
+    pub fn log_view(
+        &self,
+        record_id: String,
+        permit: Permit<RecordPermissions>,
+    ) -> Execute<ExecuteMsg> {
+        self.execute(ExecuteMsg::LogView { record_id, permit })
+    }
+
+    pub fn dispute_emergency_access(&self, index: u32, reason: String) -> Execute<ExecuteMsg> {
+        self.execute(ExecuteMsg::DisputeEmergencyAccess { index, reason })
+    }
+
+    pub fn view_by_id(
+        &self,
+        permit: Permit<RecordPermissions>,
+        record_id: String,
+    ) -> Query<QueryMsg, Record> {
+        self.query(QueryMsg::ViewById { permit, record_id })
+    }
+
+    pub fn list_records(
+        &self,
+        permit: Permit<RecordPermissions>,
+        start_after: Option<String>,
+        limit: Option<u32>,
+    ) -> Query<QueryMsg, ListRecordsResp> {
+        self.query(QueryMsg::ListRecords {
+            permit,
+            start_after,
+            limit,
+        })
+    }
+
+    pub fn view_history(
+        &self,
+        permit: Permit<RecordPermissions>,
+        record_id: String,
+    ) -> Query<QueryMsg, RecordHistoryResp> {
+        self.query(QueryMsg::ViewHistory { permit, record_id })
+    }
+
+    pub fn list_by_category(
+        &self,
+        permit: Permit<RecordPermissions>,
+        category: RecordCategory,
+        start_after: Option<String>,
+        limit: Option<u32>,
+    ) -> Query<QueryMsg, ListRecordsResp> {
+        self.query(QueryMsg::ListByCategory {
+            permit,
+            category,
+            start_after,
+            limit,
+        })
+    }
+
+    pub fn list_by_time_range(
+        &self,
+        permit: Permit<RecordPermissions>,
+        from: Timestamp,
+        to: Timestamp,
+        start_after: Option<u32>,
+        limit: Option<u32>,
+    ) -> Query<QueryMsg, ListByTimeRangeResp> {
+        self.query(QueryMsg::ListByTimeRange {
+            permit,
+            from,
+            to,
+            start_after,
+            limit,
+        })
+    }
+
+    pub fn verify_attachment(
+        &self,
+        permit: Permit<RecordPermissions>,
+        record_id: String,
+        uri: String,
+        sha256: Binary,
+    ) -> Query<QueryMsg, VerifyAttachmentResp> {
+        self.query(QueryMsg::VerifyAttachment {
+            permit,
+            record_id,
+            uri,
+            sha256,
+        })
+    }
+
+    pub fn access_log(
+        &self,
+        permit: Permit<RecordPermissions>,
+        start: Option<u32>,
+        limit: Option<u32>,
+    ) -> Query<QueryMsg, AccessLogResp> {
+        self.query(QueryMsg::AccessLog {
+            permit,
+            start,
+            limit,
+        })
+    }
+
+    pub fn consents(&self, permit: Permit<RecordPermissions>) -> Query<QueryMsg, ConsentsResp> {
+        self.query(QueryMsg::Consents { permit })
+    }
+
+    pub fn emergency_access_log(
+        &self,
+        permit: Permit<RecordPermissions>,
+        start: Option<u32>,
+        limit: Option<u32>,
+    ) -> Query<QueryMsg, EmergencyAccessLogResp> {
+        self.query(QueryMsg::EmergencyAccessLog {
+            permit,
+            start,
+            limit,
+        })
+    }
+
+    fn execute(&self, msg: ExecuteMsg) -> Execute<ExecuteMsg> {
+        Execute {
+            contract: self.contract.clone(),
+            msg,
+        }
+    }
+
+    fn query<R: DeserializeOwned>(&self, msg: QueryMsg) -> Query<QueryMsg, R> {
+        Query::new(self.contract.clone(), msg)
+    }
+}
@@ -0,0 +1,537 @@
+//! Messages of the registry, and a client building them.
+
+use cosmwasm_std::{Addr, Binary, Decimal, Timestamp};
+use secret_toolkit::permit::Permit;
+use serde::de::DeserializeOwned;
+
+pub use ::registry::attachment::Attachment;
+pub use ::registry::envelope::{key_id, EncryptedEnvelope, EnvelopeAlgorithm, EnvelopeRecipient};
+pub use ::registry::msg::{
+    AccessAction, AccessEvent, AccessLogResp, AggregateStatsResp, CheckConsentResp, Consent,
+    ConsentPurpose, ConsentsResp, CreateViewingKeyResp, Dispute, EmergencyAccess,
+    EmergencyAccessLogResp, ErasureReceiptsResp, ExecuteMsg, InfoResp, InstantiateMsg,
+    ListByTimeRangeResp, ListRecordsResp, MigrateMsg, MigrateRecordManagersResp, ModelsResp,
+    OwnerResp, PrivacyBudgetResp, ProviderResp, ProvidersResp, QueryMsg, QueryWithPermit, Record,
+    RecordCategory, RecordHistoryResp, RecordPermissions, RecordResp, RecordSummary,
+    RegistrationResp, ResearchExportResp, ResearchRow, RiskResp, VerifyAttachmentResp,
+};
+pub use ::registry::observation::{ChestPainType, CvdObservation, RestEcg, Sex, StSlope, Thal};
+pub use ::registry::privacy::{FeatureRange, Statistic};
+pub use ::registry::risk::{Feature, RiskModel};
+pub use ::registry::state::{
+    ErasureReceipt, ErasureRequester, ModelMetric, ModelStatus, ModelVersion, ProviderRoles,
+};
+
+use crate::contract::{ContractRef, Execute, Query};
+
+/// Builds the messages of a deployed registry. Queries carry the type of
+/// their response, which `Query::decode` reads.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Registry {
+    contract: ContractRef,
+}
+
+impl Registry {
+    pub fn new(address: Addr, code_hash: String) -> Self {
+        Registry {
+            contract: ContractRef::new(address, code_hash),
+        }
+    }
+
+    pub fn contract(&self) -> &ContractRef {
+        &self.contract
+    }
+
+    /// `pubkey` is the base64 of the patient's compressed public key, see
+    /// `Signer::pubkey`.
+    pub fn register(
+        &self,
+        id: String,
+        address: Addr,
+        pubkey: String,
+        key: String,
+    ) -> Execute<ExecuteMsg> {
+        self.execute(ExecuteMsg::Register {
+            id,
+            address,
+            pubkey,
+            key,
+        })
+    }
+
+    /// `permit` is the patient's, granting `RecordPermissions::Add`.
+    pub fn add_record(
+        &self,
+        patient_id: String,
+        record_id: String,
+        record: Record,
+        permit: Permit<RecordPermissions>,
+    ) -> Execute<ExecuteMsg> {
+        self.execute(ExecuteMsg::AddRecord {
+            patient_id,
+            record_id,
+            record,
+            permit,
+        })
+    }
+
+    /// `permit` is the patient's, granting `RecordPermissions::Amend` for
+    /// `record_id`.
+    pub fn amend_record(
+        &self,
+        patient_id: String,
+        record_id: String,
+        record: Record,
+        reason: String,
+        permit: Permit<RecordPermissions>,
+    ) -> Execute<ExecuteMsg> {
+        self.execute(ExecuteMsg::AmendRecord {
+            patient_id,
+            record_id,
+            record,
+            reason,
+            permit,
+        })
+    }
+
+    pub fn revoke_permit(&self, patient_id: String, permit_name: String) -> Execute<ExecuteMsg> {
+        self.execute(ExecuteMsg::RevokePermit {
+            patient_id,
+            permit_name,
+        })
+    }
+
+    pub fn grant_access(
+        &self,
+        patient_id: String,
+        grantee: Addr,
+        record_ids: Option<Vec<String>>,
+        expires: Option<Timestamp>,
+    ) -> Execute<ExecuteMsg> {
+        self.execute(ExecuteMsg::GrantAccess {
+            patient_id,
+            grantee,
+            record_ids,
+            expires,
+        })
+    }
+
+    pub fn revoke_access(&self, patient_id: String, grantee: Addr) -> Execute<ExecuteMsg> {
+        self.execute(ExecuteMsg::RevokeAccess {
+            patient_id,
+            grantee,
+        })
+    }
+
+    pub fn propose_new_owner(&self, address: Addr) -> Execute<ExecuteMsg> {
+        self.execute(ExecuteMsg::ProposeNewOwner { address })
+    }
+
+    pub fn accept_ownership(&self) -> Execute<ExecuteMsg> {
+        self.execute(ExecuteMsg::AcceptOwnership {})
+    }
+
+    pub fn cancel_ownership_proposal(&self) -> Execute<ExecuteMsg> {
+        self.execute(ExecuteMsg::CancelOwnershipProposal {})
+    }
+
+    pub fn add_provider(
+        &self,
+        address: String,
+        name: String,
+        roles: ProviderRoles,
+    ) -> Execute<ExecuteMsg> {
+        self.execute(ExecuteMsg::AddProvider {
+            address,
+            name,
+            roles,
+        })
+    }
+
+    pub fn update_provider(
+        &self,
+        address: String,
+        name: String,
+        roles: ProviderRoles,
+    ) -> Execute<ExecuteMsg> {
+        self.execute(ExecuteMsg::UpdateProvider {
+            address,
+            name,
+            roles,
+        })
+    }
+
+    pub fn remove_provider(&self, address: Addr) -> Execute<ExecuteMsg> {
+        self.execute(ExecuteMsg::RemoveProvider { address })
+    }
+
+    pub fn update_offspring(&self, code_id: u64, code_hash: String) -> Execute<ExecuteMsg> {
+        self.execute(ExecuteMsg::UpdateOffspring { code_id, code_hash })
+    }
+
+    /// Returns `MigrateRecordManagersResp` in the response data.
+    pub fn migrate_record_managers(
+        &self,
+        start_after: Option<String>,
+        limit: Option<u32>,
+    ) -> Execute<ExecuteMsg> {
+        self.execute(ExecuteMsg::MigrateRecordManagers { start_after, limit })
+    }
+
+    pub fn grant_consent(
+        &self,
+        patient_id: String,
+        purpose: ConsentPurpose,
+        expires: Timestamp,
+    ) -> Execute<ExecuteMsg> {
+        self.execute(ExecuteMsg::GrantConsent {
+            patient_id,
+            purpose,
+            expires,
+        })
+    }
+
+    pub fn withdraw_consent(
+        &self,
+        patient_id: String,
+        purpose: ConsentPurpose,
+    ) -> Execute<ExecuteMsg> {
+        self.execute(ExecuteMsg::WithdrawConsent {
+            patient_id,
+            purpose,
+        })
+    }
+
+    pub fn deregister(&self, patient_id: String) -> Execute<ExecuteMsg> {
+        self.execute(ExecuteMsg::Deregister { patient_id })
+    }
+
+    /// Returns `CreateViewingKeyResp` in the response data.
+    pub fn create_viewing_key(&self, entropy: String) -> Execute<ExecuteMsg> {
+        self.execute(ExecuteMsg::CreateViewingKey { entropy })
+    }
+
+    pub fn set_viewing_key(&self, key: String) -> Execute<ExecuteMsg> {
+        self.execute(ExecuteMsg::SetViewingKey { key })
+    }
+
+    pub fn log_view(
+        &self,
+        patient_id: String,
+        record_id: String,
+        permit: Permit<RecordPermissions>,
+    ) -> Execute<ExecuteMsg> {
+        self.execute(ExecuteMsg::LogView {
+            patient_id,
+            record_id,
+            permit,
+        })
+    }
+
+    pub fn register_model(
+        &self,
+        version: String,
+        model: RiskModel,
+        weights_hash: String,
+        dataset_hash: String,
+        metrics: Vec<ModelMetric>,
+    ) -> Execute<ExecuteMsg> {
+        self.execute(ExecuteMsg::RegisterModel {
+            version,
+            model,
+            weights_hash,
+            dataset_hash,
+            metrics,
+        })
+    }
+
+    pub fn activate_model(&self, version: String) -> Execute<ExecuteMsg> {
+        self.execute(ExecuteMsg::ActivateModel { version })
+    }
+
+    pub fn retire_model(&self, version: String) -> Execute<ExecuteMsg> {
+        self.execute(ExecuteMsg::RetireModel { version })
+    }
+
+    pub fn set_privacy_budget(&self, account: Addr, epsilon: Decimal) -> Execute<ExecuteMsg> {
+        self.execute(ExecuteMsg::SetPrivacyBudget { account, epsilon })
+    }
+
+    /// Returns `AggregateStatsResp` in the response data.
+    pub fn aggregate_stats(
+        &self,
+        purpose: ConsentPurpose,
+        statistic: Statistic,
+        cohort: Option<FeatureRange>,
+        epsilon: Decimal,
+    ) -> Execute<ExecuteMsg> {
+        self.execute(ExecuteMsg::AggregateStats {
+            purpose,
+            statistic,
+            cohort,
+            epsilon,
+        })
+    }
+
+    pub fn break_glass(&self, patient_id: String, justification: String) -> Execute<ExecuteMsg> {
+        self.execute(ExecuteMsg::BreakGlass {
+            patient_id,
+            justification,
+        })
+    }
+
+    pub fn dispute_emergency_access(
+        &self,
+        patient_id: String,
+        index: u32,
+        reason: String,
+    ) -> Execute<ExecuteMsg> {
+        self.execute(ExecuteMsg::DisputeEmergencyAccess {
+            patient_id,
+            index,
+            reason,
+        })
+    }
+
+    pub fn info(&self, id: String, key: String) -> Query<QueryMsg, InfoResp> {
+        self.query(QueryMsg::Info { id, key })
+    }
+
+    pub fn view_by_id(
+        &self,
+        id: String,
+        permit: Permit<RecordPermissions>,
+        record_id: String,
+    ) -> Query<QueryMsg, RecordResp> {
+        self.with_permit(id, permit, QueryWithPermit::ViewById { record_id })
+    }
+
+    pub fn list_records(
+        &self,
+        id: String,
+        permit: Permit<RecordPermissions>,
+        start_after: Option<String>,
+        limit: Option<u32>,
+    ) -> Query<QueryMsg, ListRecordsResp> {
+        self.with_permit(
+            id,
+            permit,
+            QueryWithPermit::ListRecords { start_after, limit },
+        )
+    }
+
+    pub fn view_history(
+        &self,
+        id: String,
+        permit: Permit<RecordPermissions>,
+        record_id: String,
+    ) -> Query<QueryMsg, RecordHistoryResp> {
+        self.with_permit(id, permit, QueryWithPermit::ViewHistory { record_id })
+    }
+
+    pub fn list_by_category(
+        &self,
+        id: String,
+        permit: Permit<RecordPermissions>,
+        category: RecordCategory,
+        start_after: Option<String>,
+        limit: Option<u32>,
+    ) -> Query<QueryMsg, ListRecordsResp> {
+        self.with_permit(
+            id,
+            permit,
+            QueryWithPermit::ListByCategory {
+                category,
+                start_after,
+                limit,
+            },
+        )
+    }
+
+    pub fn list_by_time_range(
+        &self,
+        id: String,
+        permit: Permit<RecordPermissions>,
+        from: Timestamp,
+        to: Timestamp,
+        start_after: Option<u32>,
+        limit: Option<u32>,
+    ) -> Query<QueryMsg, ListByTimeRangeResp> {
+        self.with_permit(
+            id,
+            permit,
+            QueryWithPermit::ListByTimeRange {
+                from,
+                to,
+                start_after,
+                limit,
+            },
+        )
+    }
+
+    pub fn verify_attachment(
+        &self,
+        id: String,
+        permit: Permit<RecordPermissions>,
+        record_id: String,
+        uri: String,
+        sha256: Binary,
+    ) -> Query<QueryMsg, VerifyAttachmentResp> {
+        self.with_permit(
+            id,
+            permit,
+            QueryWithPermit::VerifyAttachment {
+                record_id,
+                uri,
+                sha256,
+            },
+        )
+    }
+
+    pub fn predict_risk(
+        &self,
+        id: String,
+        permit: Permit<RecordPermissions>,
+        record_id: String,
+    ) -> Query<QueryMsg, RiskResp> {
+        self.with_permit(id, permit, QueryWithPermit::PredictRisk { record_id })
+    }
+
+    pub fn access_log(
+        &self,
+        id: String,
+        permit: Permit<RecordPermissions>,
+        start: Option<u32>,
+        limit: Option<u32>,
+    ) -> Query<QueryMsg, AccessLogResp> {
+        self.with_permit(id, permit, QueryWithPermit::AccessLog { start, limit })
+    }
+
+    pub fn emergency_access_log(
+        &self,
+        id: String,
+        permit: Permit<RecordPermissions>,
+        start: Option<u32>,
+        limit: Option<u32>,
+    ) -> Query<QueryMsg, EmergencyAccessLogResp> {
+        self.with_permit(
+            id,
+            permit,
+            QueryWithPermit::EmergencyAccessLog { start, limit },
+        )
+    }
+
+    pub fn consents(
+        &self,
+        id: String,
+        permit: Permit<RecordPermissions>,
+    ) -> Query<QueryMsg, ConsentsResp> {
+        self.with_permit(id, permit, QueryWithPermit::Consents {})
+    }
+
+    /// The registry answers it with an empty string.
+    pub fn add(&self, id: String, permit: Permit<RecordPermissions>) -> Query<QueryMsg, String> {
+        self.with_permit(id, permit, QueryWithPermit::Add)
+    }
+
+    /// `permit` is a registry permit of the provider, see
+    /// `Signer::sign_registry_permit`.
+    pub fn check_consent(
+        &self,
+        id: String,
+        purpose: ConsentPurpose,
+        permit: Permit,
+    ) -> Query<QueryMsg, CheckConsentResp> {
+        self.query(QueryMsg::CheckConsent {
+            id,
+            purpose,
+            permit,
+        })
+    }
+
+    pub fn research_export(
+        &self,
+        purpose: ConsentPurpose,
+        permit: Permit,
+        start: Option<u32>,
+        limit: Option<u32>,
+    ) -> Query<QueryMsg, ResearchExportResp> {
+        self.query(QueryMsg::ResearchExport {
+            purpose,
+            permit,
+            start,
+            limit,
+        })
+    }
+
+    pub fn registration(&self, permit: Permit) -> Query<QueryMsg, RegistrationResp> {
+        self.query(QueryMsg::Registration { permit })
+    }
+
+    pub fn registration_by_contract(
+        &self,
+        contract_address: Addr,
+        permit: Permit,
+    ) -> Query<QueryMsg, RegistrationResp> {
+        self.query(QueryMsg::RegistrationByContract {
+            contract_address,
+            permit,
+        })
+    }
+
+    pub fn owner(&self) -> Query<QueryMsg, OwnerResp> {
+        self.query(QueryMsg::Owner {})
+    }
+
+    pub fn providers(
+        &self,
+        start_after: Option<Addr>,
+        limit: Option<u32>,
+    ) -> Query<QueryMsg, ProvidersResp> {
+        self.query(QueryMsg::Providers { start_after, limit })
+    }
+
+    pub fn erasure_receipts(
+        &self,
+        start: Option<u32>,
+        limit: Option<u32>,
+    ) -> Query<QueryMsg, ErasureReceiptsResp> {
+        self.query(QueryMsg::ErasureReceipts { start, limit })
+    }
+
+    pub fn active_model(&self) -> Query<QueryMsg, ModelVersion> {
+        self.query(QueryMsg::ActiveModel {})
+    }
+
+    pub fn privacy_budget(&self, permit: Permit) -> Query<QueryMsg, PrivacyBudgetResp> {
+        self.query(QueryMsg::PrivacyBudget { permit })
+    }
+
+    pub fn models(
+        &self,
+        start_after: Option<String>,
+        limit: Option<u32>,
+    ) -> Query<QueryMsg, ModelsResp> {
+        self.query(QueryMsg::Models { start_after, limit })
+    }
+
+    fn execute(&self, msg: ExecuteMsg) -> Execute<ExecuteMsg> {
+        Execute {
+            contract: self.contract.clone(),
+            msg,
+        }
+    }
+
+    fn query<R: DeserializeOwned>(&self, msg: QueryMsg) -> Query<QueryMsg, R> {
+        Query::new(self.contract.clone(), msg)
+    }
+
+    fn with_permit<R: DeserializeOwned>(
+        &self,
+        id: String,
+        permit: Permit<RecordPermissions>,
+        query: QueryWithPermit,
+    ) -> Query<QueryMsg, R> {
+        self.query(QueryMsg::WithPermit { id, permit, query })
+    }
+}
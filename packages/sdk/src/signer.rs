@@ -0,0 +1,111 @@
+use bech32::{ToBase32, Variant};
+use cosmwasm_std::testing::MockApi;
+use cosmwasm_std::{to_binary, Addr, Binary, StdError, StdResult};
+use secret_toolkit::crypto::secp256k1::PrivateKey;
+use secret_toolkit::permit::{
+    pubkey_to_account, Permissions, Permit, PermitParams, PermitSignature, PubKey, SignedPermit,
+    TokenPermissions,
+};
+
+const ACCOUNT_HRP: &str = "secret";
+const PUBKEY_TYPE: &str = "tendermint/PubKeySecp256k1";
+
+/// Account holding a secp256k1 key, which signs permits offline the way a
+/// wallet does.
+pub struct Signer {
+    key: PrivateKey,
+}
+
+impl Signer {
+    /// Takes the 32 byte secret key of the account.
+    pub fn from_secret_key(secret_key: &[u8]) -> StdResult<Self> {
+        let secret_key: &[u8; 32] = secret_key
+            .try_into()
+            .map_err(|_| StdError::generic_err("secret key must be 32 bytes"))?;
+        let key = PrivateKey::parse(secret_key)?;
+        Ok(Signer { key })
+    }
+
+    /// Compressed public key. Its base64 is the `pubkey` a patient registers
+    /// with.
+    pub fn pubkey(&self) -> Binary {
+        Binary::from(self.key.pubkey().serialize_compressed().as_slice())
+    }
+
+    /// Bech32 address the permit validation derives from the public key.
+    pub fn address(&self) -> Addr {
+        let account = pubkey_to_account(&self.pubkey());
+        let address =
+            bech32::encode(ACCOUNT_HRP, account.as_slice().to_base32(), Variant::Bech32).unwrap();
+        Addr::unchecked(address)
+    }
+
+    /// Signs a permit for `allowed_tokens`, which must list the exact contract
+    /// the permit is validated by.
+    pub fn sign_permit<P: Permissions>(
+        &self,
+        permit_name: &str,
+        chain_id: &str,
+        allowed_tokens: Vec<String>,
+        permissions: Vec<P>,
+    ) -> StdResult<Permit<P>> {
+        let params = PermitParams {
+            allowed_tokens,
+            permit_name: permit_name.to_string(),
+            chain_id: chain_id.to_string(),
+            permissions,
+        };
+
+        // the mock api signs natively, the same way a wallet does
+        let signed_bytes = to_binary(&SignedPermit::from_params(&params))?;
+        let signature = self.key.sign(signed_bytes.as_slice(), MockApi::default());
+
+        Ok(Permit {
+            params,
+            signature: PermitSignature {
+                pub_key: PubKey {
+                    r#type: PUBKEY_TYPE.to_string(),
+                    value: self.pubkey(),
+                },
+                signature: Binary::from(signature.serialize().as_slice()),
+            },
+        })
+    }
+
+    /// Signs a permit over the records of one patient. Record permits are
+    /// validated by the patient's record manager, whether they are sent to it
+    /// directly or through the registry.
+    ///
+    /// `P` is the `RecordPermissions` of `registry` or `record_manager`,
+    /// depending on the contract the permit is sent to.
+    pub fn sign_record_permit<P: Permissions>(
+        &self,
+        permit_name: &str,
+        chain_id: &str,
+        record_manager: &Addr,
+        permissions: Vec<P>,
+    ) -> StdResult<Permit<P>> {
+        self.sign_permit(
+            permit_name,
+            chain_id,
+            vec![record_manager.to_string()],
+            permissions,
+        )
+    }
+
+    /// Signs a permit identifying the signer to the registry, for queries
+    /// about the signer's own registration, consent or privacy budget.
+    pub fn sign_registry_permit(
+        &self,
+        permit_name: &str,
+        chain_id: &str,
+        registry: &Addr,
+    ) -> StdResult<Permit> {
+        self.sign_permit(
+            permit_name,
+            chain_id,
+            vec![registry.to_string()],
+            vec![TokenPermissions::Owner],
+        )
+    }
+}
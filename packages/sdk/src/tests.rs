@@ -0,0 +1,200 @@
+use cosmwasm_std::testing::{mock_dependencies, mock_env};
+use cosmwasm_std::{to_vec, Addr, CosmosMsg, Timestamp, WasmMsg};
+use secret_toolkit::permit::validate;
+use serde_json::{json, Value};
+
+use crate::record_manager::{self, RecordManager};
+use crate::registry::{self, InfoResp, Registry};
+use crate::Signer;
+
+const CHAIN_ID: &str = "secret-4";
+
+fn signer() -> Signer {
+    Signer::from_secret_key(&[7; 32]).unwrap()
+}
+
+fn registry_client() -> Registry {
+    Registry::new(Addr::unchecked("registry"), "registry_hash".to_string())
+}
+
+fn sample_record() -> registry::Record {
+    registry::Record {
+        title: "ECG".to_string(),
+        timestamp: None,
+        description: "Resting ECG".to_string(),
+        data: "sinus rhythm".to_string(),
+        observation: None,
+        envelope: None,
+        attachments: vec![],
+        category: Some(registry::RecordCategory::Ecg),
+    }
+}
+
+#[test]
+fn signed_permits_validate() {
+    let deps = mock_dependencies();
+    let signer = signer();
+    let record_manager = Addr::unchecked("record_manager");
+
+    let permit = signer
+        .sign_record_permit(
+            "add",
+            CHAIN_ID,
+            &record_manager,
+            vec![registry::RecordPermissions::Add],
+        )
+        .unwrap();
+    let account = validate(
+        deps.as_ref(),
+        "revoked_permits",
+        &permit,
+        record_manager.to_string(),
+        None,
+    )
+    .unwrap();
+    assert_eq!(account, signer.address().as_str());
+
+    // a permit is only valid for the contract it was signed for
+    validate(
+        deps.as_ref(),
+        "revoked_permits",
+        &permit,
+        "registry".to_string(),
+        None,
+    )
+    .unwrap_err();
+
+    // nor once its content changed
+    let mut tampered = permit;
+    tampered.params.permissions = vec![registry::RecordPermissions::ViewAll];
+    validate(
+        deps.as_ref(),
+        "revoked_permits",
+        &tampered,
+        record_manager.to_string(),
+        None,
+    )
+    .unwrap_err();
+
+    let registry_permit = signer
+        .sign_registry_permit("registration", CHAIN_ID, &Addr::unchecked("registry"))
+        .unwrap();
+    validate(
+        deps.as_ref(),
+        "revoked_permits",
+        &registry_permit,
+        "registry".to_string(),
+        None,
+    )
+    .unwrap();
+
+    assert!(Signer::from_secret_key(&[7; 31]).is_err());
+}
+
+#[test]
+fn build_execute_msgs() {
+    let signer = signer();
+    let registry = registry_client();
+    let permit = signer
+        .sign_record_permit(
+            "add",
+            CHAIN_ID,
+            &Addr::unchecked("record_manager"),
+            vec![registry::RecordPermissions::Add],
+        )
+        .unwrap();
+
+    let add = registry.add_record(
+        "Alice".to_string(),
+        "1".to_string(),
+        sample_record(),
+        permit.clone(),
+    );
+    let msg: Value = serde_json::from_str(&add.to_json().unwrap()).unwrap();
+    assert_eq!(msg["add_record"]["patient_id"], json!("Alice"));
+    assert_eq!(msg["add_record"]["record"]["category"], json!("ecg"));
+    assert_eq!(
+        msg["add_record"]["permit"]["params"]["allowed_tokens"],
+        json!(["record_manager"])
+    );
+    assert_eq!(
+        msg["add_record"]["permit"]["signature"]["pub_key"]["value"],
+        json!(signer.pubkey().to_base64())
+    );
+
+    match add.clone().into_cosmos_msg().unwrap() {
+        CosmosMsg::Wasm(WasmMsg::Execute {
+            contract_addr,
+            code_hash,
+            msg,
+            funds,
+        }) => {
+            assert_eq!(contract_addr, "registry");
+            assert_eq!(code_hash, "registry_hash");
+            assert_eq!(msg, add.to_binary().unwrap());
+            assert!(funds.is_empty());
+        }
+        msg => panic!("unexpected message {:?}", msg),
+    }
+
+    let record_manager = RecordManager::new(
+        Addr::unchecked("record_manager"),
+        "record_manager_hash".to_string(),
+    );
+    let grant = record_manager.grant_consent(
+        record_manager::ConsentPurpose::ModelTraining,
+        Timestamp::from_seconds(1_000),
+    );
+    assert_eq!(
+        grant.to_json().unwrap(),
+        r#"{"grant_consent":{"purpose":"model_training","expires":"1000000000000"}}"#
+    );
+}
+
+#[test]
+fn decode_query_resps() {
+    let registry = registry_client();
+
+    let info = registry.info("Alice".to_string(), "password".to_string());
+    assert_eq!(
+        info.to_json().unwrap(),
+        r#"{"info":{"id":"Alice","key":"password"}}"#
+    );
+    let resp = InfoResp {
+        address: Addr::unchecked("secret1alice"),
+        contract_address: Addr::unchecked("record_manager"),
+        pubkey: signer().pubkey().to_base64(),
+    };
+    assert_eq!(info.decode(&to_vec(&resp).unwrap()).unwrap(), resp);
+    info.decode(br#"{"records":[]}"#).unwrap_err();
+
+    let permit = signer()
+        .sign_record_permit(
+            "view",
+            CHAIN_ID,
+            &resp.contract_address,
+            vec![record_manager::RecordPermissions::ViewById {
+                record_id: "1".to_string(),
+            }],
+        )
+        .unwrap();
+    let record_manager = RecordManager::new(resp.contract_address, "hash".to_string());
+    let view = record_manager.view_by_id(permit, "1".to_string());
+    let msg: Value = serde_json::from_slice(&view.to_binary().unwrap()).unwrap();
+    assert_eq!(msg["view_by_id"]["record_id"], json!("1"));
+
+    let record = record_manager::Record {
+        title: "ECG".to_string(),
+        timestamp: mock_env().block.time,
+        description: "Resting ECG".to_string(),
+        data: "sinus rhythm".to_string(),
+        observation: None,
+        envelope: None,
+        attachments: vec![],
+        category: None,
+        version: 1,
+        author: Addr::unchecked("clinic"),
+        reason: None,
+    };
+    assert_eq!(view.decode(&to_vec(&record).unwrap()).unwrap(), record);
+}